@@ -3,9 +3,9 @@ use std::sync::Arc;
 use std::time::Instant;
 use indexmap::IndexMap;
 use tokio::sync::Mutex;
-use tauri::{AppHandle, State, Emitter};
+use tauri::{AppHandle, State, Emitter, Manager};
 
-use super::connection::{AcpConnection, ClaudeConnection};
+use super::connection::{AcpConnection, ClaudeConnection, OpenAiConnection, PromptOptions};
 use super::types::*;
 
 // ── AnyConnection — wraps both provider connection types ────────────────────
@@ -67,6 +67,7 @@ impl Default for AcpState {
 pub enum AnySessionConnection {
     Copilot(Arc<AcpConnection>),
     Claude(Arc<ClaudeConnection>),
+    OpenAi(Arc<OpenAiConnection>),
 }
 
 impl AnySessionConnection {
@@ -74,24 +75,28 @@ impl AnySessionConnection {
         match self {
             Self::Copilot(c) => c.shutdown().await,
             Self::Claude(c) => c.shutdown().await,
+            Self::OpenAi(c) => c.shutdown().await,
         }
     }
     pub async fn is_alive(&self) -> bool {
         match self {
             Self::Copilot(c) => c.is_alive().await,
             Self::Claude(c) => c.is_alive().await,
+            Self::OpenAi(c) => c.is_alive().await,
         }
     }
     pub fn emit_status(&self, status: &str, attempt: Option<u32>) {
         match self {
             Self::Copilot(c) => c.emit_status(status, attempt),
             Self::Claude(c) => c.emit_status(status, attempt),
+            Self::OpenAi(c) => c.emit_status(status, attempt),
         }
     }
     pub fn emit_log(&self, level: &str, event: &str, message: &str) {
         match self {
             Self::Copilot(c) => c.emit_log(level, event, message),
             Self::Claude(c) => c.emit_log(level, event, message),
+            Self::OpenAi(c) => c.emit_log(level, event, message),
         }
     }
 }
@@ -134,6 +139,8 @@ pub struct SessionConnectionConfig {
     pub model: Option<String>,
     pub skip_permissions: bool,
     pub max_budget_usd: Option<String>,
+    /// OpenAI-compatible provider only
+    pub base_url: Option<String>,
 }
 
 
@@ -250,6 +257,10 @@ pub async fn acp_connect(
             state.configs.lock().await.insert(workspace_id.clone(), config);
             AnyConnection::Claude(conn)
         }
+
+        Provider::OpenAi => {
+            return Err("The OpenAI-compatible provider is only available for per-session connections".to_string());
+        }
     };
 
     state.connections.lock().await.insert(workspace_id, conn);
@@ -428,7 +439,7 @@ pub async fn acp_send_prompt(
             .await?;
         }
         AnyConnection::Claude(c) => {
-            c.send_prompt(&text, std::time::Duration::from_secs(600)).await?;
+            c.send_prompt(&text, &PromptOptions::default(), std::time::Duration::from_secs(600)).await?;
         }
     }
     Ok(())
@@ -581,13 +592,53 @@ pub async fn acp_session_connect(
     skip_permissions: Option<bool>,
     max_budget_usd: Option<String>,
     acp_session_id: Option<String>,
+    base_url: Option<String>,
+    api_key: Option<String>,
     app_handle: AppHandle,
     store: State<'_, AcpSessionStore>,
+) -> Result<String, String> {
+    connect_session(
+        &store,
+        &app_handle,
+        session_id,
+        workspace_path,
+        provider,
+        binary_path,
+        gh_token,
+        model,
+        skip_permissions,
+        max_budget_usd,
+        acp_session_id,
+        base_url,
+        api_key,
+    )
+    .await
+}
+
+/// Store-level implementation of `acp_session_connect`, shared with backend
+/// flows (e.g. comparisons) that connect sessions without a frontend call.
+pub async fn connect_session(
+    store: &AcpSessionStore,
+    app_handle: &AppHandle,
+    session_id: String,
+    workspace_path: String,
+    provider: Option<String>,
+    binary_path: Option<String>,
+    gh_token: Option<String>,
+    model: Option<String>,
+    skip_permissions: Option<bool>,
+    max_budget_usd: Option<String>,
+    acp_session_id: Option<String>,
+    base_url: Option<String>,
+    api_key: Option<String>,
 ) -> Result<String, String> {
     let resolved_provider = match provider.as_deref() {
         Some("claude") => Provider::Claude,
+        Some("openai") => Provider::OpenAi,
         _ => Provider::Copilot,
     };
+    // Sessions isolated in a git worktree run their agent inside it
+    let workspace_path = crate::worktree::session_cwd(app_handle, &session_id, workspace_path);
     eprintln!("[acp] acp_session_connect: session={} workspace={} provider={:?}", session_id, workspace_path, resolved_provider);
 
     // Return early if already alive
@@ -610,7 +661,7 @@ pub async fn acp_session_connect(
         }
     }
 
-    emit_session_status(&app_handle, &session_id, "connecting");
+    emit_session_status(app_handle, &session_id, "connecting");
 
     // Enforce cap: evict oldest if at limit
     let evicted = {
@@ -623,7 +674,7 @@ pub async fn acp_session_connect(
         } else { None }
     };
     if let Some((evict_key, old)) = evicted {
-        emit_session_status(&app_handle, &evict_key, "disconnected");
+        emit_session_status(app_handle, &evict_key, "disconnected");
         eprintln!("[acp] Evicting session {} (cap)", evict_key);
         old.connection.shutdown().await;
         store.configs.lock().await.remove(&evict_key);
@@ -766,6 +817,27 @@ pub async fn acp_session_connect(
 
             (AnySessionConnection::Claude(Arc::new(conn)), sid)
         }
+        Provider::OpenAi => {
+            let url = base_url.clone()
+                .filter(|s| !s.trim().is_empty())
+                .unwrap_or_else(|| std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:8080/v1".to_string()));
+            let key = api_key.or_else(|| std::env::var("OPENAI_API_KEY").ok());
+
+            let conn = OpenAiConnection::connect(
+                &url,
+                key,
+                model.as_deref(),
+                session_id.clone(),
+                app_handle.clone(),
+            )
+            .await?;
+            let sid = conn.session_id();
+
+            conn.emit_status("connected", None);
+            conn.emit_log("info", "connect", &format!("Chat session connected via {}", url));
+
+            (AnySessionConnection::OpenAi(Arc::new(conn)), sid)
+        }
     };
 
     eprintln!("[acp] session={} connected — provider_session_id={}", session_id, provider_session_id);
@@ -787,6 +859,7 @@ pub async fn acp_session_connect(
         model,
         skip_permissions: skip_permissions.unwrap_or(false),
         max_budget_usd,
+        base_url,
     });
 
     Ok(provider_session_id)
@@ -799,14 +872,20 @@ pub async fn acp_session_disconnect(
     store: State<'_, AcpSessionStore>,
 ) -> Result<(), String> {
     eprintln!("[acp] acp_session_disconnect: session={}", session_id);
-    store.configs.lock().await.remove(&session_id);
-    let inst = store.instances.lock().await.shift_remove(&session_id);
+    disconnect_session(&store, &app_handle, &session_id, "Disconnected by user").await;
+    Ok(())
+}
+
+/// Store-level implementation of `acp_session_disconnect`; `reason` is logged
+/// to the session's connection log.
+pub async fn disconnect_session(store: &AcpSessionStore, app_handle: &AppHandle, session_id: &str, reason: &str) {
+    store.configs.lock().await.remove(session_id);
+    let inst = store.instances.lock().await.shift_remove(session_id);
     if let Some(inst) = inst {
-        inst.connection.emit_log("info", "disconnect", "Disconnected by user");
+        inst.connection.emit_log("info", "disconnect", reason);
         inst.connection.shutdown().await;
     }
-    emit_session_status(&app_handle, &session_id, "disconnected");
-    Ok(())
+    emit_session_status(app_handle, session_id, "disconnected");
 }
 
 #[tauri::command]
@@ -839,8 +918,21 @@ pub async fn acp_session_status(
 pub async fn acp_session_send_prompt(
     session_id: String,
     text: String,
+    client_message_id: Option<String>,
     app_handle: AppHandle,
     store: State<'_, AcpSessionStore>,
+) -> Result<(), String> {
+    let options = PromptOptions { client_message_id, ..Default::default() };
+    send_session_prompt(&store, &app_handle, session_id, text, options).await
+}
+
+/// Store-level implementation of `acp_session_send_prompt`.
+pub async fn send_session_prompt(
+    store: &AcpSessionStore,
+    app_handle: &AppHandle,
+    session_id: String,
+    text: String,
+    mut options: PromptOptions,
 ) -> Result<(), String> {
     eprintln!("[acp] acp_session_send_prompt: session={} text={:.60}", session_id, text);
 
@@ -851,9 +943,13 @@ pub async fn acp_session_send_prompt(
         (inst.provider.clone(), inst.acp_session_id.clone())
     };
 
-    emit_session_status(&app_handle, &session_id, "streaming");
+    emit_session_status(app_handle, &session_id, "streaming");
 
     let timeout = std::time::Duration::from_secs(600);
+    // Forked sessions carry their replayed history into the agent's first prompt
+    if provider != Provider::OpenAi && options.context.is_none() {
+        options.context = take_context_seed(app_handle, &session_id);
+    }
 
     match provider {
         Provider::Copilot => {
@@ -865,7 +961,8 @@ pub async fn acp_session_send_prompt(
                     _ => return Err("Provider mismatch".to_string()),
                 }
             };
-            let result = conn.send_prompt(acp_id, text, timeout).await?;
+            let result = conn.send_prompt(acp_id, text, &options, timeout).await
+                .inspect_err(|_| restore_context_seed(app_handle, &session_id, options.context.as_deref()))?;
             eprintln!("[acp] session={} prompt result: {}", session_id, serde_json::to_string(&result).unwrap_or_default().chars().take(500).collect::<String>());
         }
         Provider::Claude => {
@@ -877,13 +974,44 @@ pub async fn acp_session_send_prompt(
                     _ => return Err("Provider mismatch".to_string()),
                 }
             };
-            conn.send_prompt(&text, timeout).await?;
+            conn.send_prompt(&text, &options, timeout).await
+                .inspect_err(|_| restore_context_seed(app_handle, &session_id, options.context.as_deref()))?;
+        }
+        Provider::OpenAi => {
+            let conn = {
+                let instances = store.instances.lock().await;
+                let inst = instances.get(&session_id).ok_or("Session not connected")?;
+                match &inst.connection {
+                    AnySessionConnection::OpenAi(c) => Arc::clone(c),
+                    _ => return Err("Provider mismatch".to_string()),
+                }
+            };
+            conn.send_prompt(&text, &options, timeout).await?;
         }
     }
 
     Ok(())
 }
 
+fn take_context_seed(app_handle: &AppHandle, session_id: &str) -> Option<String> {
+    let db = app_handle.try_state::<crate::comments::CommentsDb>()?;
+    let conn = db.0.lock().ok()?;
+    crate::sessions::take_context_seed(&conn, session_id).unwrap_or_else(|e| {
+        eprintln!("[acp] session={} context seed error: {}", session_id, e);
+        None
+    })
+}
+
+/// Puts a seed back when its prompt failed so the next attempt still carries it.
+fn restore_context_seed(app_handle: &AppHandle, session_id: &str, seed: Option<&str>) {
+    let Some(seed) = seed else { return };
+    if let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() {
+        if let Ok(conn) = db.0.lock() {
+            let _ = crate::sessions::set_context_seed(&conn, session_id, Some(seed));
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn acp_session_set_mode(
     session_id: String,
@@ -909,8 +1037,8 @@ pub async fn acp_session_set_mode(
             conn.send_request("session/set_mode", Some(serde_json::to_value(&params).map_err(|e| e.to_string())?))
                 .await?;
         }
-        Provider::Claude => {
-            eprintln!("[acp] set_mode is a no-op for Claude provider");
+        Provider::Claude | Provider::OpenAi => {
+            eprintln!("[acp] set_mode is a no-op for {:?} provider", provider);
         }
     }
     Ok(())
@@ -942,8 +1070,8 @@ pub async fn acp_session_set_config_option(
             conn.send_request("session/set_config_option", Some(serde_json::to_value(&params).map_err(|e| e.to_string())?))
                 .await?;
         }
-        Provider::Claude => {
-            eprintln!("[acp] set_config_option is a no-op for Claude provider");
+        Provider::Claude | Provider::OpenAi => {
+            eprintln!("[acp] set_config_option is a no-op for {:?} provider", provider);
         }
     }
     Ok(())
@@ -976,6 +1104,12 @@ pub async fn acp_session_cancel(
         Provider::Claude => {
             eprintln!("[acp] cancel is a no-op for Claude provider");
         }
+        Provider::OpenAi => {
+            let instances = store.instances.lock().await;
+            if let Some(AnySessionConnection::OpenAi(c)) = instances.get(&session_id).map(|i| &i.connection) {
+                c.cancel();
+            }
+        }
     }
     Ok(())
 }
@@ -1042,8 +1176,8 @@ pub async fn acp_session_refresh_info(
         inst.provider.clone()
     };
 
-    if provider == Provider::Claude {
-        eprintln!("[acp] refresh_info is a no-op for Claude provider");
+    if provider != Provider::Copilot {
+        eprintln!("[acp] refresh_info is a no-op for {:?} provider", provider);
         return Ok(());
     }
 
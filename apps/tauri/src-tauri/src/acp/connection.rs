@@ -39,30 +39,201 @@ fn save_to_db(
     }
 }
 
-fn flush_buffer(
-    streaming_buffer: &mut Option<String>,
-    streaming_type: &mut Option<String>,
-    saved: &mut Vec<MessageRecord>,
+/// Stores a tool call payload (and its arrival time) on the tool message and
+/// refreshes the copy kept for the end-of-turn event.
+fn record_tool_payload(
+    saved: &mut [MessageRecord],
     workspace_id: &str,
     app_handle: &AppHandle,
+    tool_call_id: &str,
+    payload: &serde_json::Value,
 ) {
-    let content = match streaming_buffer.take() {
-        Some(c) if !c.is_empty() => c,
-        _ => {
-            *streaming_type = None;
+    if let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() {
+        if let Ok(conn) = db.0.lock() {
+            match crate::messages::record_tool_update(&conn, workspace_id, tool_call_id, payload) {
+                Ok(Some(updated)) => {
+                    if let Some(record) = saved.iter_mut().rev()
+                        .find(|r| r.tool_call_id.as_deref() == Some(tool_call_id))
+                    {
+                        *record = updated;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("[acp] record_tool_payload error: {}", e),
+            }
+        }
+    }
+}
+
+/// Per-prompt options for the connections' `send_prompt`.
+#[derive(Debug, Default, Clone)]
+pub struct PromptOptions {
+    /// Prepended to what the agent receives but not persisted (Copilot and Claude)
+    pub context: Option<String>,
+    /// Persist the user message even when it repeats the previous one, e.g.
+    /// when a rewound session resends an edited message
+    pub allow_duplicate: bool,
+    /// Client-generated message id; persistence is idempotent on it and the
+    /// text-based duplicate check is skipped
+    pub client_message_id: Option<String>,
+}
+
+impl PromptOptions {
+    fn wire_text(&self, text: &str) -> String {
+        match &self.context {
+            Some(context) => format!("{}\n\n{}", context, text),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Saves the user's prompt and notifies the frontend. Slash commands are not
+/// saved. Prompts with a client message id are saved idempotently on it; others
+/// skip repeats of the last user message unless `options.allow_duplicate` is set.
+fn save_user_prompt(app_handle: &AppHandle, workspace_id: &str, text: &str, options: &PromptOptions) {
+    if text.starts_with('/') {
+        return;
+    }
+    let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() else {
+        eprintln!("[acp] send_prompt: CommentsDb state NOT FOUND");
+        return;
+    };
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[acp] send_prompt: db lock FAILED: {}", e);
             return;
         }
     };
-    let msg_type = streaming_type.take();
-    let effective_type = match msg_type.as_deref() {
-        Some("assistant") => None,
-        _ => msg_type,
+    let saved = match &options.client_message_id {
+        Some(client_id) => crate::messages::save_user_message(&conn, workspace_id, text, client_id)
+            .map(|(record, inserted)| {
+                if !inserted {
+                    eprintln!("[acp] send_prompt: user message {} already saved", client_id);
+                }
+                record
+            }),
+        None if !options.allow_duplicate && crate::messages::is_duplicate_user_message(&conn, workspace_id, text) => {
+            eprintln!("[acp] send_prompt: skipping duplicate user message");
+            return;
+        }
+        None => crate::messages::save_message(&conn, workspace_id, "user", text, None, None, None, None),
     };
-    save_to_db(
-        saved, workspace_id, app_handle,
-        "assistant", &content,
-        effective_type.as_deref(), None, None, None,
-    );
+    match saved {
+        Ok(record) => {
+            eprintln!("[acp] send_prompt: saved user message id={}", record.id);
+            let _ = app_handle.emit("acp:user-message-saved", serde_json::json!({
+                "sessionId": workspace_id,
+                "id": record.id,
+                "content": text,
+                "clientMessageId": &options.client_message_id,
+            }));
+        }
+        Err(e) => eprintln!("[acp] send_prompt: save_message FAILED: {}", e),
+    }
+}
+
+/// How often the partial text of a streaming message is written to the database.
+const STREAM_PERSIST_INTERVAL: std::time::Duration = std::time::Duration::from_millis(750);
+
+/// Text of the assistant or thinking message currently being streamed. The
+/// partial text is upserted into one row marked `streaming` at most every
+/// `STREAM_PERSIST_INTERVAL`, so a crash mid-answer keeps what was produced.
+#[derive(Default)]
+struct StreamBuffer {
+    text: String,
+    /// "assistant" or "thinking"
+    kind: Option<String>,
+    /// Row holding the partial text, once it has been persisted
+    row_id: Option<String>,
+    persisted_at: Option<std::time::Instant>,
+}
+
+impl StreamBuffer {
+    fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    fn message_type(&self) -> Option<&str> {
+        match self.kind() {
+            Some("assistant") | None => None,
+            other => other,
+        }
+    }
+
+    /// Appends streamed text of `kind`, finishing the current message first
+    /// when the kind changes.
+    fn push(
+        &mut self,
+        kind: &str,
+        text: &str,
+        saved: &mut Vec<MessageRecord>,
+        workspace_id: &str,
+        app_handle: &AppHandle,
+    ) {
+        if self.kind().is_some_and(|k| k != kind) {
+            self.flush(saved, workspace_id, app_handle);
+        }
+        self.text.push_str(text);
+        self.kind = Some(kind.to_string());
+        if !self.text.is_empty()
+            && self.persisted_at.is_none_or(|t| t.elapsed() >= STREAM_PERSIST_INTERVAL)
+        {
+            self.persist(workspace_id, app_handle);
+        }
+    }
+
+    fn persist(&mut self, workspace_id: &str, app_handle: &AppHandle) {
+        let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() else { return };
+        let Ok(conn) = db.0.lock() else { return };
+        let result = match &self.row_id {
+            Some(id) => crate::messages::update_streaming_message(&conn, id, &self.text),
+            None => crate::messages::begin_streaming_message(&conn, workspace_id, self.message_type(), &self.text)
+                .map(|id| self.row_id = Some(id)),
+        };
+        match result {
+            Ok(()) => self.persisted_at = Some(std::time::Instant::now()),
+            Err(e) => eprintln!("[acp] stream persist error: {}", e),
+        }
+    }
+
+    /// Saves the buffered message as complete.
+    fn flush(&mut self, saved: &mut Vec<MessageRecord>, workspace_id: &str, app_handle: &AppHandle) {
+        self.finish(None, saved, workspace_id, app_handle);
+    }
+
+    /// Saves the buffered message flagged `interrupted`, for turns that ended
+    /// without the agent finishing its answer.
+    fn interrupt(&mut self, saved: &mut Vec<MessageRecord>, workspace_id: &str, app_handle: &AppHandle) {
+        self.finish(Some("interrupted"), saved, workspace_id, app_handle);
+    }
+
+    fn finish(
+        &mut self,
+        stream_state: Option<&str>,
+        saved: &mut Vec<MessageRecord>,
+        workspace_id: &str,
+        app_handle: &AppHandle,
+    ) {
+        if self.text.is_empty() {
+            *self = Self::default();
+            return;
+        }
+        if self.row_id.is_none() {
+            self.persist(workspace_id, app_handle);
+        }
+        let buffer = std::mem::take(self);
+        let Some(id) = buffer.row_id else { return };
+        let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() else { return };
+        let Ok(conn) = db.0.lock() else { return };
+        match crate::messages::finish_streaming_message(&conn, &id, &buffer.text, stream_state) {
+            Ok(record) => {
+                eprintln!("[acp] stream saved: id={} type={:?} len={} state={:?}", record.id, record.message_type, buffer.text.len(), stream_state);
+                saved.push(record);
+            }
+            Err(e) => eprintln!("[acp] stream finish error: {}", e),
+        }
+    }
 }
 
 pub struct AcpConnection {
@@ -95,6 +266,8 @@ impl AcpConnection {
             .stderr(std::process::Stdio::inherit())
             .kill_on_drop(true);
 
+        // Workspace profile first so an explicit token from settings wins
+        crate::env_profiles::apply_to_command(&app_handle, cwd, &mut cmd);
         if let Some(token) = gh_token.filter(|s| !s.trim().is_empty()) {
             cmd.env("GH_TOKEN", token.trim());
         }
@@ -170,8 +343,7 @@ impl AcpConnection {
     ) {
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
-        let mut streaming_buffer: Option<String> = None;
-        let mut streaming_type: Option<String> = None;
+        let mut stream = StreamBuffer::default();
         let mut saved_this_turn: Vec<MessageRecord> = Vec::new();
 
         while let Ok(Some(line)) = lines.next_line().await {
@@ -210,8 +382,7 @@ impl AcpConnection {
                                 params,
                                 &workspace_id,
                                 &app_handle,
-                                &mut streaming_buffer,
-                                &mut streaming_type,
+                                &mut stream,
                                 &mut saved_this_turn,
                                 &suppress_updates,
                             );
@@ -239,6 +410,15 @@ impl AcpConnection {
                 }
             }
         }
+        // The agent went away mid-turn: keep whatever it had streamed
+        stream.interrupt(&mut saved_this_turn, &workspace_id, &app_handle);
+        if !saved_this_turn.is_empty() {
+            let _ = app_handle.emit("acp:assistant-message-saved", serde_json::json!({
+                "sessionId": &workspace_id,
+                "messages": saved_this_turn,
+            }));
+        }
+
         eprintln!("[acp] Reader task ended for workspace {}", workspace_id);
         emit_log_raw(&app_handle, &workspace_id, "warn", "reader_exit", "Reader task ended — stdout closed");
         let event = ConnectionStatusEvent {
@@ -376,8 +556,7 @@ impl AcpConnection {
         params: &serde_json::Value,
         workspace_id: &str,
         app_handle: &AppHandle,
-        streaming_buffer: &mut Option<String>,
-        streaming_type: &mut Option<String>,
+        stream: &mut StreamBuffer,
         saved_this_turn: &mut Vec<MessageRecord>,
         suppress_updates: &Arc<AtomicBool>,
     ) {
@@ -408,8 +587,7 @@ impl AcpConnection {
 
         if suppressed && !is_config_update {
             if update_type == "end_turn" {
-                streaming_buffer.take();
-                *streaming_type = None;
+                *stream = StreamBuffer::default();
                 saved_this_turn.clear();
                 eprintln!("[acp] end_turn: session={} suppressed (replay drain)", workspace_id);
             }
@@ -422,12 +600,7 @@ impl AcpConnection {
                     .get("content")
                     .and_then(|c| if c.get("type").and_then(|t| t.as_str()) == Some("text") { c.get("text").and_then(|t| t.as_str()) } else { None })
                 {
-                    if *streaming_type == Some("thinking".to_string()) {
-                        flush_buffer(streaming_buffer, streaming_type, saved_this_turn, workspace_id, app_handle);
-                    }
-                    let buf = streaming_buffer.get_or_insert_with(String::new);
-                    buf.push_str(text);
-                    *streaming_type = Some("assistant".to_string());
+                    stream.push("assistant", text, saved_this_turn, workspace_id, app_handle);
                 }
             }
             "agent_thought_chunk" => {
@@ -435,16 +608,11 @@ impl AcpConnection {
                     .get("content")
                     .and_then(|c| if c.get("type").and_then(|t| t.as_str()) == Some("text") { c.get("text").and_then(|t| t.as_str()) } else { None })
                 {
-                    if *streaming_type == Some("assistant".to_string()) {
-                        flush_buffer(streaming_buffer, streaming_type, saved_this_turn, workspace_id, app_handle);
-                    }
-                    let buf = streaming_buffer.get_or_insert_with(String::new);
-                    buf.push_str(text);
-                    *streaming_type = Some("thinking".to_string());
+                    stream.push("thinking", text, saved_this_turn, workspace_id, app_handle);
                 }
             }
             "tool_call" => {
-                flush_buffer(streaming_buffer, streaming_type, saved_this_turn, workspace_id, app_handle);
+                stream.flush(saved_this_turn, workspace_id, app_handle);
                 let title = payload.get("title").and_then(|v| v.as_str()).unwrap_or("Tool call");
                 let tool_call_id = payload.get("toolCallId").and_then(|v| v.as_str());
                 let status = payload.get("status").and_then(|v| v.as_str()).unwrap_or("pending");
@@ -453,11 +621,22 @@ impl AcpConnection {
                     "assistant", "",
                     Some("tool"), tool_call_id, Some(title), Some(status),
                 );
+                if let Some(tcid) = tool_call_id {
+                    record_tool_payload(saved_this_turn, workspace_id, app_handle, tcid, &payload);
+                }
+                crate::checkpoints::on_acp_tool_call(app_handle, workspace_id, &payload);
+                crate::tool_diffs::on_tool_call_payload(app_handle, workspace_id, &payload);
+                crate::plan_progress::on_acp_tool_call(app_handle, workspace_id, &payload);
             }
             "tool_call_update" => {
+                crate::tool_diffs::on_tool_call_payload(app_handle, workspace_id, &payload);
                 let tool_call_id = payload.get("toolCallId").and_then(|v| v.as_str());
                 let status = payload.get("status").and_then(|v| v.as_str());
                 if let (Some(tcid), Some(st)) = (tool_call_id, status) {
+                    if st == "completed" || st == "failed" {
+                        crate::checkpoints::on_tool_call_finished(app_handle, workspace_id, tcid);
+                        crate::plan_progress::on_tool_call_finished(app_handle, workspace_id, tcid, st == "failed");
+                    }
                     let mut new_content: Option<String> = None;
                     if st == "completed" {
                         if let Some(summary) = payload.get("rawOutput")
@@ -485,9 +664,15 @@ impl AcpConnection {
                         }
                     }
                 }
+                if let Some(tcid) = tool_call_id {
+                    record_tool_payload(saved_this_turn, workspace_id, app_handle, tcid, &payload);
+                }
+            }
+            "plan" => {
+                crate::plan_progress::on_acp_plan(app_handle, workspace_id, &payload);
             }
             "end_turn" => {
-                flush_buffer(streaming_buffer, streaming_type, saved_this_turn, workspace_id, app_handle);
+                stream.flush(saved_this_turn, workspace_id, app_handle);
                 let to_emit = std::mem::take(saved_this_turn);
                 if !to_emit.is_empty() {
                     eprintln!("[acp] end_turn: session={} emitting {} saved messages", workspace_id, to_emit.len());
@@ -559,50 +744,19 @@ impl AcpConnection {
         eprintln!("[acp] suppress_updates={} workspace={}", suppress, self.workspace_id);
     }
 
+    /// Persists `text` as the user message and sends it to the agent.
     pub async fn send_prompt(
         &self,
         acp_session_id: String,
         text: String,
+        options: &PromptOptions,
         timeout: std::time::Duration,
     ) -> Result<serde_json::Value, String> {
         let was_suppressed = self.suppress_updates.swap(false, Ordering::AcqRel);
         eprintln!("[acp] send_prompt: workspace={} was_suppressed={} text_len={}", self.workspace_id, was_suppressed, text.len());
-        if !text.starts_with('/') {
-            if let Some(db) = self.app_handle.try_state::<crate::comments::CommentsDb>() {
-                match db.0.lock() {
-                    Ok(conn) => {
-                        if crate::messages::is_duplicate_user_message(&conn, &self.workspace_id, &text) {
-                            eprintln!("[acp] send_prompt: skipping duplicate user message");
-                        } else {
-                            match crate::messages::save_message(
-                                &conn,
-                                &self.workspace_id,
-                                "user",
-                                &text,
-                                None,
-                                None,
-                                None,
-                                None,
-                            ) {
-                                Ok(record) => {
-                                    eprintln!("[acp] send_prompt: saved user message id={}", record.id);
-                                    let _ = self.app_handle.emit("acp:user-message-saved", serde_json::json!({
-                                        "sessionId": &self.workspace_id,
-                                        "id": record.id,
-                                        "content": &text,
-                                    }));
-                                }
-                                Err(e) => eprintln!("[acp] send_prompt: save_message FAILED: {}", e),
-                            }
-                        }
-                    },
-                    Err(e) => eprintln!("[acp] send_prompt: db lock FAILED: {}", e),
-                }
-            } else {
-                eprintln!("[acp] send_prompt: CommentsDb state NOT FOUND");
-            }
-        }
+        save_user_prompt(&self.app_handle, &self.workspace_id, &text, options);
 
+        let text = options.wire_text(&text);
         let params = crate::acp::types::PromptParams {
             session_id: acp_session_id,
             prompt: vec![crate::acp::types::PromptContent {
@@ -744,6 +898,7 @@ impl ClaudeConnection {
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::inherit())
             .kill_on_drop(true);
+        crate::env_profiles::apply_to_command(&app_handle, cwd, &mut cmd);
 
         let mut child = cmd
             .spawn()
@@ -809,8 +964,7 @@ impl ClaudeConnection {
         let mut lines = reader.lines();
 
         let mut saved_this_turn: Vec<MessageRecord> = Vec::new();
-        let mut streaming_buffer: Option<String> = None;
-        let mut streaming_type: Option<String> = None;
+        let mut stream = StreamBuffer::default();
 
         while let Ok(Some(line)) = lines.next_line().await {
             let line = line.trim().to_string();
@@ -847,10 +1001,7 @@ impl ClaudeConnection {
                     for block in asst.message.content {
                         match block {
                             ClaudeContentBlock::Text { text } => {
-                                streaming_type.get_or_insert_with(|| "assistant".to_string());
-                                streaming_buffer
-                                    .get_or_insert_with(String::new)
-                                    .push_str(&text);
+                                stream.push("assistant", &text, &mut saved_this_turn, &workspace_id, &app_handle);
 
                                 let ev = SessionUpdateEvent {
                                     workspace_id: workspace_id.clone(),
@@ -863,8 +1014,10 @@ impl ClaudeConnection {
                                 let _ = app_handle.emit("acp:session-update", &ev);
                             }
                             ClaudeContentBlock::ToolUse { id, name, input } => {
-                                flush_buffer(&mut streaming_buffer, &mut streaming_type, &mut saved_this_turn, &workspace_id, &app_handle);
+                                stream.flush(&mut saved_this_turn, &workspace_id, &app_handle);
 
+                                crate::checkpoints::on_claude_tool_use(&app_handle, &workspace_id, &id, &name, &input);
+                                crate::plan_progress::on_claude_tool_use(&app_handle, &workspace_id, &id, &name, &input);
                                 let input_str = serde_json::to_string(&input).unwrap_or_default();
                                 save_to_db(
                                     &mut saved_this_turn, &workspace_id, &app_handle,
@@ -872,17 +1025,20 @@ impl ClaudeConnection {
                                     Some("tool"), Some(&id), Some(&name), Some("pending"),
                                 );
 
+                                let payload = serde_json::json!({
+                                    "toolCallId": id,
+                                    "title": name,
+                                    "kind": name,
+                                    "rawInput": input,
+                                    "status": "pending"
+                                });
+                                record_tool_payload(&mut saved_this_turn, &workspace_id, &app_handle, &id, &payload);
+
                                 let ev = SessionUpdateEvent {
                                     workspace_id: workspace_id.clone(),
                                     session_id: sid.clone(),
                                     update_type: "tool_call".to_string(),
-                                    payload: serde_json::json!({
-                                        "toolCallId": id,
-                                        "title": name,
-                                        "kind": name,
-                                        "rawInput": input,
-                                        "status": "pending"
-                                    }),
+                                    payload,
                                 };
                                 let _ = app_handle.emit("acp:session-update", &ev);
                             }
@@ -902,11 +1058,17 @@ impl ClaudeConnection {
                                 .unwrap_or("")
                                 .to_string();
 
+                            let failed = block.is_error.unwrap_or(false);
+                            let status = if failed { "failed" } else { "completed" };
+
+                            crate::checkpoints::on_tool_call_finished(&app_handle, &workspace_id, &tool_use_id);
+                            crate::tool_diffs::on_claude_tool_result(&app_handle, &workspace_id, &tool_use_id, failed);
+                            crate::plan_progress::on_tool_call_finished(&app_handle, &workspace_id, &tool_use_id, failed);
                             if let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() {
                                 if let Ok(conn) = db.0.lock() {
                                     if let Ok(updated) = crate::messages::update_message_by_tool_call_id(
                                         &conn, &workspace_id, &tool_use_id,
-                                        Some(&content_str), "completed",
+                                        Some(&content_str), status,
                                     ) {
                                         if let Some(record) = saved_this_turn.iter_mut().rev()
                                             .find(|r| r.tool_call_id.as_deref() == Some(&tool_use_id))
@@ -917,15 +1079,18 @@ impl ClaudeConnection {
                                 }
                             }
 
+                            let payload = serde_json::json!({
+                                "toolCallId": tool_use_id,
+                                "status": status,
+                                "rawOutput": { "content": content_str }
+                            });
+                            record_tool_payload(&mut saved_this_turn, &workspace_id, &app_handle, &tool_use_id, &payload);
+
                             let ev = SessionUpdateEvent {
                                 workspace_id: workspace_id.clone(),
                                 session_id: sid.clone(),
                                 update_type: "tool_call_update".to_string(),
-                                payload: serde_json::json!({
-                                    "toolCallId": tool_use_id,
-                                    "status": "completed",
-                                    "rawOutput": { "content": content_str }
-                                }),
+                                payload,
                             };
                             let _ = app_handle.emit("acp:session-update", &ev);
                         }
@@ -936,7 +1101,7 @@ impl ClaudeConnection {
                     let sid = session_id.lock().await.clone().unwrap_or_default();
                     let is_error = result.is_error.unwrap_or(false);
 
-                    flush_buffer(&mut streaming_buffer, &mut streaming_type, &mut saved_this_turn, &workspace_id, &app_handle);
+                    stream.flush(&mut saved_this_turn, &workspace_id, &app_handle);
                     let to_emit = std::mem::take(&mut saved_this_turn);
                     if !to_emit.is_empty() {
                         let _ = app_handle.emit("acp:assistant-message-saved", serde_json::json!({
@@ -971,8 +1136,8 @@ impl ClaudeConnection {
             }
         }
 
-        // Flush any remaining buffer on reader exit
-        flush_buffer(&mut streaming_buffer, &mut streaming_type, &mut saved_this_turn, &workspace_id, &app_handle);
+        // Claude exited mid-turn: keep whatever it had streamed
+        stream.interrupt(&mut saved_this_turn, &workspace_id, &app_handle);
         if !saved_this_turn.is_empty() {
             let _ = app_handle.emit("acp:assistant-message-saved", serde_json::json!({
                 "sessionId": &workspace_id,
@@ -1039,33 +1204,15 @@ impl ClaudeConnection {
     pub async fn send_prompt(
         &self,
         text: &str,
+        options: &PromptOptions,
         timeout: std::time::Duration,
     ) -> Result<(), String> {
-        if !text.starts_with('/') {
-            if let Some(db) = self.app_handle.try_state::<crate::comments::CommentsDb>() {
-                if let Ok(conn) = db.0.lock() {
-                    if !crate::messages::is_duplicate_user_message(&conn, &self.workspace_id, text) {
-                        match crate::messages::save_message(
-                            &conn, &self.workspace_id, "user", text,
-                            None, None, None, None,
-                        ) {
-                            Ok(record) => {
-                                let _ = self.app_handle.emit("acp:user-message-saved", serde_json::json!({
-                                    "sessionId": &self.workspace_id,
-                                    "id": record.id,
-                                    "content": text,
-                                }));
-                            }
-                            Err(e) => eprintln!("[claude] send_prompt: save_message error: {}", e),
-                        }
-                    }
-                }
-            }
-        }
+        save_user_prompt(&self.app_handle, &self.workspace_id, text, options);
 
+        let content = options.wire_text(text);
         let msg = serde_json::json!({
             "type": "user",
-            "message": { "role": "user", "content": text }
+            "message": { "role": "user", "content": content }
         });
         let line = serde_json::to_string(&msg).map_err(|e| e.to_string())? + "\n";
 
@@ -1133,14 +1280,363 @@ impl ClaudeConnection {
     }
 
     pub fn emit_log(&self, level: &str, event: &str, message: &str) {
-        let entry = ConnectionLogEntry {
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            level: level.to_string(),
-            event: event.to_string(),
-            message: message.to_string(),
+        emit_log_raw(&self.app_handle, &self.workspace_id, level, event, message);
+    }
+}
+
+// ── OpenAiConnection ─────────────────────────────────────────────────────────
+
+/// Joins an OpenAI-compatible base URL (with or without the `/v1` suffix) and an API path.
+fn openai_endpoint(base_url: &str, path: &str) -> String {
+    let base = base_url.trim().trim_end_matches('/');
+    if base.ends_with("/v1") {
+        format!("{}/{}", base, path)
+    } else {
+        format!("{}/v1/{}", base, path)
+    }
+}
+
+fn delta_text<'a>(delta: Option<&'a serde_json::Value>, key: &str) -> Option<&'a str> {
+    delta
+        .and_then(|d| d.get(key))
+        .and_then(|v| v.as_str())
+        .filter(|t| !t.is_empty())
+}
+
+/// Conversation of the workspace's session as chat-completion messages.
+fn load_chat_history(app_handle: &AppHandle, workspace_id: &str) -> Vec<serde_json::Value> {
+    let mut history = Vec::new();
+    if let Some(db) = app_handle.try_state::<crate::comments::CommentsDb>() {
+        if let Ok(conn) = db.0.lock() {
+            match crate::messages::list_chat_history(&conn, workspace_id) {
+                Ok(records) => {
+                    history.extend(records.into_iter().filter(|r| !r.content.is_empty()).map(|r| {
+                        serde_json::json!({ "role": r.role, "content": r.content })
+                    }));
+                }
+                Err(e) => eprintln!("[openai] history rebuild error: {}", e),
+            }
+        }
+    }
+    history
+}
+
+/// `GET /models` of an OpenAI-compatible server.
+async fn list_models(client: &reqwest::Client, base_url: &str, api_key: Option<&str>) -> Result<serde_json::Value, String> {
+    let mut request = client
+        .get(openai_endpoint(base_url, "models"))
+        .timeout(std::time::Duration::from_secs(10));
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+    let body = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", base_url, e))?
+        .error_for_status()
+        .map_err(|e| format!("Model listing failed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Model listing failed: {}", e))?;
+    Ok(serde_json::from_str(&body).unwrap_or(serde_json::Value::Null))
+}
+
+/// Chat-only provider talking to an OpenAI-compatible HTTP server. There is no
+/// child process: each prompt is a streamed `/v1/chat/completions` request and
+/// the conversation is kept in memory, rebuilt from SQLite on connect.
+pub struct OpenAiConnection {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    history: Mutex<Vec<serde_json::Value>>,
+    /// Held for the whole of a turn, so a turn can be waited for
+    turn: Mutex<()>,
+    cancelled: AtomicBool,
+    closed: AtomicBool,
+    app_handle: AppHandle,
+    workspace_id: String,
+}
+
+impl OpenAiConnection {
+    pub async fn connect(
+        base_url: &str,
+        api_key: Option<String>,
+        model: Option<&str>,
+        workspace_id: String,
+        app_handle: AppHandle,
+    ) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        let base_url = base_url.trim().trim_end_matches('/').to_string();
+        let api_key = api_key
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty());
+
+        let model = match model.map(str::trim).filter(|m| !m.is_empty()) {
+            Some(m) => m.to_string(),
+            // Without a configured model, use the server's first one. Not every
+            // compatible server lists its models, so the probe is only needed here.
+            None => list_models(&client, &base_url, api_key.as_deref())
+                .await
+                .map_err(|e| format!("{}; configure a model explicitly", e))?
+                .get("data")
+                .and_then(|d| d.as_array())
+                .and_then(|a| a.first())
+                .and_then(|m| m.get("id"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .ok_or("Server reported no models; configure a model explicitly")?,
+        };
+
+        let history = load_chat_history(&app_handle, &workspace_id);
+
+        eprintln!(
+            "[openai] Connected to {} model={} workspace={} history={}",
+            base_url, model, workspace_id, history.len()
+        );
+
+        Ok(Self {
+            client,
+            base_url,
+            api_key,
+            model,
+            history: Mutex::new(history),
+            turn: Mutex::new(()),
+            cancelled: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            app_handle,
+            workspace_id,
+        })
+    }
+
+    /// Rebuilds the conversation from the database, e.g. after the session was rewound.
+    pub async fn reload_history(&self) {
+        *self.history.lock().await = load_chat_history(&self.app_handle, &self.workspace_id);
+    }
+
+    /// Identifier stored as the session's `acp_session_id`; the server itself is stateless.
+    pub fn session_id(&self) -> String {
+        format!("openai-{}", self.workspace_id)
+    }
+
+    fn emit_update(&self, update_type: &str, payload: serde_json::Value) {
+        let ev = SessionUpdateEvent {
+            workspace_id: self.workspace_id.clone(),
+            session_id: self.session_id(),
+            update_type: update_type.to_string(),
+            payload,
+        };
+        let _ = self.app_handle.emit("acp:session-update", &ev);
+    }
+
+    /// Persist the user message, then stream the completion for the whole conversation.
+    /// The history is replayed on every request, so `options.context` is not needed.
+    pub async fn send_prompt(
+        &self,
+        text: &str,
+        options: &PromptOptions,
+        timeout: std::time::Duration,
+    ) -> Result<(), String> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err("Connection closed".to_string());
+        }
+        let _turn = self.turn.lock().await;
+
+        save_user_prompt(&self.app_handle, &self.workspace_id, text, options);
+
+        // The turn joins the history only once it got an answer, so a retry
+        // after a failed request does not send the prompt twice
+        let user_turn = serde_json::json!({ "role": "user", "content": text });
+        let mut messages = self.history.lock().await.clone();
+        messages.push(user_turn.clone());
+
+        self.cancelled.store(false, Ordering::Release);
+        let reply = self.stream_completion(messages, timeout).await?;
+
+        let mut history = self.history.lock().await;
+        history.push(user_turn);
+        if !reply.is_empty() {
+            history.push(serde_json::json!({ "role": "assistant", "content": reply }));
+        }
+        Ok(())
+    }
+
+    /// Streams one completion and returns the assistant's reply text. A turn
+    /// running past `timeout` ends like a broken stream: the partial answer is
+    /// saved as interrupted and `end_turn` reports the error.
+    async fn stream_completion(
+        &self,
+        messages: Vec<serde_json::Value>,
+        timeout: std::time::Duration,
+    ) -> Result<String, String> {
+        use futures_util::StreamExt;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let timed_out = || format!("Timeout waiting for response after {}s", timeout.as_secs());
+
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": true,
+        });
+        let mut request = self
+            .client
+            .post(openai_endpoint(&self.base_url, "chat/completions"))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(&body).map_err(|e| e.to_string())?);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = match tokio::time::timeout_at(deadline, request.send()).await {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => {
+                let err = format!("Request failed: {}", e);
+                self.emit_log("error", "request_failed", &err);
+                self.emit_update("end_turn", serde_json::json!({ "isError": true }));
+                return Err(err);
+            }
+            Err(_) => {
+                let err = timed_out();
+                self.emit_log("error", "timeout", &err);
+                self.emit_update("end_turn", serde_json::json!({ "isError": true }));
+                return Err(err);
+            }
+        };
+        if !response.status().is_success() {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+            let err = format!("Server returned {}: {}", status, detail.chars().take(500).collect::<String>());
+            self.emit_log("error", "request_failed", &err);
+            self.emit_update("end_turn", serde_json::json!({ "isError": true }));
+            return Err(err);
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut line_buf: Vec<u8> = Vec::new();
+        let mut buffer = StreamBuffer::default();
+        let mut saved_this_turn: Vec<MessageRecord> = Vec::new();
+        let mut reply = String::new();
+        let mut stream_error: Option<String> = None;
+
+        'stream: loop {
+            let chunk = match tokio::time::timeout_at(deadline, stream.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(_) => {
+                    stream_error = Some(timed_out());
+                    break;
+                }
+            };
+            if self.cancelled.load(Ordering::Acquire) {
+                break;
+            }
+            let bytes = match chunk {
+                Ok(b) => b,
+                Err(e) => {
+                    stream_error = Some(format!("Stream error: {}", e));
+                    break;
+                }
+            };
+            line_buf.extend_from_slice(&bytes);
+
+            // SSE frames are newline-delimited; keep any partial line for the next chunk
+            while let Some(pos) = line_buf.iter().position(|b| *b == b'\n') {
+                let raw: Vec<u8> = line_buf.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&raw);
+                let data = match line.trim().strip_prefix("data:") {
+                    Some(d) => d.trim().to_string(),
+                    None => continue,
+                };
+                if data == "[DONE]" {
+                    break 'stream;
+                }
+                let event: serde_json::Value = match serde_json::from_str(&data) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        eprintln!("[openai] Failed to parse chunk: {} — {}", e, &data[..data.len().min(200)]);
+                        continue;
+                    }
+                };
+                let delta = event.pointer("/choices/0/delta");
+
+                if let Some(text) = delta_text(delta, "reasoning_content") {
+                    buffer.push("thinking", text, &mut saved_this_turn, &self.workspace_id, &self.app_handle);
+                    self.emit_update("agent_thought_chunk", serde_json::json!({
+                        "content": { "type": "text", "text": text }
+                    }));
+                }
+                if let Some(text) = delta_text(delta, "content") {
+                    buffer.push("assistant", text, &mut saved_this_turn, &self.workspace_id, &self.app_handle);
+                    reply.push_str(text);
+                    self.emit_update("agent_message_chunk", serde_json::json!({
+                        "content": { "type": "text", "text": text }
+                    }));
+                }
+            }
+        }
+
+        if stream_error.is_some() {
+            buffer.interrupt(&mut saved_this_turn, &self.workspace_id, &self.app_handle);
+        } else {
+            buffer.flush(&mut saved_this_turn, &self.workspace_id, &self.app_handle);
+        }
+        if !saved_this_turn.is_empty() {
+            let _ = self.app_handle.emit("acp:assistant-message-saved", serde_json::json!({
+                "sessionId": &self.workspace_id,
+                "messages": saved_this_turn,
+            }));
+        }
+        let cancelled = self.cancelled.swap(false, Ordering::AcqRel);
+        self.emit_update("end_turn", serde_json::json!({
+            "isError": stream_error.is_some(),
+            "stopReason": if cancelled { "cancelled" } else { "end_turn" },
+        }));
+
+        match stream_error {
+            Some(e) => {
+                self.emit_log("error", "stream_error", &e);
+                Err(e)
+            }
+            None => Ok(reply),
+        }
+    }
+
+    /// Stops the in-flight completion at the next received chunk.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Cancels the in-flight turn and waits until it has saved what it got.
+    /// No new turn starts while the returned guard is held.
+    pub async fn stop_turn(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.cancel();
+        self.turn.lock().await
+    }
+
+    pub async fn shutdown(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.cancel();
+    }
+
+    pub async fn is_alive(&self) -> bool {
+        !self.closed.load(Ordering::Acquire)
+    }
+
+    pub fn emit_status(&self, status: &str, attempt: Option<u32>) {
+        let event = ConnectionStatusEvent {
             workspace_id: self.workspace_id.clone(),
+            status: status.to_string(),
+            attempt,
         };
-        let _ = self.app_handle.emit("acp:log", &entry);
+        let _ = self.app_handle.emit("acp:connection-status", &event);
+    }
+
+    pub fn emit_log(&self, level: &str, event: &str, message: &str) {
+        emit_log_raw(&self.app_handle, &self.workspace_id, level, event, message);
     }
 }
 
@@ -1157,7 +1653,7 @@ pub fn emit_log_raw(app_handle: &AppHandle, workspace_id: &str, level: &str, eve
         timestamp: chrono::Utc::now().to_rfc3339(),
         level: level.to_string(),
         event: event.to_string(),
-        message: message.to_string(),
+        message: crate::env_profiles::redact(app_handle, message, &[]),
         workspace_id: workspace_id.to_string(),
     };
     let _ = app_handle.emit("acp:log", &entry);
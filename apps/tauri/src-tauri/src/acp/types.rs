@@ -216,6 +216,8 @@ pub struct ConnectionLogEntry {
 pub enum Provider {
     Copilot,
     Claude,
+    /// OpenAI-compatible `/v1/chat/completions` endpoint (llama.cpp server, vLLM, ...)
+    OpenAi,
 }
 
 impl Default for Provider {
@@ -0,0 +1,567 @@
+//! Keeping comments attached to the right block as a document changes.
+//!
+//! Block ids (`mkw-para-3`) are positional, so any edit above a commented
+//! block shifts them. Each `comment_blocks` row therefore also stores a
+//! fingerprint: the block's normalized text and that of its neighbours. When
+//! a document no longer matches the hash its comments were saved against,
+//! every anchor is looked up again by fuzzy matching the fingerprint against
+//! the new blocks. Anchors that only match loosely are flagged
+//! `low_confidence`, and those that match nothing are `orphaned` rather than
+//! left on whatever block now has their old id.
+//!
+//! An anchor may also narrow the comment to a range of source text within
+//! its block. The range stores the text it covered, and follows that text
+//! when the block moves or is edited around it.
+
+use crate::markdown::MarkdownBlock;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+pub const ANCHORED: &str = "anchored";
+pub const RELOCATED: &str = "relocated";
+pub const LOW_CONFIDENCE: &str = "low_confidence";
+pub const ORPHANED: &str = "orphaned";
+
+/// Score from which a relocated anchor is trusted.
+const CONFIDENT: f64 = 0.85;
+/// Score below which an anchor is orphaned.
+const MIN_MATCH: f64 = 0.5;
+/// Weight of the block's own text against its neighbours'.
+const TEXT_WEIGHT: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fingerprint {
+    pub text: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// A span of source text within one of a comment's blocks.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TextRange {
+    pub block_id: String,
+    /// One-based source lines of the document (inclusive)
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Offset into the first line, and exclusive end offset into the last,
+    /// in UTF-16 code units as DOM selections count them; the range takes
+    /// the whole lines when unset
+    #[serde(default)]
+    pub start_char: Option<usize>,
+    #[serde(default)]
+    pub end_char: Option<usize>,
+    /// The text the range covered when it was made
+    #[serde(default)]
+    pub quote: String,
+}
+
+/// `comment_blocks` columns holding an anchor's range, read by `row_range`.
+pub const RANGE_COLUMNS: &str = "start_line, end_line, start_char, end_char, quote";
+
+/// The range in the five `RANGE_COLUMNS` from `first` on, if there is one.
+pub fn row_range(row: &rusqlite::Row, block_id: &str, first: usize) -> rusqlite::Result<Option<TextRange>> {
+    let Some(start_line) = row.get::<_, Option<usize>>(first)? else {
+        return Ok(None);
+    };
+    Ok(Some(TextRange {
+        block_id: block_id.to_string(),
+        start_line,
+        end_line: row.get(first + 1)?,
+        start_char: row.get(first + 2)?,
+        end_char: row.get(first + 3)?,
+        quote: row.get::<_, Option<String>>(first + 4)?.unwrap_or_default(),
+    }))
+}
+
+/// Byte offset of the position `n` UTF-16 code units into `line`. None past
+/// the end of the line or inside a surrogate pair.
+fn char_offset(line: &str, n: usize) -> Option<usize> {
+    let mut units = 0;
+    for (i, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        if units == n {
+            return Some(i);
+        }
+        if units > n {
+            return None;
+        }
+        units += c.len_utf16();
+    }
+    None
+}
+
+/// Length of `text` in UTF-16 code units.
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Checks the shape of `range` without looking at the document.
+pub fn check_range(range: &TextRange) -> Result<(), String> {
+    if range.start_line == 0 || range.end_line < range.start_line {
+        return Err(format!("Invalid line range {}-{} on {}", range.start_line, range.end_line, range.block_id));
+    }
+    if let (true, Some(start), Some(end)) = (range.start_line == range.end_line, range.start_char, range.end_char) {
+        if end <= start {
+            return Err(format!("Invalid character range {}-{} on {}", start, end, range.block_id));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `range` lies within its block of `markdown` and covers its
+/// quote, which is filled in when empty.
+pub fn validate_range(markdown: &str, range: &mut TextRange) -> Result<(), String> {
+    check_range(range)?;
+    let (_, block) = crate::markdown::viewer_blocks(markdown)
+        .into_iter()
+        .find(|(id, _)| *id == range.block_id)
+        .ok_or_else(|| format!("Block not found: {}", range.block_id))?;
+    if range.start_line < block.start_line || range.end_line > block.end_line {
+        return Err(format!(
+            "Lines {}-{} are outside {} (lines {}-{})",
+            range.start_line, range.end_line, range.block_id, block.start_line, block.end_line
+        ));
+    }
+    let lines: Vec<&str> = markdown.lines().collect();
+    let (first, last) = (lines[range.start_line - 1], lines[range.end_line - 1]);
+    let past_end = |n: usize, line: usize| format!("Offset {} is not a character boundary of line {}", n, line);
+    let start = match range.start_char {
+        Some(n) => char_offset(first, n).ok_or_else(|| past_end(n, range.start_line))?,
+        None => 0,
+    };
+    let end = match range.end_char {
+        Some(n) => char_offset(last, n).ok_or_else(|| past_end(n, range.end_line))?,
+        None => last.len(),
+    };
+    let text = if range.start_line == range.end_line {
+        first[start..end.max(start)].to_string()
+    } else {
+        let mut text = first[start..].to_string();
+        for line in &lines[range.start_line..range.end_line] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text.truncate(text.len() - (last.len() - end));
+        text
+    };
+    if text.trim().is_empty() {
+        return Err(format!("Range on {} covers no text", range.block_id));
+    }
+    if range.quote.is_empty() {
+        range.quote = text;
+    } else if range.quote != text {
+        return Err(format!(
+            "Quoted text does not match lines {}-{} of {}",
+            range.start_line, range.end_line, range.block_id
+        ));
+    }
+    Ok(())
+}
+
+/// Where `previous.quote` now is within `block`, as a range of its source.
+/// When it occurs more than once, the occurrence nearest the previous
+/// position is taken and the match is flagged as ambiguous.
+pub fn find_quote(block_id: &str, block: &MarkdownBlock, previous: &TextRange) -> Option<(TextRange, bool)> {
+    let quote = previous.quote.as_str();
+    if quote.is_empty() {
+        return None;
+    }
+    // Block text is its source lines joined by newlines
+    let position = |offset: usize| {
+        let before = &block.text[..offset];
+        let line = block.start_line + before.matches('\n').count();
+        (line, utf16_len(before.rsplit('\n').next().unwrap_or("")))
+    };
+    let matches: Vec<((usize, usize), usize)> =
+        block.text.match_indices(quote).map(|(at, _)| (position(at), at)).collect();
+    let distance = |(line, char): (usize, usize)| {
+        (line.abs_diff(previous.start_line), char.abs_diff(previous.start_char.unwrap_or(0)))
+    };
+    let &((start_line, start_char), at) = matches.iter().min_by_key(|(pos, _)| distance(*pos))?;
+    let (end_line, end_char) = position(at + quote.len());
+    let range = TextRange {
+        block_id: block_id.to_string(),
+        start_line,
+        end_line,
+        start_char: Some(start_char),
+        end_char: Some(end_char),
+        quote: quote.to_string(),
+    };
+    Some((range, matches.len() > 1))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub block_id: String,
+    pub status: &'static str,
+    pub confidence: f64,
+}
+
+/// Lowercased words without Markdown markers, so re-bulleting or checking
+/// off an item does not count as a change.
+pub fn normalize(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.trim_start().trim_start_matches(['#', '>', '-', '*', '+', ' ']);
+            let digits = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            let line = digits.strip_prefix(". ").or_else(|| digits.strip_prefix(") ")).unwrap_or(line);
+            ["[ ] ", "[x] ", "[X] "].iter().fold(line, |l, m| l.strip_prefix(m).unwrap_or(l))
+        })
+        .flat_map(str::split_whitespace)
+        .map(|w| w.trim_matches(['*', '_', '`']).to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Fingerprint of every commentable block, keyed by its viewer id.
+pub fn fingerprints(markdown: &str) -> Vec<(String, Fingerprint)> {
+    let blocks: Vec<(String, String)> = crate::markdown::viewer_blocks(markdown)
+        .into_iter()
+        .map(|(id, block)| (id, normalize(&block.text)))
+        .collect();
+    (0..blocks.len())
+        .map(|i| {
+            let before = i.checked_sub(1).map(|j| blocks[j].1.clone()).unwrap_or_default();
+            let after = blocks.get(i + 1).map(|b| b.1.clone()).unwrap_or_default();
+            (blocks[i].0.clone(), Fingerprint { text: blocks[i].1.clone(), before, after })
+        })
+        .collect()
+}
+
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut pairs: Vec<(char, char)> = chars.windows(2).map(|w| (w[0], w[1])).collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Dice coefficient over character bigrams, in `0.0..=1.0`.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let (x, y) = (bigrams(a), bigrams(b));
+    if x.is_empty() || y.is_empty() {
+        return 0.0;
+    }
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < x.len() && j < y.len() {
+        match x[i].cmp(&y[j]) {
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+        }
+    }
+    2.0 * common as f64 / (x.len() + y.len()) as f64
+}
+
+/// Where the anchor `(old_id, fp)` belongs among `blocks`.
+pub fn locate(old_id: &str, fp: &Fingerprint, blocks: &[(String, Fingerprint)]) -> Relocation {
+    if blocks.iter().any(|(id, b)| id == old_id && b.text == fp.text) {
+        return Relocation { block_id: old_id.to_string(), status: ANCHORED, confidence: 1.0 };
+    }
+    let score = |b: &Fingerprint| {
+        let context = (similarity(&fp.before, &b.before) + similarity(&fp.after, &b.after)) / 2.0;
+        TEXT_WEIGHT * similarity(&fp.text, &b.text) + (1.0 - TEXT_WEIGHT) * context
+    };
+    // The same text moved elsewhere is followed whatever now surrounds it;
+    // the context only picks between copies
+    let moved = blocks
+        .iter()
+        .filter(|(_, b)| !fp.text.is_empty() && b.text == fp.text)
+        .map(|(id, b)| (id, score(b)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((id, confidence)) = moved {
+        return Relocation { block_id: id.clone(), status: RELOCATED, confidence };
+    }
+    let best = blocks.iter().map(|(id, b)| (id, score(b))).max_by(|a, b| a.1.total_cmp(&b.1));
+    match best {
+        Some((id, score)) if score >= CONFIDENT => Relocation {
+            block_id: id.clone(),
+            status: if id == old_id { ANCHORED } else { RELOCATED },
+            confidence: score,
+        },
+        Some((id, score)) if score >= MIN_MATCH => Relocation { block_id: id.clone(), status: LOW_CONFIDENCE, confidence: score },
+        _ => Relocation { block_id: old_id.to_string(), status: ORPHANED, confidence: best.map_or(0.0, |b| b.1) },
+    }
+}
+
+/// Fingerprints the anchors on `file_path` that have none yet from the
+/// blocks of its content, `markdown`, that they point at.
+pub fn fill_fingerprints(conn: &Connection, file_path: &str, markdown: &str) -> Result<(), String> {
+    for (id, fp) in fingerprints(markdown) {
+        conn.execute(
+            "UPDATE comment_blocks SET fingerprint = ?3, context_before = ?4, context_after = ?5
+             WHERE block_id = ?2 AND fingerprint IS NULL
+               AND comment_id IN (SELECT id FROM comments WHERE file_path = ?1)",
+            params![file_path, id, fp.text, fp.before, fp.after],
+        )
+        .map_err(|e| format!("Update fingerprint error: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Re-locates the anchors of every comment on `file_path` in its new
+/// content. Anchors saved before fingerprints existed cannot be followed;
+/// while there are any, the stored hash is kept so the file still reads as
+/// stale, otherwise `hash` is recorded as the content the anchors now match.
+pub fn reanchor(conn: &Connection, file_path: &str, markdown: &str, hash: &str) -> Result<usize, String> {
+    let blocks = fingerprints(markdown);
+    let viewer = crate::markdown::viewer_blocks(markdown);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT b.comment_id, b.block_id, b.fingerprint, b.context_before, b.context_after, {}
+             FROM comment_blocks b JOIN comments c ON c.id = b.comment_id
+             WHERE c.file_path = ?1",
+            RANGE_COLUMNS
+        ))
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let anchors = stmt
+        .query_map(params![file_path], |row| {
+            let block_id: String = row.get(1)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(2)?.map(|text| Fingerprint {
+                    text,
+                    before: row.get::<_, Option<String>>(3).ok().flatten().unwrap_or_default(),
+                    after: row.get::<_, Option<String>>(4).ok().flatten().unwrap_or_default(),
+                }),
+                row_range(row, &block_id, 5)?,
+                block_id,
+            ))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    // Joins the caller's transaction when there is one
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction().map_err(|e| format!("Transaction error: {}", e))?)
+    } else {
+        None
+    };
+    // Every anchor is looked up before any is moved, so one moving onto
+    // another's old block never clobbers it
+    let mut moved = 0;
+    let mut relocated = Vec::new();
+    for (comment_id, fp, range, old_id) in &anchors {
+        let Some(fp) = fp else { continue };
+        let mut found = locate(old_id, fp, &blocks);
+        if found.block_id != *old_id {
+            moved += 1;
+        }
+        // A confident match takes the block's current text as its fingerprint;
+        // a doubtful one keeps the original to match against next time
+        let new_fp = match found.status {
+            ANCHORED | RELOCATED => blocks.iter().find(|(id, _)| *id == found.block_id).map(|(_, b)| b),
+            _ => None,
+        }
+        .unwrap_or(fp)
+        .clone();
+        // A range follows its quote within the block; when the quote is gone
+        // or repeated, the comment is kept with less certainty, falling back
+        // to the whole block if the quote is gone
+        let block = viewer.iter().find(|(id, _)| *id == found.block_id).map(|(_, b)| b);
+        let new_range = match (range, block) {
+            (Some(range), Some(block)) if found.status != ORPHANED => match find_quote(&found.block_id, block, range) {
+                Some((new_range, ambiguous)) => {
+                    if ambiguous {
+                        found.status = LOW_CONFIDENCE;
+                    }
+                    Some(new_range)
+                }
+                None => {
+                    found.status = LOW_CONFIDENCE;
+                    Some(TextRange {
+                        block_id: found.block_id.clone(),
+                        start_line: block.start_line,
+                        end_line: block.end_line,
+                        start_char: None,
+                        end_char: None,
+                        quote: range.quote.clone(),
+                    })
+                }
+            },
+            _ => range.clone(),
+        };
+        conn.execute(
+            "DELETE FROM comment_blocks WHERE comment_id = ?1 AND block_id = ?2",
+            params![comment_id, old_id],
+        )
+        .map_err(|e| format!("Delete anchor error: {}", e))?;
+        relocated.push((comment_id, found, new_fp, new_range));
+    }
+    for (comment_id, found, fp, range) in relocated {
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO comment_blocks
+                    (comment_id, block_id, fingerprint, context_before, context_after, anchor_status, confidence, {})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                RANGE_COLUMNS
+            ),
+            params![
+                comment_id,
+                found.block_id,
+                fp.text,
+                fp.before,
+                fp.after,
+                found.status,
+                found.confidence,
+                range.as_ref().map(|r| r.start_line),
+                range.as_ref().map(|r| r.end_line),
+                range.as_ref().and_then(|r| r.start_char),
+                range.as_ref().and_then(|r| r.end_char),
+                range.as_ref().map(|r| &r.quote),
+            ],
+        )
+        .map_err(|e| format!("Update anchor error: {}", e))?;
+    }
+    if anchors.iter().all(|(_, fp, _, _)| fp.is_some()) {
+        conn.execute(
+            "UPDATE file_hashes SET file_hash = ?2 WHERE file_path = ?1",
+            params![file_path, hash],
+        )
+        .map_err(|e| format!("Hash update error: {}", e))?;
+    }
+    if let Some(tx) = tx {
+        tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::test_support::TestDb;
+
+    fn fp(text: &str, before: &str, after: &str) -> Fingerprint {
+        Fingerprint { text: text.to_string(), before: before.to_string(), after: after.to_string() }
+    }
+
+    #[test]
+    fn similarity_bounds() {
+        assert_eq!(similarity("same text", "same text"), 1.0);
+        assert_eq!(similarity("abc", ""), 0.0);
+        assert_eq!(similarity("ab", "cd"), 0.0);
+        let close = similarity("deploy the service", "deploy the services");
+        assert!(close > 0.9 && close < 1.0, "{}", close);
+    }
+
+    #[test]
+    fn normalize_ignores_markers() {
+        assert_eq!(normalize("- [x] Run **tests**"), normalize("1. [ ] run tests"));
+        assert_eq!(normalize("## `Deploy`"), "deploy");
+    }
+
+    #[test]
+    fn locate_follows_moved_and_edited_blocks() {
+        let blocks = fingerprints("Intro.\n\nNew paragraph.\n\nKeep the cache warm.\n\nOutro.\n");
+        let anchored = locate("mkw-para-1", &fp("new paragraph.", "intro.", "keep the cache warm."), &blocks);
+        assert_eq!((anchored.block_id.as_str(), anchored.status), ("mkw-para-1", ANCHORED));
+
+        let moved = locate("mkw-para-1", &fp("keep the cache warm.", "intro.", "outro."), &blocks);
+        assert_eq!((moved.block_id.as_str(), moved.status), ("mkw-para-2", RELOCATED));
+
+        let edited = locate("mkw-para-0", &fp("keep the cache warm now.", "new paragraph.", "outro."), &blocks);
+        assert_eq!((edited.block_id.as_str(), edited.status), ("mkw-para-2", RELOCATED));
+
+        let gone = locate("mkw-para-3", &fp("rotate the signing keys", "", ""), &blocks);
+        assert_eq!((gone.block_id.as_str(), gone.status), ("mkw-para-3", ORPHANED));
+    }
+
+    #[test]
+    fn locate_flags_loose_matches() {
+        let blocks = fingerprints("Rotate the keys weekly.\n");
+        let loose = locate("mkw-para-0", &fp("rotate the signing keys monthly.", "", ""), &blocks);
+        assert_eq!(loose.status, LOW_CONFIDENCE);
+        assert!(loose.confidence >= MIN_MATCH && loose.confidence < CONFIDENT);
+    }
+
+    #[test]
+    fn reanchor_moves_comments_with_their_block() {
+        let conn = TestDb::new();
+        let path = conn.dir.join("doc.md");
+        std::fs::write(&path, "First.\n\nSecond.\n").unwrap();
+        let key = path.to_string_lossy().to_string();
+        let new = crate::comments::NewComment {
+            block_ids: vec!["mkw-para-1".to_string()],
+            text: "about second".to_string(),
+            ..Default::default()
+        };
+        crate::comments::add_comment(&conn, &key, &conn.workspace_id, &new).unwrap();
+
+        let markdown = "Inserted.\n\nFirst.\n\nSecond.\n";
+        std::fs::write(&path, markdown).unwrap();
+        let hash = crate::checkpoints::hash_bytes(markdown.as_bytes());
+        assert_eq!(reanchor(&conn, &key, markdown, &hash).unwrap(), 1);
+        let comment = crate::comments::load_comments(&conn, &key).unwrap().comments.remove(0);
+        assert_eq!(comment.block_ids, ["mkw-para-2"]);
+        assert_eq!(comment.anchor_status, RELOCATED);
+    }
+
+    fn range(block_id: &str, lines: (usize, usize), chars: (Option<usize>, Option<usize>), quote: &str) -> TextRange {
+        TextRange {
+            block_id: block_id.to_string(),
+            start_line: lines.0,
+            end_line: lines.1,
+            start_char: chars.0,
+            end_char: chars.1,
+            quote: quote.to_string(),
+        }
+    }
+
+    #[test]
+    fn validate_range_fills_and_checks_the_quote() {
+        let markdown = "Intro.\n\nFirst line\nsecond line\n";
+        let mut single = range("mkw-para-1", (3, 3), (Some(6), Some(10)), "");
+        validate_range(markdown, &mut single).unwrap();
+        assert_eq!(single.quote, "line");
+
+        let mut across = range("mkw-para-1", (3, 4), (Some(6), Some(6)), "");
+        validate_range(markdown, &mut across).unwrap();
+        assert_eq!(across.quote, "line\nsecond");
+
+        let mut wrong = range("mkw-para-1", (3, 3), (Some(0), Some(5)), "Other");
+        assert!(validate_range(markdown, &mut wrong).is_err());
+        let mut outside = range("mkw-para-0", (3, 3), (None, None), "");
+        assert!(validate_range(markdown, &mut outside).is_err());
+        let mut past_end = range("mkw-para-1", (3, 3), (Some(6), Some(40)), "");
+        assert!(validate_range(markdown, &mut past_end).is_err());
+    }
+
+    #[test]
+    fn offsets_count_utf16_units() {
+        // "🚀" is two UTF-16 units and four bytes
+        let markdown = "🚀 Launch é now\n";
+        let mut launch = range("mkw-para-0", (1, 1), (Some(3), Some(9)), "");
+        validate_range(markdown, &mut launch).unwrap();
+        assert_eq!(launch.quote, "Launch");
+        let mut split_pair = range("mkw-para-0", (1, 1), (Some(1), Some(9)), "");
+        assert!(validate_range(markdown, &mut split_pair).is_err());
+
+        let (_, block) = crate::markdown::viewer_blocks(markdown).remove(0);
+        let (found, _) = find_quote("mkw-para-0", &block, &range("mkw-para-0", (1, 1), (None, None), "é now")).unwrap();
+        assert_eq!((found.start_char, found.end_char), (Some(10), Some(15)));
+    }
+
+    #[test]
+    fn find_quote_prefers_the_occurrence_nearest_the_old_one() {
+        let markdown = "retry the call\nthen retry the call\n";
+        let (_, block) = crate::markdown::viewer_blocks(markdown).remove(0);
+        let previous = range("mkw-para-0", (2, 2), (Some(5), Some(10)), "retry");
+        let (found, ambiguous) = find_quote("mkw-para-0", &block, &previous).unwrap();
+        assert_eq!((found.start_line, found.start_char, found.end_char), (2, Some(5), Some(10)));
+        assert!(ambiguous);
+
+        let unique = range("mkw-para-0", (1, 1), (Some(0), Some(4)), "then");
+        let (found, ambiguous) = find_quote("mkw-para-0", &block, &unique).unwrap();
+        assert_eq!((found.start_line, found.start_char), (2, Some(0)));
+        assert!(!ambiguous);
+
+        assert!(find_quote("mkw-para-0", &block, &range("mkw-para-0", (1, 1), (None, None), "gone")).is_none());
+    }
+}
@@ -0,0 +1,394 @@
+//! Portable session bundles for sharing a session between machines.
+//!
+//! A bundle is a single JSON file holding the `sessions` row, every message
+//! (with tool payloads and update history), the plan file, its review
+//! comments, provider metadata and a rendered Markdown transcript. Importing
+//! assigns fresh ids to the session, messages and comments so a bundle can be
+//! imported any number of times without touching local records.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::comments::Comment;
+use crate::messages::MessageRecord;
+use crate::sessions::{SessionRecord, WorkspaceRecord};
+
+const BUNDLE_FORMAT: &str = "arandu-session";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundleWorkspace {
+    pub path: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderMetadata {
+    pub provider: String,
+    /// Provider-side session id on the exporting machine; not reused on import
+    pub acp_session_id: Option<String>,
+    pub acp_preferences: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub workspace: BundleWorkspace,
+    pub session: SessionRecord,
+    pub provider: ProviderMetadata,
+    pub messages: Vec<MessageRecord>,
+    pub plan_markdown: String,
+    pub comments: Vec<Comment>,
+    pub transcript_markdown: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportResult {
+    pub bundle_path: String,
+    pub transcript_path: String,
+}
+
+fn format_time(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+fn quote(text: &str) -> String {
+    text.lines().map(|l| format!("> {}", l)).collect::<Vec<_>>().join("\n")
+}
+
+pub fn render_transcript(
+    session: &SessionRecord,
+    messages: &[MessageRecord],
+    plan_markdown: &str,
+    comments: &[Comment],
+) -> String {
+    let mut out = format!("# {}\n\n", session.name);
+    out.push_str(&format!("- Provider: {}\n", session.provider));
+    out.push_str(&format!("- Phase: {}\n", session.phase));
+    out.push_str(&format!("- Created: {}\n\n", format_time(session.created_at)));
+
+    if !session.initial_prompt.trim().is_empty() {
+        out.push_str(&format!("## Initial prompt\n\n{}\n\n", session.initial_prompt.trim()));
+    }
+    if !plan_markdown.trim().is_empty() {
+        out.push_str(&format!("## Plan\n\n{}\n\n", plan_markdown.trim()));
+    }
+
+    out.push_str("## Conversation\n\n");
+    for message in messages {
+        match message.message_type.as_deref() {
+            Some("tool") => {
+                let title = message.tool_title.as_deref().unwrap_or("Tool call");
+                let status = message.tool_status.as_deref().unwrap_or("unknown");
+                out.push_str(&format!("- Tool: {} ({})\n\n", title, status));
+            }
+            Some("thinking") => {
+                out.push_str(&format!("{}\n\n", quote(&format!("Thinking: {}", message.content.trim()))));
+            }
+            Some("notice") => {
+                out.push_str(&format!("_{}_\n\n", message.content.trim()));
+            }
+            _ => {
+                let who = if message.role == "user" { "User" } else { "Assistant" };
+                out.push_str(&format!("### {} · {}\n\n{}\n\n", who, format_time(message.created_at), message.content.trim()));
+            }
+        }
+    }
+
+    if !comments.is_empty() {
+        out.push_str("## Review comments\n\n");
+        for comment in comments.iter().filter(|c| c.parent_id.is_none()) {
+            let mark = if comment.resolved { "x" } else { " " };
+            out.push_str(&format!("- [{}] {}\n", mark, comment.text.trim()));
+            for reply in comments.iter().filter(|r| r.parent_id.as_deref() == Some(comment.id.as_str())) {
+                let author = reply.author.as_deref().unwrap_or(&reply.author_kind);
+                out.push_str(&format!("  - {}: {}\n", author, reply.text.trim()));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Comment file paths the session's plan may be stored under.
+fn plan_comment_paths(app_data_dir: &PathBuf, session: &SessionRecord) -> Vec<String> {
+    let default = crate::plan_file::get_plan_path(app_data_dir, &session.id)
+        .to_string_lossy()
+        .to_string();
+    let mut paths = vec![default];
+    if let Some(path) = &session.plan_file_path {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+    paths
+}
+
+pub fn build_bundle(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<SessionBundle, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    let workspace = crate::sessions::get_workspace(conn, &session.workspace_id)?;
+
+    let total = crate::messages::count_session_messages(conn, session_id)?;
+    let mut messages = crate::messages::list_messages(conn, session_id, 0, total.max(1))?;
+    for message in messages.iter_mut().filter(|m| m.tool_call_id.is_some()) {
+        message.tool_history = Some(crate::messages::list_tool_history(conn, &message.id)?);
+    }
+
+    let plan_markdown = crate::plan_storage::read_session_plan(conn, app_data_dir, session_id)?;
+    let mut comments = Vec::new();
+    for path in plan_comment_paths(app_data_dir, &session) {
+        comments.extend(crate::comments::load_comments(conn, &path)?.comments);
+    }
+
+    let transcript_markdown = render_transcript(&session, &messages, &plan_markdown, &comments);
+    let provider = ProviderMetadata {
+        provider: session.provider.clone(),
+        acp_session_id: session.acp_session_id.clone(),
+        acp_preferences: serde_json::from_str(&session.acp_preferences_json).unwrap_or_default(),
+    };
+
+    Ok(SessionBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: crate::comments::now(),
+        workspace: BundleWorkspace { path: workspace.path, display_name: workspace.display_name },
+        session,
+        provider,
+        messages,
+        plan_markdown,
+        comments,
+        transcript_markdown,
+    })
+}
+
+pub fn parse_bundle(json: &str) -> Result<SessionBundle, String> {
+    let bundle: SessionBundle = serde_json::from_str(json)
+        .map_err(|e| format!("Invalid session bundle: {}", e))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(format!("Not a session bundle (format: {})", bundle.format));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!("Unsupported bundle version {}; update the app to import it", bundle.version));
+    }
+    Ok(bundle)
+}
+
+/// Inserts the bundle as a new session of `workspace` with fresh ids. Returns
+/// the new session; the caller writes the plan file.
+pub fn import_bundle(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    bundle: &SessionBundle,
+    workspace: &WorkspaceRecord,
+) -> Result<SessionRecord, String> {
+    let provider = bundle.session.provider.as_str();
+    if !crate::sessions::VALID_PROVIDERS.contains(&provider) {
+        return Err(format!("Unsupported provider in bundle: {}", provider));
+    }
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let plan_path = crate::plan_file::get_plan_path(app_data_dir, &session_id)
+        .to_string_lossy()
+        .to_string();
+    let has_plan = !bundle.plan_markdown.is_empty();
+    let now = crate::comments::now();
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+
+    // The provider session only exists on the exporting machine, so the
+    // imported session starts without one
+    tx.execute(
+        "INSERT INTO sessions (id, workspace_id, acp_session_id, provider, name, initial_prompt,
+                               plan_file_path, phase, acp_preferences_json, created_at, updated_at)
+         VALUES (?1, ?2, NULL, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            session_id,
+            workspace.id,
+            provider,
+            bundle.session.name,
+            bundle.session.initial_prompt,
+            has_plan.then_some(&plan_path),
+            bundle.session.phase,
+            bundle.session.acp_preferences_json,
+            bundle.session.created_at,
+            now,
+        ],
+    )
+    .map_err(|e| format!("Insert session error: {}", e))?;
+    for tag in &bundle.session.tags {
+        crate::sessions::add_tag(&tx, &session_id, tag)?;
+    }
+
+    for message in &bundle.messages {
+        let message_id = uuid::Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO messages (id, session_id, role, content, message_type, tool_call_id, tool_title,
+                                   tool_status, tool_payload, started_at, completed_at, created_at, stream_state)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                message_id,
+                session_id,
+                message.role,
+                message.content,
+                message.message_type,
+                message.tool_call_id,
+                message.tool_title,
+                message.tool_status,
+                message.tool_payload.as_ref().map(|p| p.to_string()),
+                message.started_at,
+                message.completed_at,
+                message.created_at,
+                message.stream_state,
+            ],
+        )
+        .map_err(|e| format!("Insert message error: {}", e))?;
+
+        for update in message.tool_history.iter().flatten() {
+            tx.execute(
+                "INSERT INTO tool_call_updates (message_id, payload, received_at) VALUES (?1, ?2, ?3)",
+                params![message_id, update.payload.to_string(), update.received_at],
+            )
+            .map_err(|e| format!("Insert tool history error: {}", e))?;
+        }
+    }
+
+    let comment_ids: HashMap<&str, String> = bundle
+        .comments
+        .iter()
+        .map(|c| (c.id.as_str(), uuid::Uuid::new_v4().to_string()))
+        .collect();
+    // Replies may come before the comments they answer
+    tx.pragma_update(None, "defer_foreign_keys", "ON")
+        .map_err(|e| format!("Pragma error: {}", e))?;
+    for comment in &bundle.comments {
+        let comment_id = &comment_ids[comment.id.as_str()];
+        let parent_id = comment.parent_id.as_deref().and_then(|p| comment_ids.get(p));
+        tx.execute(
+            "INSERT INTO comments (id, workspace_id, file_path, text, resolved, created_at,
+                                   parent_id, author, author_kind, edited_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                comment_id,
+                workspace.id,
+                plan_path,
+                comment.text,
+                comment.resolved as i32,
+                comment.timestamp,
+                parent_id,
+                comment.author,
+                comment.author_kind,
+                comment.edited_at,
+            ],
+        )
+        .map_err(|e| format!("Insert comment error: {}", e))?;
+        for block_id in &comment.block_ids {
+            let range = comment.ranges.iter().find(|r| r.block_id == *block_id);
+            tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO comment_blocks (comment_id, block_id, {})
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    crate::anchors::RANGE_COLUMNS
+                ),
+                params![
+                    comment_id,
+                    block_id,
+                    range.map(|r| r.start_line),
+                    range.map(|r| r.end_line),
+                    range.and_then(|r| r.start_char),
+                    range.and_then(|r| r.end_char),
+                    range.map(|r| &r.quote),
+                ],
+            )
+            .map_err(|e| format!("Insert comment block error: {}", e))?;
+        }
+    }
+
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    crate::sessions::get_session(conn, &session_id)
+}
+
+fn transcript_path_for(bundle_path: &Path) -> PathBuf {
+    bundle_path.with_extension("md")
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::Manager;
+
+/// Writes the bundle to `path` and the Markdown transcript next to it.
+#[tauri::command]
+pub fn session_export(
+    session_id: String,
+    path: String,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<ExportResult, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let bundle = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        build_bundle(&conn, &app_data, &session_id)?
+    };
+
+    let bundle_path = PathBuf::from(&path);
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&bundle_path, json)
+        .map_err(|e| format!("Failed to write bundle: {}", e))?;
+    let transcript_path = transcript_path_for(&bundle_path);
+    std::fs::write(&transcript_path, &bundle.transcript_markdown)
+        .map_err(|e| format!("Failed to write transcript: {}", e))?;
+
+    Ok(ExportResult {
+        bundle_path: bundle_path.to_string_lossy().to_string(),
+        transcript_path: transcript_path.to_string_lossy().to_string(),
+    })
+}
+
+/// Imports a bundle into an existing workspace (`workspace_id`) or into the
+/// workspace at `workspace_path`, creating it if needed. Without either, the
+/// exporter's workspace path is used.
+#[tauri::command]
+pub fn session_import(
+    path: String,
+    workspace_id: Option<String>,
+    workspace_path: Option<String>,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<SessionRecord, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read bundle: {}", e))?;
+    let bundle = parse_bundle(&json)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let workspace = match workspace_id {
+        Some(id) => crate::sessions::get_workspace(&conn, &id)?,
+        None => {
+            let target = workspace_path.unwrap_or_else(|| bundle.workspace.path.clone());
+            let display_name = Path::new(&target)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| bundle.workspace.display_name.clone());
+            let new_id = uuid::Uuid::new_v4().to_string();
+            crate::sessions::upsert_workspace(&conn, &new_id, &target, &display_name, "directory")?
+        }
+    };
+
+    let session = import_bundle(&conn, &app_data, &bundle, &workspace)?;
+    if !bundle.plan_markdown.is_empty() {
+        crate::plan_storage::write_session_plan(&conn, &app_data, &session.id, &bundle.plan_markdown)?;
+        crate::plan_history::record_revision(&conn, &session.id, &bundle.plan_markdown, "import")?;
+        return crate::sessions::get_session(&conn, &session.id);
+    }
+    Ok(session)
+}
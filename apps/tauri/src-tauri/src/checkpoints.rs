@@ -0,0 +1,542 @@
+//! Checkpoints of files edited by agent tool calls.
+//!
+//! When an edit-kind tool call starts, the files it is about to touch are
+//! snapshotted into `checkpoint_files`. Paths come from the ACP `locations`
+//! (or Claude's `file_path` input); when a tool call names no paths, the
+//! session root is scanned by content hash before and after the call and only
+//! the files that changed are stored. Scans run on a blocking worker, off the
+//! provider's reader loop and without holding the database lock, and keep
+//! file contents only up to a total budget. Restoring a checkpoint rolls the
+//! session's files back to their state just before it.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// ACP tool kinds that modify files.
+const EDIT_KINDS: [&str; 3] = ["edit", "delete", "move"];
+/// Claude tools that modify files.
+const CLAUDE_EDIT_TOOLS: [&str; 4] = ["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// Limits for the workspace-wide scan fallback.
+const SCAN_MAX_FILES: usize = 5000;
+const SCAN_MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Contents kept in memory per scan; files past it are tracked by hash only.
+const SCAN_MAX_TOTAL_BYTES: u64 = 32 * 1024 * 1024;
+const SCAN_SKIP_DIRS: [&str; 6] = [".git", ".arandu", "node_modules", "target", "dist", "build"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CheckpointFile {
+    pub path: String,
+    /// False when the tool call created the file
+    pub existed_before: bool,
+    /// True when the tool call deleted the file
+    pub deleted_after: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CheckpointRecord {
+    pub id: String,
+    pub session_id: String,
+    pub turn_message_id: Option<String>,
+    pub tool_call_id: String,
+    pub tool_title: Option<String>,
+    pub created_at: i64,
+    pub completed_at: Option<i64>,
+    pub files: Vec<CheckpointFile>,
+}
+
+/// Checkpoints created while answering one user message.
+#[derive(Debug, Serialize, Clone)]
+pub struct TurnCheckpoints {
+    pub turn_message_id: Option<String>,
+    pub checkpoints: Vec<CheckpointRecord>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RestoreResult {
+    pub restored: Vec<String>,
+    /// Files changed outside the agent since its last edit; nothing is
+    /// restored when this is non-empty unless the restore is forced
+    pub conflicts: Vec<String>,
+}
+
+/// Content hash of each scanned file, with its bytes while the scan's content
+/// budget lasts.
+type Snapshot = HashMap<PathBuf, (String, Option<Vec<u8>>)>;
+
+/// Pre-call scan of a tool call that named no paths.
+pub struct PendingScan {
+    root: PathBuf,
+    /// The scan, which may still be running
+    before: tauri::async_runtime::JoinHandle<Snapshot>,
+}
+
+/// Pre-call scans for tool calls that named no paths, keyed by
+/// `(session_id, tool_call_id)`.
+#[derive(Default)]
+pub struct CheckpointState(pub Mutex<HashMap<(String, String), PendingScan>>);
+
+pub(crate) fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn hash_path(path: &Path) -> Option<String> {
+    std::fs::read(path).ok().map(|b| hash_bytes(&b))
+}
+
+fn scan_dir(dir: &Path, out: &mut Snapshot, budget: &mut u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        if out.len() >= SCAN_MAX_FILES {
+            return;
+        }
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if !SCAN_SKIP_DIRS.iter().any(|s| name == *s) {
+                scan_dir(&path, out, budget);
+            }
+        } else if file_type.is_file() {
+            let small = entry.metadata().map(|m| m.len() <= SCAN_MAX_FILE_BYTES).unwrap_or(false);
+            if small {
+                if let Ok(bytes) = std::fs::read(&path) {
+                    let hash = hash_bytes(&bytes);
+                    let len = bytes.len() as u64;
+                    let bytes = if len <= *budget {
+                        *budget -= len;
+                        Some(bytes)
+                    } else {
+                        None
+                    };
+                    out.insert(path, (hash, bytes));
+                }
+            }
+        }
+    }
+}
+
+fn scan(root: &Path) -> Snapshot {
+    let mut out = HashMap::new();
+    let mut budget = SCAN_MAX_TOTAL_BYTES;
+    scan_dir(root, &mut out, &mut budget);
+    out
+}
+
+/// Directory the session's agent edits: its worktree, else the workspace.
+pub(crate) fn session_root(conn: &Connection, session_id: &str) -> Option<PathBuf> {
+    let session = crate::sessions::get_session(conn, session_id).ok()?;
+    let root = match session.worktree_path {
+        Some(path) => path,
+        None => crate::sessions::get_workspace(conn, &session.workspace_id).ok()?.path,
+    };
+    Some(PathBuf::from(root))
+}
+
+pub(crate) fn current_turn(conn: &Connection, session_id: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT id FROM messages WHERE session_id = ?1 AND role = 'user'
+         ORDER BY seq DESC LIMIT 1",
+        params![session_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+fn resolve(root: &Path, path: &str) -> PathBuf {
+    let p = Path::new(path);
+    if p.is_absolute() { p.to_path_buf() } else { root.join(p) }
+}
+
+fn insert_checkpoint(
+    conn: &Connection,
+    session_id: &str,
+    tool_call_id: &str,
+    tool_title: Option<&str>,
+) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO checkpoints (id, session_id, turn_message_id, tool_call_id, tool_title, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, session_id, current_turn(conn, session_id), tool_call_id, tool_title, crate::comments::now()],
+    )
+    .map_err(|e| format!("Insert checkpoint error: {}", e))?;
+    Ok(id)
+}
+
+fn insert_file(conn: &Connection, checkpoint_id: &str, path: &Path, before: Option<&[u8]>) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO checkpoint_files (checkpoint_id, path, before_content, before_hash)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![checkpoint_id, path.to_string_lossy(), before, before.map(hash_bytes)],
+    )
+    .map_err(|e| format!("Insert checkpoint file error: {}", e))?;
+    Ok(())
+}
+
+/// Snapshots the files a tool call is about to edit. With no `paths`, returns
+/// the session root for the caller to scan outside the database lock; files
+/// are then stored on completion.
+pub fn capture(
+    conn: &Connection,
+    session_id: &str,
+    tool_call_id: &str,
+    tool_title: Option<&str>,
+    paths: &[String],
+) -> Result<Option<PathBuf>, String> {
+    let Some(root) = session_root(conn, session_id) else { return Ok(None) };
+    let already: bool = conn
+        .query_row(
+            "SELECT 1 FROM checkpoints WHERE session_id = ?1 AND tool_call_id = ?2",
+            params![session_id, tool_call_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| format!("Query error: {}", e))?
+        .is_some();
+    if already {
+        return Ok(None);
+    }
+
+    let id = insert_checkpoint(conn, session_id, tool_call_id, tool_title)?;
+    if paths.is_empty() {
+        return Ok(Some(root));
+    }
+
+    for path in paths {
+        let path = resolve(&root, path);
+        let before = std::fs::read(&path).ok();
+        insert_file(conn, &id, &path, before.as_deref())?;
+    }
+    eprintln!("[checkpoints] tool_call={} captured {} files", tool_call_id, paths.len());
+    Ok(None)
+}
+
+/// Records the post-edit state of a checkpoint once its tool call finished.
+/// `scans` holds the before and after scans of a call that named no paths.
+pub fn complete(
+    conn: &Connection,
+    session_id: &str,
+    tool_call_id: &str,
+    scans: Option<(&Snapshot, &Snapshot)>,
+) -> Result<(), String> {
+    let id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM checkpoints WHERE session_id = ?1 AND tool_call_id = ?2 AND completed_at IS NULL",
+            params![session_id, tool_call_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Query error: {}", e))?;
+    let Some(id) = id else { return Ok(()) };
+
+    if let Some((before, after)) = scans {
+        for (path, (hash, bytes)) in before {
+            if after.get(path).map(|(h, _)| h) == Some(hash) {
+                continue;
+            }
+            match bytes {
+                Some(bytes) => insert_file(conn, &id, path, Some(bytes))?,
+                None => eprintln!(
+                    "[checkpoints] tool_call={} changed {} past the scan budget; it can't be restored",
+                    tool_call_id,
+                    path.display()
+                ),
+            }
+        }
+        for path in after.keys().filter(|p| !before.contains_key(*p)) {
+            insert_file(conn, &id, path, None)?;
+        }
+    }
+
+    let paths: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT path FROM checkpoint_files WHERE checkpoint_id = ?1")
+            .map_err(|e| format!("Query prepare error: {}", e))?;
+        let rows = stmt
+            .query_map(params![id], |row| row.get(0))
+            .map_err(|e| format!("Query error: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?;
+        rows
+    };
+    for path in paths {
+        conn.execute(
+            "UPDATE checkpoint_files SET after_hash = ?1 WHERE checkpoint_id = ?2 AND path = ?3",
+            params![hash_path(Path::new(&path)), id, path],
+        )
+        .map_err(|e| format!("Update checkpoint file error: {}", e))?;
+    }
+    conn.execute(
+        "UPDATE checkpoints SET completed_at = ?1 WHERE id = ?2",
+        params![crate::comments::now(), id],
+    )
+    .map_err(|e| format!("Update checkpoint error: {}", e))?;
+    Ok(())
+}
+
+pub fn list_checkpoints(conn: &Connection, session_id: &str) -> Result<Vec<TurnCheckpoints>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, turn_message_id, tool_call_id, tool_title, created_at, completed_at
+             FROM checkpoints WHERE session_id = ?1 ORDER BY created_at ASC, rowid ASC",
+        )
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let checkpoints = stmt
+        .query_map(params![session_id], |row| {
+            Ok(CheckpointRecord {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                turn_message_id: row.get(2)?,
+                tool_call_id: row.get(3)?,
+                tool_title: row.get(4)?,
+                created_at: row.get(5)?,
+                completed_at: row.get(6)?,
+                files: Vec::new(),
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    let mut file_stmt = conn
+        .prepare(
+            "SELECT path, before_content IS NOT NULL, after_hash IS NULL
+             FROM checkpoint_files WHERE checkpoint_id = ?1 ORDER BY path",
+        )
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+
+    let mut turns: Vec<TurnCheckpoints> = Vec::new();
+    for mut checkpoint in checkpoints {
+        let completed = checkpoint.completed_at.is_some();
+        checkpoint.files = file_stmt
+            .query_map(params![checkpoint.id], |row| {
+                Ok(CheckpointFile {
+                    path: row.get(0)?,
+                    existed_before: row.get(1)?,
+                    deleted_after: completed && row.get::<_, bool>(2)?,
+                })
+            })
+            .map_err(|e| format!("Query error: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?;
+
+        match turns.last_mut() {
+            Some(turn) if turn.turn_message_id == checkpoint.turn_message_id => turn.checkpoints.push(checkpoint),
+            _ => turns.push(TurnCheckpoints {
+                turn_message_id: checkpoint.turn_message_id.clone(),
+                checkpoints: vec![checkpoint],
+            }),
+        }
+    }
+    Ok(turns)
+}
+
+/// Rolls every file touched by `checkpoint_id` or any later checkpoint of the
+/// session back to its state just before that checkpoint, then drops those
+/// checkpoints. A file counts as a conflict when its current content differs
+/// from what the agent last wrote to it.
+pub fn restore_checkpoint(conn: &Connection, checkpoint_id: &str, force: bool) -> Result<RestoreResult, String> {
+    let (session_id, rowid): (String, i64) = conn
+        .query_row(
+            "SELECT session_id, rowid FROM checkpoints WHERE id = ?1",
+            params![checkpoint_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Checkpoint not found: {}", e))?;
+
+    // Ordered oldest first: the first row per path holds the state to restore,
+    // the last completed row holds the content the agent last wrote
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.id, f.path, f.before_content, f.after_hash, c.completed_at IS NOT NULL
+             FROM checkpoints c JOIN checkpoint_files f ON f.checkpoint_id = c.id
+             WHERE c.session_id = ?1 AND c.rowid >= ?2
+             ORDER BY c.rowid ASC",
+        )
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id, rowid], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<Vec<u8>>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, bool>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    let mut targets: Vec<(String, Option<Vec<u8>>)> = Vec::new();
+    let mut last_written: HashMap<String, Option<String>> = HashMap::new();
+    let mut checkpoint_ids: Vec<String> = Vec::new();
+    for (id, path, before, after_hash, completed) in rows {
+        if !targets.iter().any(|(p, _)| *p == path) {
+            targets.push((path.clone(), before));
+        }
+        if completed {
+            last_written.insert(path, after_hash);
+        }
+        if !checkpoint_ids.contains(&id) {
+            checkpoint_ids.push(id);
+        }
+    }
+
+    let conflicts: Vec<String> = targets
+        .iter()
+        .filter(|(path, _)| match last_written.get(path) {
+            Some(expected) => hash_path(Path::new(path)) != *expected,
+            None => false,
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    if !conflicts.is_empty() && !force {
+        return Ok(RestoreResult { restored: Vec::new(), conflicts });
+    }
+
+    let mut restored = Vec::new();
+    for (path, before) in targets {
+        let p = Path::new(&path);
+        match before {
+            Some(bytes) => {
+                if let Some(parent) = p.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                }
+                std::fs::write(p, bytes).map_err(|e| format!("Failed to restore {}: {}", path, e))?;
+            }
+            None => {
+                if p.exists() {
+                    std::fs::remove_file(p).map_err(|e| format!("Failed to remove {}: {}", path, e))?;
+                }
+            }
+        }
+        restored.push(path);
+    }
+
+    for id in &checkpoint_ids {
+        conn.execute("DELETE FROM checkpoints WHERE id = ?1", params![id])
+            .map_err(|e| format!("Delete checkpoint error: {}", e))?;
+    }
+    eprintln!("[checkpoints] Restored {} files from checkpoint {}", restored.len(), checkpoint_id);
+    Ok(RestoreResult { restored, conflicts })
+}
+
+// --- Hooks for provider connections ---
+
+fn with_db<T>(app: &AppHandle, f: impl FnOnce(&Connection) -> Result<T, String>) -> Option<T> {
+    let db = app.try_state::<CommentsDb>()?;
+    let conn = db.0.lock().ok()?;
+    f(&conn).map_err(|e| eprintln!("[checkpoints] {}", e)).ok()
+}
+
+/// Captures a checkpoint, starting a scan of the session root in the
+/// background when the tool call named no paths.
+fn capture_and_scan(app: &AppHandle, session_id: &str, tool_call_id: &str, title: Option<&str>, paths: &[String]) {
+    let Some(state) = app.try_state::<CheckpointState>() else { return };
+    let Some(Some(root)) = with_db(app, |conn| capture(conn, session_id, tool_call_id, title, paths)) else {
+        return;
+    };
+    let scan_root = root.clone();
+    let before = tauri::async_runtime::spawn_blocking(move || scan(&scan_root));
+    match state.0.lock() {
+        Ok(mut pending) => {
+            pending.insert((session_id.to_string(), tool_call_id.to_string()), PendingScan { root, before });
+        }
+        Err(e) => eprintln!("[checkpoints] {}", e),
+    };
+}
+
+/// Handles an ACP `tool_call` update: snapshots files for edit kinds.
+pub fn on_acp_tool_call(app: &AppHandle, session_id: &str, payload: &serde_json::Value) {
+    let kind = payload.get("kind").and_then(|k| k.as_str()).unwrap_or("");
+    let Some(tool_call_id) = payload.get("toolCallId").and_then(|v| v.as_str()) else { return };
+    if !EDIT_KINDS.contains(&kind) {
+        return;
+    }
+    let mut paths: Vec<String> = payload
+        .get("locations")
+        .and_then(|l| l.as_array())
+        .map(|l| l.iter().filter_map(|loc| loc.get("path")?.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    // Diff content items also name the file being edited
+    if let Some(items) = payload.get("content").and_then(|c| c.as_array()) {
+        for item in items {
+            if item.get("type").and_then(|t| t.as_str()) == Some("diff") {
+                if let Some(path) = item.get("path").and_then(|p| p.as_str()) {
+                    paths.push(path.to_string());
+                }
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    let title = payload.get("title").and_then(|v| v.as_str());
+    capture_and_scan(app, session_id, tool_call_id, title, &paths);
+}
+
+/// Handles a Claude `tool_use` block: snapshots files for editing tools.
+pub fn on_claude_tool_use(app: &AppHandle, session_id: &str, tool_use_id: &str, name: &str, input: &serde_json::Value) {
+    if !CLAUDE_EDIT_TOOLS.contains(&name) {
+        return;
+    }
+    let paths: Vec<String> = ["file_path", "notebook_path"]
+        .iter()
+        .filter_map(|k| input.get(*k).and_then(|p| p.as_str()).map(String::from))
+        .collect();
+    capture_and_scan(app, session_id, tool_use_id, Some(name), &paths);
+}
+
+/// Marks a tool call finished (completed or failed) and records file states.
+/// A call that was scanned is finished once both scans are done.
+pub fn on_tool_call_finished(app: &AppHandle, session_id: &str, tool_call_id: &str) {
+    let pending = app
+        .try_state::<CheckpointState>()
+        .and_then(|state| state.0.lock().ok()?.remove(&(session_id.to_string(), tool_call_id.to_string())));
+    let Some(PendingScan { root, before }) = pending else {
+        with_db(app, |conn| complete(conn, session_id, tool_call_id, None));
+        return;
+    };
+    let (app, session_id, tool_call_id) = (app.clone(), session_id.to_string(), tool_call_id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let before = before.await.map_err(|e| eprintln!("[checkpoints] scan failed: {}", e)).ok();
+        let after = tauri::async_runtime::spawn_blocking(move || scan(&root))
+            .await
+            .map_err(|e| eprintln!("[checkpoints] scan failed: {}", e))
+            .ok();
+        let scans = before.as_ref().zip(after.as_ref());
+        with_db(&app, |conn| complete(conn, &session_id, &tool_call_id, scans));
+    });
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Manager};
+
+#[tauri::command]
+pub fn checkpoint_list(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<TurnCheckpoints>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_checkpoints(&conn, &session_id)
+}
+
+#[tauri::command]
+pub fn checkpoint_restore(
+    checkpoint_id: String,
+    force: Option<bool>,
+    db: tauri::State<CommentsDb>,
+) -> Result<RestoreResult, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    restore_checkpoint(&conn, &checkpoint_id, force.unwrap_or(false))
+}
@@ -1,5 +1,7 @@
-use rusqlite::{params, Connection};
+use crate::anchors::TextRange;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
@@ -12,6 +14,46 @@ pub struct Comment {
     pub text: String,
     pub timestamp: i64,
     pub resolved: bool,
+    /// The comment this one replies to; replies carry no blocks of their own
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// User name, or the provider name for agent replies
+    #[serde(default)]
+    pub author: Option<String>,
+    /// `user` or `agent`
+    #[serde(default = "default_author_kind")]
+    pub author_kind: String,
+    #[serde(default)]
+    pub edited_at: Option<i64>,
+    /// Least certain of the comment's anchors since the document last
+    /// changed: `anchored`, `relocated`, `low_confidence` or `orphaned`
+    #[serde(default = "default_anchor_status")]
+    pub anchor_status: String,
+    /// Spans of source text the comment is about, at most one per block; a
+    /// block without one is commented as a whole
+    #[serde(default)]
+    pub ranges: Vec<TextRange>,
+}
+
+fn default_author_kind() -> String {
+    "user".to_string()
+}
+
+fn default_anchor_status() -> String {
+    crate::anchors::ANCHORED.to_string()
+}
+
+/// A comment to add; `timestamp`, ids and `resolved` are filled in.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct NewComment {
+    #[serde(default)]
+    pub block_ids: Vec<String>,
+    pub text: String,
+    pub parent_id: Option<String>,
+    pub author: Option<String>,
+    pub author_kind: Option<String>,
+    #[serde(default)]
+    pub ranges: Vec<TextRange>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,6 +83,15 @@ fn has_table(conn: &Connection, name: &str) -> bool {
         .is_ok()
 }
 
+fn table_sql(conn: &Connection, name: &str) -> String {
+    conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type='table' AND name=?1",
+        params![name],
+        |row| row.get::<_, String>(0),
+    )
+    .unwrap_or_default()
+}
+
 fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
     conn.prepare(&format!("SELECT {} FROM {} LIMIT 0", column, table))
         .is_ok()
@@ -94,9 +145,351 @@ pub fn init_db(app_data_dir: &PathBuf) -> Result<Connection, String> {
         ).map_err(|e| format!("Failed to create workspace_acp_defaults table: {}", e))?;
     }
 
+    if !has_table(&conn, "comparisons") {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS comparisons (
+                id              TEXT    PRIMARY KEY,
+                workspace_id    TEXT    NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+                name            TEXT    NOT NULL,
+                prompt          TEXT    NOT NULL,
+                created_at      INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_comparisons_workspace ON comparisons(workspace_id, created_at DESC);"
+        ).map_err(|e| format!("Failed to create comparisons table: {}", e))?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "comparison_id") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN comparison_id TEXT REFERENCES comparisons(id) ON DELETE SET NULL;
+             CREATE INDEX IF NOT EXISTS idx_sessions_comparison ON sessions(comparison_id);"
+        ).map_err(|e| format!("Failed to add comparison_id column: {}", e))?;
+    }
+
+    // Providers are validated in Rust (sessions::VALID_PROVIDERS); older schemas
+    // hard-coded them in a CHECK constraint that SQLite can only drop by rebuilding.
+    if table_sql(&conn, "sessions").contains("CHECK (provider IN") {
+        migrate_sessions_drop_provider_check(&conn)?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "worktree_path") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN worktree_path TEXT;
+             ALTER TABLE sessions ADD COLUMN worktree_branch TEXT;"
+        ).map_err(|e| format!("Failed to add worktree columns: {}", e))?;
+    }
+
+    if !has_table(&conn, "checkpoints") {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                id              TEXT    PRIMARY KEY,
+                session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+                turn_message_id TEXT,
+                tool_call_id    TEXT    NOT NULL,
+                tool_title      TEXT,
+                created_at      INTEGER NOT NULL,
+                completed_at    INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_checkpoints_session ON checkpoints(session_id, tool_call_id);
+
+            CREATE TABLE IF NOT EXISTS checkpoint_files (
+                checkpoint_id   TEXT    NOT NULL REFERENCES checkpoints(id) ON DELETE CASCADE,
+                path            TEXT    NOT NULL,
+                before_content  BLOB,
+                before_hash     TEXT,
+                after_hash      TEXT,
+                PRIMARY KEY (checkpoint_id, path)
+            );"
+        ).map_err(|e| format!("Failed to create checkpoints tables: {}", e))?;
+    }
+
+    if !has_table(&conn, "tool_call_diffs") {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tool_call_diffs (
+                id              TEXT    PRIMARY KEY,
+                message_id      TEXT    NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+                session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+                turn_message_id TEXT,
+                tool_call_id    TEXT    NOT NULL,
+                path            TEXT    NOT NULL,
+                old_text        TEXT,
+                new_text        TEXT    NOT NULL,
+                status          TEXT    NOT NULL DEFAULT 'pending'
+                                        CHECK (status IN ('pending', 'accepted', 'rejected', 'partial')),
+                hunks           TEXT    NOT NULL DEFAULT '[]',
+                hunk_decisions  TEXT    NOT NULL DEFAULT '[]',
+                created_at      INTEGER NOT NULL
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_tool_call_diffs_message_path ON tool_call_diffs(message_id, path);
+            CREATE INDEX IF NOT EXISTS idx_tool_call_diffs_turn ON tool_call_diffs(session_id, turn_message_id, status);"
+        ).map_err(|e| format!("Failed to create tool_call_diffs table: {}", e))?;
+    }
+
+    if has_table(&conn, "messages") && !has_column(&conn, "messages", "tool_payload") {
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN tool_payload TEXT;
+             ALTER TABLE messages ADD COLUMN started_at INTEGER;
+             ALTER TABLE messages ADD COLUMN completed_at INTEGER;"
+        ).map_err(|e| format!("Failed to add tool payload columns: {}", e))?;
+    }
+
+    if !has_table(&conn, "tool_call_updates") {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tool_call_updates (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                message_id      TEXT    NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+                payload         TEXT    NOT NULL,
+                received_at     INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_tool_call_updates_message ON tool_call_updates(message_id, id);"
+        ).map_err(|e| format!("Failed to create tool_call_updates table: {}", e))?;
+    }
+
+    if !has_table(&conn, "messages_fts") {
+        create_search_index(&conn)?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "forked_from_session_id") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN forked_from_session_id TEXT REFERENCES sessions(id) ON DELETE SET NULL;
+             ALTER TABLE sessions ADD COLUMN forked_from_message_id TEXT;
+             ALTER TABLE sessions ADD COLUMN context_seed TEXT;"
+        ).map_err(|e| format!("Failed to add fork columns: {}", e))?;
+    }
+
+    if has_table(&conn, "messages") && !has_column(&conn, "messages", "client_message_id") {
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN client_message_id TEXT;
+             CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_client_id
+                 ON messages(session_id, client_message_id) WHERE client_message_id IS NOT NULL;"
+        ).map_err(|e| format!("Failed to add client_message_id column: {}", e))?;
+    }
+
+    if has_table(&conn, "messages") && !has_column(&conn, "messages", "stream_state") {
+        conn.execute_batch(
+            "ALTER TABLE messages ADD COLUMN stream_state TEXT
+                 CHECK (stream_state IS NULL OR stream_state IN ('streaming', 'interrupted'));
+             CREATE INDEX IF NOT EXISTS idx_messages_streaming ON messages(stream_state) WHERE stream_state IS NOT NULL;"
+        ).map_err(|e| format!("Failed to add stream_state column: {}", e))?;
+    }
+
+    if has_table(&conn, "messages") && !has_column(&conn, "messages", "seq") {
+        add_message_sequence(&conn)?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "archived") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN archived INTEGER NOT NULL DEFAULT 0 CHECK (archived IN (0, 1));"
+        ).map_err(|e| format!("Failed to add archived column: {}", e))?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "rewound_at") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN rewound_at INTEGER;"
+        ).map_err(|e| format!("Failed to add rewound_at column: {}", e))?;
+    }
+
+    if has_table(&conn, "sessions") && !has_column(&conn, "sessions", "pinned") {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0 CHECK (pinned IN (0, 1));
+            CREATE TABLE IF NOT EXISTS tags (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                name            TEXT    NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS session_tags (
+                session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+                tag_id          INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                PRIMARY KEY (session_id, tag_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_session_tags_tag ON session_tags(tag_id);"
+        ).map_err(|e| format!("Failed to add pinned/tags: {}", e))?;
+    }
+
+    if has_table(&conn, "comments") && !has_column(&conn, "comments", "parent_id") {
+        conn.execute_batch(
+            "ALTER TABLE comments ADD COLUMN parent_id TEXT REFERENCES comments(id) ON DELETE CASCADE;
+            ALTER TABLE comments ADD COLUMN author TEXT;
+            ALTER TABLE comments ADD COLUMN author_kind TEXT NOT NULL DEFAULT 'user' CHECK (author_kind IN ('user', 'agent'));
+            ALTER TABLE comments ADD COLUMN edited_at INTEGER;
+            CREATE INDEX IF NOT EXISTS idx_comments_parent ON comments(parent_id);"
+        ).map_err(|e| format!("Failed to add comment thread columns: {}", e))?;
+    }
+
+    if has_table(&conn, "comment_blocks") && !has_column(&conn, "comment_blocks", "fingerprint") {
+        conn.execute_batch(
+            "ALTER TABLE comment_blocks ADD COLUMN fingerprint TEXT;
+            ALTER TABLE comment_blocks ADD COLUMN context_before TEXT;
+            ALTER TABLE comment_blocks ADD COLUMN context_after TEXT;
+            ALTER TABLE comment_blocks ADD COLUMN anchor_status TEXT NOT NULL DEFAULT 'anchored'
+                CHECK (anchor_status IN ('anchored', 'relocated', 'low_confidence', 'orphaned'));
+            ALTER TABLE comment_blocks ADD COLUMN confidence REAL;"
+        ).map_err(|e| format!("Failed to add comment anchor columns: {}", e))?;
+    }
+
+    if has_table(&conn, "comment_blocks") && !has_column(&conn, "comment_blocks", "start_line") {
+        conn.execute_batch(
+            "ALTER TABLE comment_blocks ADD COLUMN start_line INTEGER;
+            ALTER TABLE comment_blocks ADD COLUMN end_line INTEGER;
+            ALTER TABLE comment_blocks ADD COLUMN start_char INTEGER;
+            ALTER TABLE comment_blocks ADD COLUMN end_char INTEGER;
+            ALTER TABLE comment_blocks ADD COLUMN quote TEXT;"
+        ).map_err(|e| format!("Failed to add comment range columns: {}", e))?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_phase_events (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+            from_phase      TEXT    NOT NULL,
+            to_phase        TEXT    NOT NULL,
+            reason          TEXT,
+            created_at      INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_phase_events_session ON session_phase_events(session_id, id);"
+    ).map_err(|e| format!("Failed to create session_phase_events: {}", e))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS plan_blobs (
+            hash            TEXT    PRIMARY KEY,
+            content         TEXT    NOT NULL,
+            created_at      INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS plan_revisions (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+            hash            TEXT    NOT NULL REFERENCES plan_blobs(hash),
+            source          TEXT    NOT NULL,
+            created_at      INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_plan_revisions_session ON plan_revisions(session_id, id);"
+    ).map_err(|e| format!("Failed to create plan history tables: {}", e))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS plan_step_status (
+            session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+            step_id         TEXT    NOT NULL,
+            status          TEXT    NOT NULL CHECK (status IN ('pending', 'in_progress', 'done', 'blocked')),
+            tool_call_id    TEXT,
+            updated_at      INTEGER NOT NULL,
+            PRIMARY KEY (session_id, step_id)
+        );"
+    ).map_err(|e| format!("Failed to create plan_step_status: {}", e))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS plan_runs (
+            session_id      TEXT    PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+            status          TEXT    NOT NULL CHECK (status IN ('running', 'awaiting_review', 'completed', 'cancelled')),
+            current_step_id TEXT,
+            created_at      INTEGER NOT NULL,
+            updated_at      INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS plan_run_steps (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id      TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+            step_id         TEXT    NOT NULL,
+            attempt         INTEGER NOT NULL,
+            outcome         TEXT    NOT NULL CHECK (outcome IN ('running', 'finished', 'failed', 'interrupted')),
+            decision        TEXT    CHECK (decision IN ('approved', 'commented', 'retried')),
+            comment         TEXT,
+            error           TEXT,
+            started_at      INTEGER NOT NULL,
+            finished_at     INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_plan_run_steps_session ON plan_run_steps(session_id, id);"
+    ).map_err(|e| format!("Failed to create plan run tables: {}", e))?;
+
+    // Workspaces with a row here keep plans in the repo, under `plans_dir`
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS workspace_plan_settings (
+            workspace_id TEXT PRIMARY KEY REFERENCES workspaces(id) ON DELETE CASCADE,
+            plans_dir    TEXT NOT NULL
+        );"
+    ).map_err(|e| format!("Failed to create workspace_plan_settings: {}", e))?;
+
+    // Review comments sent to the agent, and whether its turn addressed them
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS comment_feedback (
+            comment_id        TEXT    NOT NULL,
+            session_id        TEXT    NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+            client_message_id TEXT    NOT NULL,
+            message_id        TEXT,
+            turn              INTEGER,
+            blocks_json       TEXT    NOT NULL,
+            plan_hash         TEXT    NOT NULL,
+            status            TEXT    NOT NULL CHECK (status IN ('sent', 'addressed', 'unchanged', 'unknown', 'failed')),
+            sent_at           INTEGER NOT NULL,
+            addressed_at      INTEGER,
+            PRIMARY KEY (comment_id, client_message_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_comment_feedback_session ON comment_feedback(session_id, client_message_id);"
+    ).map_err(|e| format!("Failed to create comment_feedback: {}", e))?;
+
     Ok(conn)
 }
 
+/// External-content FTS5 indexes over message text and session name/prompt,
+/// kept in sync by triggers and backfilled from the existing rows.
+///
+/// Both tables have TEXT primary keys, so their implicit rowids may be
+/// renumbered by `VACUUM`. The indexes are keyed by an explicit `search_id`
+/// column instead, assigned on insert and never changed afterwards.
+fn create_search_index(conn: &Connection) -> Result<(), String> {
+    eprintln!("[db] Building full-text search index");
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| format!("Migration transaction error: {}", e))?;
+    for table in ["messages", "sessions"] {
+        tx.execute_batch(&format!(
+            "ALTER TABLE {0} ADD COLUMN search_id INTEGER;
+            UPDATE {0} SET search_id = rowid;
+            CREATE UNIQUE INDEX idx_{0}_search_id ON {0}(search_id);",
+            table
+        )).map_err(|e| format!("Failed to add {}.search_id: {}", table, e))?;
+    }
+
+    // The insert triggers assign `search_id` and index the row in one step so
+    // the index never sees a row without its key
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE messages_fts USING fts5(
+            content, content='messages', content_rowid='search_id', tokenize='unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            UPDATE messages SET search_id = (SELECT COALESCE(MAX(search_id), 0) + 1 FROM messages)
+                WHERE rowid = new.rowid;
+            INSERT INTO messages_fts(rowid, content)
+                SELECT search_id, content FROM messages WHERE rowid = new.rowid;
+        END;
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.search_id, old.content);
+        END;
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+            INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.search_id, old.content);
+            INSERT INTO messages_fts(rowid, content) VALUES (new.search_id, new.content);
+        END;
+
+        CREATE VIRTUAL TABLE sessions_fts USING fts5(
+            name, initial_prompt, content='sessions', content_rowid='search_id', tokenize='unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER sessions_fts_insert AFTER INSERT ON sessions BEGIN
+            UPDATE sessions SET search_id = (SELECT COALESCE(MAX(search_id), 0) + 1 FROM sessions)
+                WHERE rowid = new.rowid;
+            INSERT INTO sessions_fts(rowid, name, initial_prompt)
+                SELECT search_id, name, initial_prompt FROM sessions WHERE rowid = new.rowid;
+        END;
+        CREATE TRIGGER sessions_fts_delete AFTER DELETE ON sessions BEGIN
+            INSERT INTO sessions_fts(sessions_fts, rowid, name, initial_prompt)
+                VALUES ('delete', old.search_id, old.name, old.initial_prompt);
+        END;
+        CREATE TRIGGER sessions_fts_update AFTER UPDATE OF name, initial_prompt ON sessions BEGIN
+            INSERT INTO sessions_fts(sessions_fts, rowid, name, initial_prompt)
+                VALUES ('delete', old.search_id, old.name, old.initial_prompt);
+            INSERT INTO sessions_fts(rowid, name, initial_prompt) VALUES (new.search_id, new.name, new.initial_prompt);
+        END;
+
+        INSERT INTO messages_fts(messages_fts) VALUES ('rebuild');
+        INSERT INTO sessions_fts(sessions_fts) VALUES ('rebuild');"
+    ).map_err(|e| format!("Failed to create search index: {}", e))?;
+    tx.commit().map_err(|e| format!("Migration commit: {}", e))
+}
+
 fn create_schema_v2(conn: &Connection) -> Result<(), String> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS workspaces (
@@ -114,8 +507,7 @@ fn create_schema_v2(conn: &Connection) -> Result<(), String> {
             id              TEXT    PRIMARY KEY,
             workspace_id    TEXT    NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
             acp_session_id  TEXT,
-            provider        TEXT    NOT NULL DEFAULT 'copilot'
-                                    CHECK (provider IN ('copilot', 'claude')),
+            provider        TEXT    NOT NULL DEFAULT 'copilot',
             name            TEXT    NOT NULL,
             initial_prompt  TEXT    NOT NULL DEFAULT '',
             plan_file_path  TEXT,
@@ -375,49 +767,166 @@ fn migrate_v1_to_v2(conn: &Connection) -> Result<(), String> {
     Ok(())
 }
 
+/// Adds `messages.seq`, a strictly increasing insert counter used for stable
+/// ordering and cursor pagination (`created_at` only has second resolution).
+/// Existing rows are numbered in their current order; new rows get the next
+/// value from `sequences` via trigger, so it never goes backwards even after
+/// deletes.
+fn add_message_sequence(conn: &Connection) -> Result<(), String> {
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| format!("Migration transaction error: {}", e))?;
+    tx.execute_batch(
+        "ALTER TABLE messages ADD COLUMN seq INTEGER;
+        UPDATE messages SET seq = numbered.n
+            FROM (SELECT rowid AS r, ROW_NUMBER() OVER (ORDER BY created_at, rowid) AS n FROM messages) AS numbered
+            WHERE numbered.r = messages.rowid;
+        CREATE TABLE IF NOT EXISTS sequences (
+            name            TEXT    PRIMARY KEY,
+            value           INTEGER NOT NULL
+        );
+        INSERT INTO sequences (name, value) SELECT 'messages', COALESCE(MAX(seq), 0) FROM messages;
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_seq ON messages(session_id, seq);
+        CREATE TRIGGER messages_assign_seq AFTER INSERT ON messages WHEN new.seq IS NULL BEGIN
+            UPDATE sequences SET value = value + 1 WHERE name = 'messages';
+            UPDATE messages SET seq = (SELECT value FROM sequences WHERE name = 'messages') WHERE rowid = new.rowid;
+        END;"
+    ).map_err(|e| format!("Failed to add message sequence: {}", e))?;
+    tx.commit().map_err(|e| format!("Migration commit: {}", e))
+}
+
+fn migrate_sessions_drop_provider_check(conn: &Connection) -> Result<(), String> {
+    eprintln!("[db] Rebuilding sessions table without provider CHECK");
+
+    conn.pragma_update(None, "foreign_keys", "OFF")
+        .map_err(|e| format!("Failed to disable FK for migration: {}", e))?;
+
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| format!("Migration transaction error: {}", e))?;
+
+    tx.execute_batch(
+        "CREATE TABLE sessions_v3 (
+            id              TEXT    PRIMARY KEY,
+            workspace_id    TEXT    NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            acp_session_id  TEXT,
+            provider        TEXT    NOT NULL DEFAULT 'copilot',
+            name            TEXT    NOT NULL,
+            initial_prompt  TEXT    NOT NULL DEFAULT '',
+            plan_file_path  TEXT,
+            phase           TEXT    NOT NULL DEFAULT 'idle'
+                                    CHECK (phase IN ('idle', 'planning', 'reviewing', 'executing', 'done')),
+            acp_preferences_json TEXT NOT NULL DEFAULT '{}',
+            comparison_id   TEXT    REFERENCES comparisons(id) ON DELETE SET NULL,
+            created_at      INTEGER NOT NULL,
+            updated_at      INTEGER NOT NULL
+        );
+        INSERT INTO sessions_v3 (id, workspace_id, acp_session_id, provider, name, initial_prompt,
+                                 plan_file_path, phase, acp_preferences_json, comparison_id, created_at, updated_at)
+            SELECT id, workspace_id, acp_session_id, provider, name, initial_prompt,
+                   plan_file_path, phase, acp_preferences_json, comparison_id, created_at, updated_at
+            FROM sessions;
+        DROP TABLE sessions;
+        ALTER TABLE sessions_v3 RENAME TO sessions;
+        CREATE INDEX idx_sessions_workspace ON sessions(workspace_id, updated_at DESC);
+        CREATE INDEX idx_sessions_comparison ON sessions(comparison_id);"
+    ).map_err(|e| format!("Rebuild sessions: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Migration commit: {}", e))?;
+
+    conn.pragma_update(None, "foreign_keys", "ON")
+        .map_err(|e| format!("Failed to re-enable FK: {}", e))?;
+
+    Ok(())
+}
+
+const COMMENT_COLUMNS: &str = "c.id, c.text, c.created_at, c.resolved, c.parent_id, c.author, c.author_kind, c.edited_at";
+
+/// Block ids are loaded separately.
+fn row_to_comment(row: &rusqlite::Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get(0)?,
+        block_ids: Vec::new(),
+        text: row.get(1)?,
+        timestamp: row.get(2)?,
+        resolved: row.get::<_, i32>(3)? != 0,
+        parent_id: row.get(4)?,
+        author: row.get(5)?,
+        author_kind: row.get(6)?,
+        edited_at: row.get(7)?,
+        anchor_status: default_anchor_status(),
+        ranges: Vec::new(),
+    })
+}
+
+/// Fills in the comment's block ids, ranges and least certain anchor status.
+fn load_anchors(conn: &Connection, comment: &mut Comment) -> Result<(), String> {
+    let mut block_stmt = conn
+        .prepare(&format!(
+            "SELECT block_id, anchor_status, {} FROM comment_blocks WHERE comment_id = ?1 ORDER BY rowid",
+            crate::anchors::RANGE_COLUMNS
+        ))
+        .map_err(|e| format!("Prepare block_ids: {}", e))?;
+    let anchors = block_stmt
+        .query_map(params![comment.id], |row| {
+            let block_id: String = row.get(0)?;
+            let range = crate::anchors::row_range(row, &block_id, 2)?;
+            Ok((block_id, row.get::<_, String>(1)?, range))
+        })
+        .map_err(|e| format!("Query block_ids: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row block_ids: {}", e))?;
+    let rank = |status: &str| {
+        [crate::anchors::ORPHANED, crate::anchors::LOW_CONFIDENCE, crate::anchors::RELOCATED]
+            .iter()
+            .position(|s| *s == status)
+            .unwrap_or(3)
+    };
+    if let Some((_, status, _)) = anchors.iter().min_by_key(|(_, status, _)| rank(status)) {
+        comment.anchor_status = status.clone();
+    }
+    comment.ranges = anchors.iter().filter_map(|(_, _, range)| range.clone()).collect();
+    comment.block_ids = anchors.into_iter().map(|(id, _, _)| id).collect();
+    Ok(())
+}
+
+fn stored_hash(conn: &Connection, file_path: &str) -> String {
+    conn.query_row(
+        "SELECT file_hash FROM file_hashes WHERE file_path = ?1",
+        params![file_path],
+        |row| row.get(0),
+    )
+    .unwrap_or_default()
+}
+
+/// Comments on `file_path`. When the file has changed since they were
+/// saved, their anchors are first re-located in its current content.
 pub fn load_comments(conn: &Connection, file_path: &str) -> Result<CommentsData, String> {
-    let file_hash: String = conn
-        .query_row(
-            "SELECT file_hash FROM file_hashes WHERE file_path = ?1",
-            params![file_path],
-            |row| row.get(0),
-        )
-        .unwrap_or_default();
+    let mut file_hash = stored_hash(conn, file_path);
+    if !file_hash.is_empty() {
+        if let Ok(markdown) = std::fs::read_to_string(file_path) {
+            let hash = crate::checkpoints::hash_bytes(markdown.as_bytes());
+            if hash != file_hash {
+                let moved = crate::anchors::reanchor(conn, file_path, &markdown, &hash)?;
+                if moved > 0 {
+                    eprintln!("[comments] re-anchored {} comment block(s) in {}", moved, file_path);
+                }
+                file_hash = stored_hash(conn, file_path);
+            }
+        }
+    }
 
     let mut stmt = conn
         .prepare(
-            "SELECT c.id, c.text, c.created_at, c.resolved
-             FROM comments c WHERE c.file_path = ?1",
+            &format!("SELECT {} FROM comments c WHERE c.file_path = ?1 ORDER BY c.created_at, c.rowid", COMMENT_COLUMNS),
         )
         .map_err(|e| format!("Query prepare error: {}", e))?;
 
-    let comments = stmt
-        .query_map(params![file_path], |row| {
-            let id: String = row.get(0)?;
-            Ok((id, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i32>(3)?))
-        })
+    let mut result = stmt
+        .query_map(params![file_path], row_to_comment)
         .map_err(|e| format!("Query error: {}", e))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Row error: {}", e))?;
-
-    let mut result = Vec::with_capacity(comments.len());
-    for (id, text, created_at, resolved) in comments {
-        let mut block_stmt = conn
-            .prepare("SELECT block_id FROM comment_blocks WHERE comment_id = ?1")
-            .map_err(|e| format!("Prepare block_ids: {}", e))?;
-        let block_ids: Vec<String> = block_stmt
-            .query_map(params![id], |row| row.get(0))
-            .map_err(|e| format!("Query block_ids: {}", e))?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| format!("Row block_ids: {}", e))?;
-
-        result.push(Comment {
-            id,
-            block_ids,
-            text,
-            timestamp: created_at,
-            resolved: resolved != 0,
-        });
+    for comment in result.iter_mut() {
+        load_anchors(conn, comment)?;
     }
 
     Ok(CommentsData {
@@ -426,26 +935,144 @@ pub fn load_comments(conn: &Connection, file_path: &str) -> Result<CommentsData,
     })
 }
 
+/// What a save keeps of an anchor that was already stored.
+struct StoredAnchor {
+    fingerprint: Option<String>,
+    context_before: Option<String>,
+    context_after: Option<String>,
+    status: String,
+    confidence: Option<f64>,
+    range: Option<TextRange>,
+}
+
+/// The comment's ranges, checked to be on its blocks, one per block, and to
+/// cover their quotes in `markdown` when it could be read. Ranges `known`
+/// to be stored already are not checked against the document again.
+fn checked_ranges(
+    markdown: Option<&str>,
+    block_ids: &[String],
+    ranges: &[TextRange],
+    known: impl Fn(&TextRange) -> bool,
+) -> Result<Vec<TextRange>, String> {
+    let mut checked: Vec<TextRange> = Vec::new();
+    for range in ranges {
+        if !block_ids.contains(&range.block_id) {
+            return Err(format!("Range is on {}, which the comment is not on", range.block_id));
+        }
+        if checked.iter().any(|r| r.block_id == range.block_id) {
+            return Err(format!("More than one range on {}", range.block_id));
+        }
+        let mut range = range.clone();
+        match markdown {
+            _ if known(&range) => crate::anchors::check_range(&range)?,
+            Some(markdown) => crate::anchors::validate_range(markdown, &mut range)?,
+            // A new range cannot be trusted without the text it points into
+            None => return Err(format!("Cannot check the range on {}: the document could not be read", range.block_id)),
+        }
+        checked.push(range);
+    }
+    Ok(checked)
+}
+
+fn insert_anchor(conn: &Connection, comment_id: &str, block_id: &str, anchor: &StoredAnchor) -> Result<(), String> {
+    let range = anchor.range.as_ref();
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO comment_blocks (comment_id, block_id, fingerprint, context_before, context_after,
+                                                   anchor_status, confidence, {})
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            crate::anchors::RANGE_COLUMNS
+        ),
+        params![
+            comment_id,
+            block_id,
+            anchor.fingerprint,
+            anchor.context_before,
+            anchor.context_after,
+            anchor.status,
+            anchor.confidence,
+            range.map(|r| r.start_line),
+            range.map(|r| r.end_line),
+            range.and_then(|r| r.start_char),
+            range.and_then(|r| r.end_char),
+            range.map(|r| &r.quote),
+        ],
+    )
+    .map_err(|e| format!("Insert block error: {}", e))?;
+    Ok(())
+}
+
+fn new_anchor(range: Option<TextRange>) -> StoredAnchor {
+    StoredAnchor {
+        fingerprint: None,
+        context_before: None,
+        context_after: None,
+        status: default_anchor_status(),
+        confidence: None,
+        range,
+    }
+}
+
 pub fn save_comments(
     conn: &Connection,
     file_path: &str,
     workspace_id: &str,
     data: &CommentsData,
 ) -> Result<(), String> {
-    let tx = conn
-        .unchecked_transaction()
-        .map_err(|e| format!("Transaction error: {}", e))?;
+    // Joins the caller's transaction when there is one
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction().map_err(|e| format!("Transaction error: {}", e))?)
+    } else {
+        None
+    };
+    // Replies may come before the comments they answer
+    conn.pragma_update(None, "defer_foreign_keys", "ON")
+        .map_err(|e| format!("Pragma error: {}", e))?;
 
-    tx.execute(
+    let markdown = std::fs::read_to_string(file_path).ok();
+    // Anchors that survive the save keep their fingerprint and status
+    let mut kept: HashMap<(String, String), StoredAnchor> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT b.comment_id, b.block_id, b.fingerprint, b.context_before, b.context_after,
+                        b.anchor_status, b.confidence, {}
+                 FROM comment_blocks b JOIN comments c ON c.id = b.comment_id
+                 WHERE c.file_path = ?1",
+                crate::anchors::RANGE_COLUMNS
+            ))
+            .map_err(|e| format!("Prepare anchors error: {}", e))?;
+        let rows = stmt
+            .query_map(params![file_path], |row| {
+                let block_id: String = row.get(1)?;
+                let anchor = StoredAnchor {
+                    fingerprint: row.get(2)?,
+                    context_before: row.get(3)?,
+                    context_after: row.get(4)?,
+                    status: row.get(5)?,
+                    confidence: row.get(6)?,
+                    range: crate::anchors::row_range(row, &block_id, 7)?,
+                };
+                Ok(((row.get(0)?, block_id), anchor))
+            })
+            .map_err(|e| format!("Query anchors error: {}", e))?;
+        for row in rows {
+            let (key, anchor) = row.map_err(|e| format!("Row anchors error: {}", e))?;
+            kept.insert(key, anchor);
+        }
+    }
+
+    conn.execute(
         "DELETE FROM comments WHERE file_path = ?1",
         params![file_path],
     )
     .map_err(|e| format!("Delete error: {}", e))?;
 
     for comment in &data.comments {
-        tx.execute(
-            "INSERT INTO comments (id, workspace_id, file_path, text, resolved, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        conn.execute(
+            "INSERT INTO comments (id, workspace_id, file_path, text, resolved, created_at,
+                                   parent_id, author, author_kind, edited_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 comment.id,
                 workspace_id,
@@ -453,28 +1080,174 @@ pub fn save_comments(
                 comment.text,
                 comment.resolved as i32,
                 comment.timestamp,
+                comment.parent_id,
+                comment.author,
+                comment.author_kind,
+                comment.edited_at,
             ],
         )
         .map_err(|e| format!("Insert error: {}", e))?;
 
+        let ranges = checked_ranges(markdown.as_deref(), &comment.block_ids, &comment.ranges, |range| {
+            kept.get(&(comment.id.clone(), range.block_id.clone()))
+                .is_some_and(|a| a.range.as_ref() == Some(range))
+        })?;
         for block_id in &comment.block_ids {
-            tx.execute(
-                "INSERT INTO comment_blocks (comment_id, block_id) VALUES (?1, ?2)",
-                params![comment.id, block_id],
-            )
-            .map_err(|e| format!("Insert block error: {}", e))?;
+            let range = ranges.iter().find(|r| r.block_id == *block_id).cloned();
+            let anchor = match kept.remove(&(comment.id.clone(), block_id.clone())) {
+                Some(anchor) => StoredAnchor { range, ..anchor },
+                None => new_anchor(range),
+            };
+            insert_anchor(conn, &comment.id, block_id, &anchor)?;
         }
     }
+    if let Some(markdown) = &markdown {
+        crate::anchors::fill_fingerprints(conn, file_path, markdown)?;
+    }
 
-    tx.execute(
+    conn.execute(
         "INSERT OR REPLACE INTO file_hashes (file_path, file_hash) VALUES (?1, ?2)",
         params![file_path, data.file_hash],
     )
     .map_err(|e| format!("Hash update error: {}", e))?;
 
-    tx.commit()
-        .map_err(|e| format!("Commit error: {}", e))?;
+    if let Some(tx) = tx {
+        tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Comment timestamps are in milliseconds, as the frontend writes them.
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+pub fn get_comment(conn: &Connection, id: &str) -> Result<Comment, String> {
+    let mut comment = conn
+        .query_row(
+            &format!("SELECT {} FROM comments c WHERE c.id = ?1", COMMENT_COLUMNS),
+            params![id],
+            row_to_comment,
+        )
+        .optional()
+        .map_err(|e| format!("Query comment error: {}", e))?
+        .ok_or_else(|| format!("Comment not found: {}", id))?;
+    load_anchors(conn, &mut comment)?;
+    Ok(comment)
+}
+
+/// Adds a comment on `file_path`, or a reply when `parent_id` is set. A
+/// reply belongs to its parent's file and workspace and has no blocks;
+/// threads are one level deep, so answering a reply joins its thread.
+pub fn add_comment(
+    conn: &Connection,
+    file_path: &str,
+    workspace_id: &str,
+    new: &NewComment,
+) -> Result<Comment, String> {
+    let text = new.text.trim();
+    if text.is_empty() {
+        return Err("Comment text is empty".to_string());
+    }
+    let author_kind = new.author_kind.as_deref().unwrap_or("user");
+    if author_kind != "user" && author_kind != "agent" {
+        return Err(format!("Invalid author kind: {}. Must be user or agent", author_kind));
+    }
+    let (file_path, workspace_id, parent_id, block_ids) = match &new.parent_id {
+        Some(parent_id) => {
+            let (parent_file, parent_workspace, root): (String, String, Option<String>) = conn
+                .query_row(
+                    "SELECT file_path, workspace_id, parent_id FROM comments WHERE id = ?1",
+                    params![parent_id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()
+                .map_err(|e| format!("Query comment error: {}", e))?
+                .ok_or_else(|| format!("Comment not found: {}", parent_id))?;
+            (parent_file, parent_workspace, Some(root.unwrap_or_else(|| parent_id.clone())), Vec::new())
+        }
+        None if new.block_ids.is_empty() => return Err("A comment needs at least one block".to_string()),
+        None => (file_path.to_string(), workspace_id.to_string(), None, new.block_ids.clone()),
+    };
+    // Like blocks, ranges only belong on top-level comments
+    let (markdown, ranges) = if block_ids.is_empty() {
+        (None, Vec::new())
+    } else {
+        let markdown = std::fs::read_to_string(&file_path).ok();
+        let ranges = checked_ranges(markdown.as_deref(), &block_ids, &new.ranges, |_| false)?;
+        (markdown, ranges)
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+    tx.execute(
+        "INSERT INTO comments (id, workspace_id, file_path, text, resolved, created_at, parent_id, author, author_kind)
+         VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8)",
+        params![id, workspace_id, file_path, text, now_millis(), parent_id, new.author, author_kind],
+    )
+    .map_err(|e| format!("Insert error: {}", e))?;
+    for block_id in &block_ids {
+        let range = ranges.iter().find(|r| r.block_id == *block_id).cloned();
+        insert_anchor(&tx, &id, block_id, &new_anchor(range))?;
+    }
+    if let Some(markdown) = &markdown {
+        crate::anchors::fill_fingerprints(&tx, &file_path, markdown)?;
+        // The first comment on a file records the content it was made on
+        tx.execute(
+            "INSERT OR IGNORE INTO file_hashes (file_path, file_hash) VALUES (?1, ?2)",
+            params![file_path, crate::checkpoints::hash_bytes(markdown.as_bytes())],
+        )
+        .map_err(|e| format!("Hash update error: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    get_comment(conn, &id)
+}
+
+pub fn update_comment_text(conn: &Connection, id: &str, text: &str) -> Result<Comment, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Comment text is empty".to_string());
+    }
+    let changed = conn
+        .execute(
+            "UPDATE comments SET text = ?1, edited_at = ?2 WHERE id = ?3",
+            params![text, now_millis(), id],
+        )
+        .map_err(|e| format!("Update comment error: {}", e))?;
+    if changed == 0 {
+        return Err(format!("Comment not found: {}", id));
+    }
+    get_comment(conn, id)
+}
 
+/// Resolves or reopens a thread; replies follow their top-level comment.
+pub fn set_comment_resolved(conn: &Connection, id: &str, resolved: bool) -> Result<Comment, String> {
+    let comment = get_comment(conn, id)?;
+    if comment.parent_id.is_some() {
+        return Err("Only a top-level comment can be resolved".to_string());
+    }
+    conn.execute(
+        "UPDATE comments SET resolved = ?1 WHERE id = ?2",
+        params![resolved as i32, id],
+    )
+    .map_err(|e| format!("Update comment error: {}", e))?;
+    get_comment(conn, id)
+}
+
+/// Deletes the comment and every reply under it.
+pub fn delete_comment(conn: &Connection, id: &str) -> Result<(), String> {
+    let deleted = conn
+        .execute("DELETE FROM comments WHERE id = ?1", params![id])
+        .map_err(|e| format!("Delete comment error: {}", e))?;
+    if deleted == 0 {
+        return Err(format!("Comment not found: {}", id));
+    }
     Ok(())
 }
 
@@ -508,7 +1281,7 @@ pub fn delete_comments_for_workspace(conn: &Connection, workspace_path: &str) ->
 pub fn count_unresolved_batch(conn: &Connection, file_paths: &[String]) -> Result<Vec<(String, i64)>, String> {
     let mut results = Vec::with_capacity(file_paths.len());
     let mut stmt = conn
-        .prepare("SELECT COUNT(*) FROM comments WHERE file_path = ?1 AND resolved = 0")
+        .prepare("SELECT COUNT(*) FROM comments WHERE file_path = ?1 AND resolved = 0 AND parent_id IS NULL")
         .map_err(|e| format!("Prepare error: {}", e))?;
     for path in file_paths {
         let count: i64 = stmt
@@ -584,3 +1357,80 @@ pub fn has_setting(conn: &Connection, key: &str) -> bool {
 pub fn now() -> i64 {
     now_epoch()
 }
+
+/// Fixtures shared by the modules' tests.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use rusqlite::Connection;
+    use std::path::PathBuf;
+
+    /// A database in a fresh temp directory with one workspace rooted there.
+    /// The directory is removed when this is dropped.
+    pub(crate) struct TestDb {
+        pub dir: PathBuf,
+        pub workspace_id: String,
+        conn: Option<Connection>,
+    }
+
+    impl TestDb {
+        pub fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("arandu-test-{}", uuid::Uuid::new_v4()));
+            let conn = super::init_db(&dir).unwrap();
+            let workspace = crate::sessions::upsert_workspace(&conn, "w1", dir.to_str().unwrap(), "ws", "directory").unwrap();
+            Self { dir, workspace_id: workspace.id, conn: Some(conn) }
+        }
+
+        /// Creates a session in the workspace and returns its id.
+        pub fn session(&self) -> String {
+            crate::sessions::create_session(self, &self.workspace_id, "n", "p", "claude").unwrap().id
+        }
+    }
+
+    impl std::ops::Deref for TestDb {
+        type Target = Connection;
+
+        fn deref(&self) -> &Connection {
+            self.conn.as_ref().unwrap()
+        }
+    }
+
+    impl Drop for TestDb {
+        fn drop(&mut self) {
+            // Close the database before removing its files
+            drop(self.conn.take());
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::TestDb;
+
+    #[test]
+    fn new_ranges_need_a_readable_document() {
+        let conn = TestDb::new();
+        let range = TextRange {
+            block_id: "mkw-para-0".to_string(),
+            start_line: 1,
+            end_line: 1,
+            start_char: Some(0),
+            end_char: Some(4),
+            quote: "Some".to_string(),
+        };
+        let new = NewComment {
+            block_ids: vec!["mkw-para-0".to_string()],
+            text: "why?".to_string(),
+            ranges: vec![range],
+            ..Default::default()
+        };
+        let missing = conn.dir.join("missing.md").to_string_lossy().to_string();
+        assert!(add_comment(&conn, &missing, &conn.workspace_id, &new).is_err());
+
+        let path = conn.dir.join("doc.md");
+        std::fs::write(&path, "Some text.\n").unwrap();
+        let comment = add_comment(&conn, &path.to_string_lossy(), &conn.workspace_id, &new).unwrap();
+        assert_eq!(comment.ranges[0].quote, "Some");
+    }
+}
@@ -0,0 +1,305 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::sessions::SessionRecord;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComparisonRecord {
+    pub id: String,
+    pub workspace_id: String,
+    pub name: String,
+    pub prompt: String,
+    pub created_at: i64,
+}
+
+/// One provider/model combination to run the comparison prompt against.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompareVariant {
+    pub provider: String,
+    pub model: Option<String>,
+    pub binary_path: Option<String>,
+    /// Endpoint for the `openai` provider
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparisonEntry {
+    pub session: SessionRecord,
+    pub final_answer: Option<String>,
+    pub plan_markdown: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComparisonDetail {
+    pub comparison: ComparisonRecord,
+    pub entries: Vec<ComparisonEntry>,
+}
+
+fn row_to_comparison(row: &rusqlite::Row) -> rusqlite::Result<ComparisonRecord> {
+    Ok(ComparisonRecord {
+        id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        name: row.get(2)?,
+        prompt: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+fn variant_label(variant: &CompareVariant) -> String {
+    match variant.model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        Some(model) => format!("{}/{}", variant.provider, model),
+        None => variant.provider.clone(),
+    }
+}
+
+/// Creates the comparison row and one sibling session per variant, in a single transaction.
+pub fn create_comparison(
+    conn: &Connection,
+    workspace_id: &str,
+    name: &str,
+    prompt: &str,
+    variants: &[CompareVariant],
+) -> Result<(ComparisonRecord, Vec<SessionRecord>), String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = crate::comments::now();
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO comparisons (id, workspace_id, name, prompt, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, workspace_id, name, prompt, now],
+    )
+    .map_err(|e| format!("Insert comparison error: {}", e))?;
+
+    let mut sessions = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let session_name = format!("{} · {}", name, variant_label(variant));
+        let session = crate::sessions::create_session(&tx, workspace_id, &session_name, prompt, &variant.provider)?;
+        tx.execute(
+            "UPDATE sessions SET comparison_id = ?1 WHERE id = ?2",
+            params![id, session.id],
+        )
+        .map_err(|e| format!("Update comparison_id error: {}", e))?;
+        sessions.push(crate::sessions::get_session(&tx, &session.id)?);
+    }
+
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+
+    let comparison = ComparisonRecord {
+        id,
+        workspace_id: workspace_id.to_string(),
+        name: name.to_string(),
+        prompt: prompt.to_string(),
+        created_at: now,
+    };
+    Ok((comparison, sessions))
+}
+
+pub fn get_comparison(conn: &Connection, id: &str) -> Result<ComparisonRecord, String> {
+    conn.query_row(
+        "SELECT id, workspace_id, name, prompt, created_at FROM comparisons WHERE id = ?1",
+        params![id],
+        row_to_comparison,
+    )
+    .map_err(|e| format!("Comparison not found: {}", e))
+}
+
+pub fn list_comparisons(conn: &Connection, workspace_id: &str) -> Result<Vec<ComparisonRecord>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, workspace_id, name, prompt, created_at FROM comparisons
+             WHERE workspace_id = ?1 ORDER BY created_at DESC",
+        )
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![workspace_id], row_to_comparison)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+pub fn list_comparison_sessions(conn: &Connection, comparison_id: &str) -> Result<Vec<SessionRecord>, String> {
+    let sql = format!(
+        "SELECT {} FROM sessions WHERE comparison_id = ?1 ORDER BY created_at ASC, rowid ASC",
+        crate::sessions::SESSION_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![comparison_id], crate::sessions::row_to_session)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+pub fn delete_comparison(conn: &Connection, id: &str) -> Result<(), String> {
+    conn.execute("DELETE FROM comparisons WHERE id = ?1", params![id])
+        .map_err(|e| format!("Delete comparison error: {}", e))?;
+    Ok(())
+}
+
+// --- Background fan-out ---
+
+#[derive(Debug, Clone)]
+struct VariantRun {
+    comparison_id: String,
+    session: SessionRecord,
+    workspace_path: String,
+    variant: CompareVariant,
+    gh_token: Option<String>,
+    skip_permissions: Option<bool>,
+    max_budget_usd: Option<String>,
+}
+
+fn emit_variant_status(app: &AppHandle, run: &VariantRun, status: &str, error: Option<&str>) {
+    let _ = app.emit("compare:variant-status", serde_json::json!({
+        "comparisonId": run.comparison_id,
+        "sessionId": run.session.id,
+        "status": status,
+        "error": error,
+    }));
+}
+
+async fn run_variant(app: &AppHandle, run: &VariantRun) -> Result<(), String> {
+    let store = app.state::<AcpSessionStore>();
+
+    emit_variant_status(app, run, "connecting", None);
+    let acp_id = crate::acp::commands::connect_session(
+        &store,
+        app,
+        run.session.id.clone(),
+        run.workspace_path.clone(),
+        Some(run.variant.provider.clone()),
+        run.variant.binary_path.clone(),
+        run.gh_token.clone(),
+        run.variant.model.clone(),
+        run.skip_permissions,
+        run.max_budget_usd.clone(),
+        None,
+        run.variant.base_url.clone(),
+        None,
+    )
+    .await?;
+
+    if let Some(db) = app.try_state::<CommentsDb>() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        crate::sessions::update_session_acp_id(&conn, &run.session.id, &acp_id)?;
+        crate::phase::transition(&conn, &run.session.id, crate::phase::Phase::Planning, Some("comparison started"))?;
+    }
+
+    // Same prompt shape the frontend uses when it starts a new session
+    let prompt = format!("{}\n\n{}", run.session.name, run.session.initial_prompt);
+    emit_variant_status(app, run, "prompting", None);
+    crate::acp::commands::send_session_prompt(&store, app, run.session.id.clone(), prompt, Default::default()).await?;
+    emit_variant_status(app, run, "done", None);
+    Ok(())
+}
+
+// --- Tauri commands ---
+
+use crate::acp::commands::AcpSessionStore;
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Creates sibling sessions for every variant and sends the prompt to all of
+/// them in parallel. Returns immediately; progress is reported through
+/// `compare:variant-status` events and the regular `acp:*` session events.
+#[tauri::command]
+pub fn compare_create(
+    workspace_id: String,
+    workspace_path: String,
+    name: String,
+    prompt: String,
+    variants: Vec<CompareVariant>,
+    gh_token: Option<String>,
+    skip_permissions: Option<bool>,
+    max_budget_usd: Option<String>,
+    db: tauri::State<CommentsDb>,
+    store: tauri::State<AcpSessionStore>,
+    app: AppHandle,
+) -> Result<ComparisonDetail, String> {
+    if variants.len() < 2 {
+        return Err("A comparison needs at least two variants".to_string());
+    }
+    if variants.len() > store.max_instances {
+        return Err(format!("A comparison supports at most {} variants", store.max_instances));
+    }
+
+    let (comparison, sessions) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        create_comparison(&conn, &workspace_id, &name, &prompt, &variants)?
+    };
+
+    for (session, variant) in sessions.iter().zip(variants) {
+        let run = VariantRun {
+            comparison_id: comparison.id.clone(),
+            session: session.clone(),
+            workspace_path: workspace_path.clone(),
+            variant,
+            gh_token: gh_token.clone(),
+            skip_permissions,
+            max_budget_usd: max_budget_usd.clone(),
+        };
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = run_variant(&app, &run).await {
+                eprintln!("[compare] variant session={} failed: {}", run.session.id, e);
+                emit_variant_status(&app, &run, "error", Some(&e));
+            }
+        });
+    }
+
+    let entries = sessions
+        .into_iter()
+        .map(|session| ComparisonEntry { session, final_answer: None, plan_markdown: String::new() })
+        .collect();
+    Ok(ComparisonDetail { comparison, entries })
+}
+
+#[tauri::command]
+pub fn compare_list(
+    workspace_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<ComparisonRecord>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_comparisons(&conn, &workspace_id)
+}
+
+/// Returns each sibling session with its latest assistant answer and plan file,
+/// for side-by-side display.
+#[tauri::command]
+pub fn compare_get(
+    id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<ComparisonDetail, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let comparison = get_comparison(&conn, &id)?;
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let mut entries = Vec::new();
+    for session in list_comparison_sessions(&conn, &id)? {
+        let final_answer = crate::messages::last_assistant_message(&conn, &session.id)?
+            .map(|m| m.content);
+        let plan_markdown = crate::plan_storage::read_session_plan(&conn, &app_data, &session.id)?;
+        entries.push(ComparisonEntry { session, final_answer, plan_markdown });
+    }
+
+    Ok(ComparisonDetail { comparison, entries })
+}
+
+/// Deletes the comparison grouping; the sibling sessions are kept as regular sessions.
+#[tauri::command]
+pub fn compare_delete(
+    id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_comparison(&conn, &id)
+}
@@ -0,0 +1,380 @@
+//! Per-workspace environment profiles for agent processes.
+//!
+//! Each workspace can define plain variables and secrets that are injected into
+//! every provider process spawned for it. All profiles are stored together in
+//! `<app_data>/workspace_env.enc`, encrypted with ChaCha20-Poly1305 under a
+//! random key kept next to it in `workspace_env.key` (owner-only permissions on
+//! Unix). Secret values never leave the backend: the listing command omits
+//! them, and [`redact`] masks them in diagnostics and connection logs.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+const PROFILES_FILE: &str = "workspace_env.enc";
+const KEY_FILE: &str = "workspace_env.key";
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EnvProfile {
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub secrets: BTreeMap<String, String>,
+}
+
+/// UI-safe view of one profile entry; `value` is always `None` for secrets.
+#[derive(Debug, Serialize, Clone)]
+pub struct EnvEntry {
+    pub key: String,
+    pub value: Option<String>,
+    pub secret: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Decrypted profiles keyed by workspace path, loaded lazily on first use.
+#[derive(Default)]
+pub struct EnvProfilesState {
+    profiles: Mutex<Option<HashMap<String, EnvProfile>>>,
+    /// Secret values of all profiles, longest first, rebuilt whenever the
+    /// profiles are loaded or changed so log redaction never waits on them
+    secrets: RwLock<Option<Vec<String>>>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if !s.len().is_multiple_of(2) {
+        return Err("Invalid hex length".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| format!("Invalid hex: {}", e)))
+        .collect()
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn load_key(app_data_dir: &Path) -> Result<Option<Key>, String> {
+    let path = app_data_dir.join(KEY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&path)
+        .map_err(|e| format!("Failed to read env key: {}", e))?;
+    if bytes.len() != 32 {
+        return Err("Env key file is corrupted".to_string());
+    }
+    Ok(Some(*Key::from_slice(&bytes)))
+}
+
+/// The key of an existing profiles file is irreplaceable: without it the file
+/// can never be decrypted again, so a missing key is an error rather than a
+/// reason to start over with a new one.
+fn missing_key(app_data_dir: &Path) -> String {
+    format!(
+        "Env profiles in {} cannot be decrypted: key file {} is missing. Restore it, or delete the profiles file to start over",
+        app_data_dir.join(PROFILES_FILE).display(),
+        app_data_dir.join(KEY_FILE).display()
+    )
+}
+
+fn load_or_create_key(app_data_dir: &Path) -> Result<Key, String> {
+    if let Some(key) = load_key(app_data_dir)? {
+        return Ok(key);
+    }
+    if app_data_dir.join(PROFILES_FILE).exists() {
+        return Err(missing_key(app_data_dir));
+    }
+    let path = app_data_dir.join(KEY_FILE);
+    std::fs::create_dir_all(app_data_dir)
+        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    write_private(&path, key.as_slice())?;
+    Ok(key)
+}
+
+pub fn load_profiles(app_data_dir: &Path) -> Result<HashMap<String, EnvProfile>, String> {
+    let path = app_data_dir.join(PROFILES_FILE);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read env profiles: {}", e))?;
+    let file: EncryptedFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse env profiles: {}", e))?;
+
+    let key = load_key(app_data_dir)?.ok_or_else(|| missing_key(app_data_dir))?;
+    let nonce = from_hex(&file.nonce)?;
+    if nonce.len() != 12 {
+        return Err("Env profiles nonce is corrupted".to_string());
+    }
+    let ciphertext = from_hex(&file.ciphertext)?;
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Failed to decrypt env profiles (wrong key or corrupted file)".to_string())?;
+
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("Failed to parse env profiles: {}", e))
+}
+
+pub fn save_profiles(app_data_dir: &Path, profiles: &HashMap<String, EnvProfile>) -> Result<(), String> {
+    let key = load_or_create_key(app_data_dir)?;
+    let plaintext = serde_json::to_vec(profiles).map_err(|e| e.to_string())?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|_| "Failed to encrypt env profiles".to_string())?;
+
+    let file = EncryptedFile {
+        version: 1,
+        nonce: to_hex(nonce.as_slice()),
+        ciphertext: to_hex(&ciphertext),
+    };
+    let json = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+
+    // Write-then-rename so a crash never leaves a truncated profile file
+    let path = app_data_dir.join(PROFILES_FILE);
+    let tmp = app_data_dir.join(format!("{}.tmp", PROFILES_FILE));
+    write_private(&tmp, json.as_bytes())?;
+    std::fs::rename(&tmp, &path)
+        .map_err(|e| format!("Failed to save env profiles: {}", e))
+}
+
+/// Finds the profile for `cwd`: an exact workspace match, or the deepest
+/// workspace that contains it (e.g. a worktree under the workspace).
+fn profile_for<'a>(profiles: &'a HashMap<String, EnvProfile>, cwd: &str) -> Option<&'a EnvProfile> {
+    let cwd = Path::new(cwd);
+    profiles
+        .iter()
+        .filter(|(path, _)| cwd.starts_with(Path::new(path.as_str())))
+        .max_by_key(|(path, _)| path.len())
+        .map(|(_, profile)| profile)
+}
+
+/// Secret values to mask, longest first so a secret containing another is
+/// masked as a whole.
+fn secret_values(profiles: &HashMap<String, EnvProfile>) -> Vec<String> {
+    let mut secrets: Vec<String> = profiles
+        .values()
+        .flat_map(|p| p.secrets.values().map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .collect();
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    secrets
+}
+
+fn state(app: &AppHandle) -> Result<tauri::State<'_, EnvProfilesState>, String> {
+    app.try_state::<EnvProfilesState>()
+        .ok_or_else(|| "Env profiles state not available".to_string())
+}
+
+/// Runs `f` on the profiles, loading them first if needed. With `changes`,
+/// the redaction set is rebuilt afterwards.
+fn access_profiles<T>(
+    app: &AppHandle,
+    changes: bool,
+    f: impl FnOnce(&mut HashMap<String, EnvProfile>) -> T,
+) -> Result<T, String> {
+    let state = state(app)?;
+    let mut guard = state.profiles.lock().map_err(|e| e.to_string())?;
+    let loaded = guard.is_none();
+    if loaded {
+        let app_data = app.path().app_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+        *guard = Some(load_profiles(&app_data)?);
+    }
+    let profiles = guard.as_mut().expect("profiles loaded above");
+    let out = f(profiles);
+    if loaded || changes {
+        *state.secrets.write().map_err(|e| e.to_string())? = Some(secret_values(profiles));
+    }
+    Ok(out)
+}
+
+fn with_profiles<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&HashMap<String, EnvProfile>) -> T,
+) -> Result<T, String> {
+    access_profiles(app, false, |profiles| f(profiles))
+}
+
+fn update_profiles<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut HashMap<String, EnvProfile>) -> T,
+) -> Result<T, String> {
+    access_profiles(app, true, f)
+}
+
+/// Variables and secrets to inject for a process running in `cwd`.
+pub fn env_for_cwd(app: &AppHandle, cwd: &str) -> Vec<(String, String)> {
+    with_profiles(app, |profiles| {
+        profile_for(profiles, cwd)
+            .map(|p| {
+                p.vars
+                    .iter()
+                    .chain(p.secrets.iter())
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("[env] Failed to load env profiles: {}", e);
+        Vec::new()
+    })
+}
+
+/// Applies the workspace profile for `cwd` to a process about to be spawned.
+pub fn apply_to_command(app: &AppHandle, cwd: &str, cmd: &mut tokio::process::Command) {
+    let env = env_for_cwd(app, cwd);
+    if !env.is_empty() {
+        eprintln!("[env] Applying {} variable(s) for {}", env.len(), cwd);
+        cmd.envs(env);
+    }
+}
+
+/// Masks every known secret value (and any `extra` values such as a GH token
+/// passed from the frontend) in `text`.
+pub fn redact(app: &AppHandle, text: &str, extra: &[&str]) -> String {
+    let Ok(state) = state(app) else {
+        return text.to_string();
+    };
+    let cached = state.secrets.read().ok().and_then(|s| s.clone());
+    // Only the first call ever has to load the profiles
+    let mut secrets = match cached {
+        Some(secrets) => secrets,
+        None => with_profiles(app, secret_values).unwrap_or_default(),
+    };
+    let extra = extra.iter().map(|s| s.trim()).filter(|s| !s.is_empty());
+    if extra.clone().next().is_some() {
+        secrets.extend(extra.map(str::to_string));
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+
+    let mut out = text.to_string();
+    for secret in &secrets {
+        out = out.replace(secret.as_str(), REDACTED);
+    }
+    out
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    let valid = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid environment variable name: {}", key))
+    }
+}
+
+fn persist(app: &AppHandle, profiles: &HashMap<String, EnvProfile>) -> Result<(), String> {
+    let app_data: PathBuf = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    save_profiles(&app_data, profiles)
+}
+
+// --- Tauri commands ---
+
+use tauri::{AppHandle, Manager};
+
+/// Lists the profile entries of a workspace without secret values.
+#[tauri::command]
+pub fn workspace_env_list(
+    workspace_path: String,
+    app: AppHandle,
+) -> Result<Vec<EnvEntry>, String> {
+    with_profiles(&app, |profiles| {
+        let profile = profiles.get(&workspace_path).cloned().unwrap_or_default();
+        let mut entries: Vec<EnvEntry> = profile
+            .vars
+            .into_iter()
+            .map(|(key, value)| EnvEntry { key, value: Some(value), secret: false })
+            .collect();
+        entries.extend(
+            profile
+                .secrets
+                .into_keys()
+                .map(|key| EnvEntry { key, value: None, secret: true }),
+        );
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        entries
+    })
+}
+
+#[tauri::command]
+pub fn workspace_env_set(
+    workspace_path: String,
+    key: String,
+    value: String,
+    secret: bool,
+    app: AppHandle,
+) -> Result<(), String> {
+    validate_key(&key)?;
+    let snapshot = update_profiles(&app, |profiles| {
+        let profile = profiles.entry(workspace_path).or_default();
+        // A key lives in exactly one of the two maps
+        if secret {
+            profile.vars.remove(&key);
+            profile.secrets.insert(key, value);
+        } else {
+            profile.secrets.remove(&key);
+            profile.vars.insert(key, value);
+        }
+        profiles.clone()
+    })?;
+    persist(&app, &snapshot)
+}
+
+#[tauri::command]
+pub fn workspace_env_remove(
+    workspace_path: String,
+    key: String,
+    app: AppHandle,
+) -> Result<(), String> {
+    let snapshot = update_profiles(&app, |profiles| {
+        if let Some(profile) = profiles.get_mut(&workspace_path) {
+            profile.vars.remove(&key);
+            profile.secrets.remove(&key);
+            if profile.vars.is_empty() && profile.secrets.is_empty() {
+                profiles.remove(&workspace_path);
+            }
+        }
+        profiles.clone()
+    })?;
+    persist(&app, &snapshot)
+}
+
+/// Drops a workspace's whole profile (used when forgetting workspace data).
+pub fn remove_workspace_profile(app: &AppHandle, workspace_path: &str) -> Result<(), String> {
+    let snapshot = update_profiles(app, |profiles| {
+        profiles.remove(workspace_path).map(|_| profiles.clone())
+    })?;
+    match snapshot {
+        Some(profiles) => persist(app, &profiles),
+        None => Ok(()),
+    }
+}
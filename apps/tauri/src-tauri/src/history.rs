@@ -0,0 +1,376 @@
+//! Branching session history: forking a session at a message and rewinding
+//! it to edit and resend an earlier prompt.
+//!
+//! A fork is a new `sessions` row holding copies of the original messages up
+//! to the chosen one, the plan file and the ACP preferences. A rewind keeps the
+//! full conversation in an archived branch session and truncates the original
+//! at the edited message. Either way the agent side starts fresh:
+//! OpenAI-compatible sessions rebuild their history from the stored messages,
+//! while Copilot and Claude sessions get a replay of the conversation
+//! prepended to their next prompt (see `sessions::take_context_seed`). Claude's
+//! `--resume` cannot stop at an earlier turn because the CLI's message ids are
+//! not tracked, so it is not used here.
+
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+
+use crate::messages::MessageRecord;
+use crate::sessions::SessionRecord;
+
+/// Upper bound for the replayed conversation; older turns are dropped first.
+const REPLAY_MAX_CHARS: usize = 60_000;
+
+/// Sequence number of a message in its session, for "up to and including" queries.
+fn message_position(conn: &Connection, session_id: &str, message_id: &str) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT seq FROM messages WHERE id = ?1 AND session_id = ?2",
+        params![message_id, session_id],
+        |row| row.get(0),
+    )
+    .map_err(|_| format!("Message {} not found in session {}", message_id, session_id))
+}
+
+/// Copies the messages of `from_session` up to and including sequence number
+/// `upto` into `to_session`, with fresh ids and tool history.
+/// Returns the number of messages copied.
+pub(crate) fn copy_messages(
+    conn: &Connection,
+    from_session: &str,
+    to_session: &str,
+    upto: i64,
+) -> Result<usize, String> {
+    let ids: Vec<String> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM messages
+                 WHERE session_id = ?1 AND seq <= ?2
+                 ORDER BY seq ASC",
+            )
+            .map_err(|e| format!("Prepare error: {}", e))?;
+        let rows = stmt
+            .query_map(params![from_session, upto], |row| row.get(0))
+            .map_err(|e| format!("Query error: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?;
+        rows
+    };
+
+    for id in &ids {
+        let new_id = uuid::Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO messages (id, session_id, role, content, message_type, tool_call_id, tool_title,
+                                   tool_status, tool_payload, started_at, completed_at, created_at, stream_state)
+             SELECT ?1, ?2, role, content, message_type, tool_call_id, tool_title,
+                    tool_status, tool_payload, started_at, completed_at, created_at, stream_state
+             FROM messages WHERE id = ?3",
+            params![new_id, to_session, id],
+        )
+        .map_err(|e| format!("Copy message error: {}", e))?;
+        conn.execute(
+            "INSERT INTO tool_call_updates (message_id, payload, received_at)
+             SELECT ?1, payload, received_at FROM tool_call_updates WHERE message_id = ?2 ORDER BY id",
+            params![new_id, id],
+        )
+        .map_err(|e| format!("Copy tool history error: {}", e))?;
+    }
+    Ok(ids.len())
+}
+
+/// Renders the user/assistant turns as a prompt preamble for a fresh agent
+/// session, keeping the most recent turns within `REPLAY_MAX_CHARS`.
+pub fn render_replay(messages: &[MessageRecord]) -> Option<String> {
+    let mut turns: Vec<String> = Vec::new();
+    let mut used = 0;
+    for message in messages.iter().rev() {
+        if message.content.trim().is_empty() {
+            continue;
+        }
+        let who = if message.role == "user" { "User" } else { "Assistant" };
+        let turn = format!("[{}]\n{}", who, message.content.trim());
+        if used + turn.len() > REPLAY_MAX_CHARS {
+            break;
+        }
+        used += turn.len();
+        turns.push(turn);
+    }
+    if turns.is_empty() {
+        return None;
+    }
+    let omitted = messages.iter().filter(|m| !m.content.trim().is_empty()).count() - turns.len();
+    turns.reverse();
+
+    let mut out = String::from(
+        "This conversation continues an earlier session. Its history is replayed below for context; \
+         do not act on it again, just continue from where it ends.\n\n<previous_conversation>\n",
+    );
+    if omitted > 0 {
+        out.push_str(&format!("({} earlier messages omitted)\n\n", omitted));
+    }
+    out.push_str(&turns.join("\n\n"));
+    out.push_str("\n</previous_conversation>");
+    Some(out)
+}
+
+/// Creates the forked session. The plan file is copied by the caller.
+pub fn fork_session(conn: &Connection, session_id: &str, message_id: &str) -> Result<SessionRecord, String> {
+    let source = crate::sessions::get_session(conn, session_id)?;
+    let upto = message_position(conn, session_id, message_id)?;
+
+    let fork_id = uuid::Uuid::new_v4().to_string();
+    let now = crate::comments::now();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO sessions (id, workspace_id, provider, name, initial_prompt, phase,
+                               acp_preferences_json, forked_from_session_id, forked_from_message_id,
+                               created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+        params![
+            fork_id,
+            source.workspace_id,
+            source.provider,
+            format!("{} (fork)", source.name),
+            source.initial_prompt,
+            source.phase,
+            source.acp_preferences_json,
+            source.id,
+            message_id,
+            now,
+        ],
+    )
+    .map_err(|e| format!("Insert session error: {}", e))?;
+
+    copy_messages(&tx, session_id, &fork_id, upto)?;
+
+    let fork = crate::sessions::get_session(&tx, &fork_id)?;
+    crate::sessions::set_context_seed(&tx, &fork_id, replay_seed(&tx, &fork)?.as_deref())?;
+
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    crate::sessions::get_session(conn, &fork_id)
+}
+
+/// Replay seed for the session's current messages; `None` for providers that
+/// rebuild history themselves or when there is nothing to replay.
+fn replay_seed(conn: &Connection, session: &SessionRecord) -> Result<Option<String>, String> {
+    if session.provider == "openai" {
+        return Ok(None);
+    }
+    Ok(render_replay(&crate::messages::list_chat_history(conn, &session.id)?))
+}
+
+/// Position of the user message a session can be rewound to.
+fn rewind_point(conn: &Connection, session_id: &str, message_id: &str) -> Result<i64, String> {
+    let at = message_position(conn, session_id, message_id)?;
+    let role: String = conn
+        .query_row("SELECT role FROM messages WHERE id = ?1", params![message_id], |row| row.get(0))
+        .map_err(|e| format!("Query error: {}", e))?;
+    if role != "user" {
+        return Err("Only user messages can be edited".to_string());
+    }
+    Ok(at)
+}
+
+/// Moves the conversation from `message_id` (a user message) onward out of the
+/// session: everything is copied to a new archived branch marked with
+/// `rewound_at`, then the session is truncated before the message and
+/// detached from its provider session. Returns the branch.
+pub fn rewind_session(conn: &Connection, session_id: &str, message_id: &str) -> Result<SessionRecord, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    let at = rewind_point(conn, session_id, message_id)?;
+
+    let branch_id = uuid::Uuid::new_v4().to_string();
+    let now = crate::comments::now();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+
+    // The branch keeps the provider session so it can still be loaded later
+    tx.execute(
+        "INSERT INTO sessions (id, workspace_id, acp_session_id, provider, name, initial_prompt, plan_file_path,
+                               phase, acp_preferences_json, forked_from_session_id, forked_from_message_id,
+                               archived, rewound_at, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, 1, ?12, ?12, ?12)",
+        params![
+            branch_id,
+            session.workspace_id,
+            session.acp_session_id,
+            session.provider,
+            format!("{} (before edit)", session.name),
+            session.initial_prompt,
+            session.plan_file_path,
+            session.phase,
+            session.acp_preferences_json,
+            session.id,
+            message_id,
+            now,
+        ],
+    )
+    .map_err(|e| format!("Insert branch error: {}", e))?;
+    copy_messages(&tx, session_id, &branch_id, i64::MAX)?;
+
+    tx.execute(
+        "DELETE FROM messages WHERE session_id = ?1 AND seq >= ?2",
+        params![session_id, at],
+    )
+    .map_err(|e| format!("Truncate messages error: {}", e))?;
+    tx.execute(
+        "UPDATE sessions SET acp_session_id = NULL, updated_at = ?1 WHERE id = ?2",
+        params![now, session_id],
+    )
+    .map_err(|e| format!("Update session error: {}", e))?;
+    crate::sessions::set_context_seed(&tx, session_id, replay_seed(&tx, &session)?.as_deref())?;
+
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    crate::sessions::get_session(conn, &branch_id)
+}
+
+/// Branches kept by rewinds of `session_id`, newest first. Forks and
+/// sessions archived by hand are not branches.
+pub fn list_branches(conn: &Connection, session_id: &str) -> Result<Vec<SessionRecord>, String> {
+    let sql = format!(
+        "SELECT {} FROM sessions WHERE forked_from_session_id = ?1 AND rewound_at IS NOT NULL ORDER BY created_at DESC",
+        crate::sessions::SESSION_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], crate::sessions::row_to_session)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+/// Copies the session's plan file to the fork and points the fork at it.
+fn copy_plan(conn: &Connection, app_data_dir: &PathBuf, source: &SessionRecord, fork: &SessionRecord) -> Result<(), String> {
+    if !crate::plan_storage::is_managed_plan(conn, app_data_dir, source)? {
+        // A plan stored elsewhere (e.g. written by the agent) is shared, not copied
+        if let Some(path) = &source.plan_file_path {
+            crate::sessions::update_plan_file_path(conn, &fork.id, path)?;
+        }
+        return Ok(());
+    }
+    let markdown = crate::plan_storage::read_session_plan(conn, app_data_dir, &source.id)?;
+    if markdown.is_empty() {
+        return Ok(());
+    }
+    crate::plan_storage::write_session_plan(conn, app_data_dir, &fork.id, &markdown)?;
+    crate::plan_history::record_revision(conn, &fork.id, &markdown, "fork")?;
+    Ok(())
+}
+
+// --- Tauri commands ---
+
+use crate::acp::commands::{
+    connect_session, disconnect_session, send_session_prompt, AcpSessionStore, AnySessionConnection,
+};
+use crate::acp::connection::PromptOptions;
+use crate::acp::types::Provider;
+use crate::comments::CommentsDb;
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+
+/// Forks `session_id` at `message_id` (inclusive) into a new session.
+#[tauri::command]
+pub fn session_fork(
+    session_id: String,
+    message_id: String,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<SessionRecord, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let source = crate::sessions::get_session(&conn, &session_id)?;
+    let fork = fork_session(&conn, &session_id, &message_id)?;
+    copy_plan(&conn, &app_data, &source, &fork)?;
+    eprintln!("[history] Forked session {} at message {} into {}", session_id, message_id, fork.id);
+    crate::sessions::get_session(&conn, &fork.id)
+}
+
+#[tauri::command]
+pub fn session_list_branches(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<SessionRecord>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_branches(&conn, &session_id)
+}
+
+/// Rewinds a connected session to `message_id`, replaces that prompt with
+/// `text` and resends it on a fresh agent context. The previous conversation is
+/// kept in an archived branch, which is returned once the turn completes;
+/// `acp:session-rewound` is emitted before the prompt is sent.
+#[tauri::command]
+pub async fn session_rewind(
+    session_id: String,
+    message_id: String,
+    text: String,
+    db: tauri::State<'_, CommentsDb>,
+    store: tauri::State<'_, AcpSessionStore>,
+    app: tauri::AppHandle,
+) -> Result<SessionRecord, String> {
+    let config = store.configs.lock().await.get(&session_id).cloned()
+        .ok_or("Session not connected")?;
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        rewind_point(&conn, &session_id, &message_id)?;
+    }
+
+    // Stop the agent first so a streaming reply cannot land in the truncated history
+    let openai = if config.provider == Provider::OpenAi {
+        let instances = store.instances.lock().await;
+        match instances.get(&session_id).map(|inst| &inst.connection) {
+            Some(AnySessionConnection::OpenAi(c)) => Some(Arc::clone(c)),
+            _ => return Err("Session not connected".to_string()),
+        }
+    } else {
+        disconnect_session(&store, &app, &session_id, "Session rewound").await;
+        None
+    };
+    let turn = match &openai {
+        Some(c) => Some(c.stop_turn().await),
+        None => None,
+    };
+
+    let branch = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        rewind_session(&conn, &session_id, &message_id)?
+    };
+    eprintln!("[history] Rewound session {} at message {} (branch {})", session_id, message_id, branch.id);
+    let _ = app.emit("acp:session-rewound", serde_json::json!({
+        "sessionId": &session_id,
+        "branchSessionId": &branch.id,
+        "messageId": &message_id,
+    }));
+
+    if let Some(conn) = &openai {
+        conn.reload_history().await;
+        drop(turn);
+    } else {
+        let provider = if config.provider == Provider::Claude { "claude" } else { "copilot" };
+        let acp_id = connect_session(
+            &store,
+            &app,
+            session_id.clone(),
+            config.cwd,
+            Some(provider.to_string()),
+            Some(config.binary).filter(|b| !b.is_empty()),
+            config.gh_token,
+            config.model,
+            Some(config.skip_permissions),
+            config.max_budget_usd,
+            None,
+            config.base_url,
+            None,
+        )
+        .await?;
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        crate::sessions::update_session_acp_id(&conn, &session_id, &acp_id)?;
+    }
+
+    let options = PromptOptions { allow_duplicate: true, ..Default::default() };
+    send_session_prompt(&store, &app, session_id, text, options).await?;
+    Ok(branch)
+}
@@ -1,4 +1,4 @@
-use comrak::{markdown_to_html, Options};
+use comrak::markdown_to_html;
 use notify::{Event, RecursiveMode, Watcher};
 use serde::Serialize;
 use std::collections::HashSet;
@@ -10,18 +10,34 @@ use tauri_plugin_cli::CliExt;
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
 mod acp;
+mod anchors;
+mod bundle;
+mod checkpoints;
 #[cfg(target_os = "macos")]
 mod cli_installer;
 mod comments;
+mod compare;
+mod env_profiles;
+mod history;
+mod markdown;
 mod messages;
+mod phase;
 mod plan_file;
+mod plan_history;
+mod plan_import;
+mod plan_progress;
+mod plan_runner;
+mod plan_storage;
+mod review;
 mod sessions;
 mod ipc_common;
 #[cfg(unix)]
 mod ipc;
 mod tcp_ipc;
+mod tool_diffs;
 mod tray;
 mod whisper;
+mod worktree;
 
 #[derive(Debug, Serialize, Clone)]
 pub struct Heading {
@@ -45,13 +61,7 @@ struct InstallResult {
 
 #[tauri::command]
 fn render_markdown(content: String) -> String {
-    let mut options = Options::default();
-    options.extension.table = true;
-    options.extension.tasklist = true;
-    options.extension.strikethrough = true;
-    options.extension.autolink = true;
-    options.render.escape = true;
-    markdown_to_html(&content, &options)
+    markdown_to_html(&content, &markdown::viewer_options())
 }
 
 #[tauri::command]
@@ -122,6 +132,7 @@ fn create_file_watcher(app: tauri::AppHandle) -> Result<notify::RecommendedWatch
         if let Ok(event) = res {
             if event.kind.is_modify() {
                 if let Some(path) = event.paths.first() {
+                    crate::plan_history::record_file_change(&app, path);
                     let path_str = path.to_string_lossy().to_string();
                     let _ = app.emit("file-changed", path_str);
                 }
@@ -264,7 +275,7 @@ pub struct DiagnosticsResult {
     auth_method: Option<String>,
 }
 
-async fn test_acp_connection(binary: &str, gh_token: Option<&str>) -> (bool, Option<u64>, Option<String>, String, Option<String>) {
+async fn test_acp_connection(binary: &str, gh_token: Option<&str>, env: &[(String, String)]) -> (bool, Option<u64>, Option<String>, String, Option<String>) {
     use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
     let timeout = std::time::Duration::from_secs(10);
@@ -274,6 +285,7 @@ async fn test_acp_connection(binary: &str, gh_token: Option<&str>) -> (bool, Opt
 
     let mut cmd = tokio::process::Command::new(binary);
     cmd.args(["--acp", "--stdio"])
+        .envs(env.iter().cloned())
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
@@ -366,7 +378,7 @@ async fn test_acp_connection(binary: &str, gh_token: Option<&str>) -> (bool, Opt
     }
 }
 
-async fn test_claude_connection(binary: &str) -> (bool, Option<u64>, Option<String>, String, Option<String>) {
+async fn test_claude_connection(binary: &str, env: &[(String, String)]) -> (bool, Option<u64>, Option<String>, String, Option<String>) {
     use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
     let timeout = std::time::Duration::from_secs(15);
@@ -375,6 +387,7 @@ async fn test_claude_connection(binary: &str) -> (bool, Option<u64>, Option<Stri
 
     let mut cmd = tokio::process::Command::new(binary);
     cmd.args(["--print", "--output-format", "stream-json", "--input-format", "stream-json", "--dangerously-skip-permissions"])
+        .envs(env.iter().cloned())
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
@@ -460,8 +473,15 @@ async fn run_diagnostics(
     binary_path: Option<String>,
     gh_token: Option<String>,
     provider: Option<String>,
+    workspace_path: Option<String>,
+    app: tauri::AppHandle,
 ) -> DiagnosticsResult {
     let is_claude = provider.as_deref() == Some("claude");
+    // Run every probe with the same workspace profile the agent would get
+    let env = workspace_path
+        .as_deref()
+        .map(|p| env_profiles::env_for_cwd(&app, p))
+        .unwrap_or_default();
 
     let binary = binary_path
         .as_deref()
@@ -494,6 +514,7 @@ async fn run_diagnostics(
     let version_result = tokio::time::timeout(
         std::time::Duration::from_secs(5),
         tokio::process::Command::new(&binary)
+            .envs(env.iter().cloned())
             .kill_on_drop(true)
             .arg("--version")
             .output(),
@@ -520,6 +541,7 @@ async fn run_diagnostics(
         let result = tokio::time::timeout(
             std::time::Duration::from_secs(5),
             tokio::process::Command::new(&binary)
+                .envs(env.iter().cloned())
                 .args(["auth", "status", "--json"])
                 .kill_on_drop(true)
                 .output(),
@@ -552,10 +574,10 @@ async fn run_diagnostics(
     // ACP/connection test
     let (acp_ok, acp_elapsed_ms, acp_error, acp_command, acp_stderr) = if copilot_binary_found {
         if is_claude {
-            test_claude_connection(&binary).await
+            test_claude_connection(&binary, &env).await
         } else {
             let token_ref = gh_token.as_deref();
-            test_acp_connection(&binary, token_ref).await
+            test_acp_connection(&binary, token_ref, &env).await
         }
     } else {
         let cmd = if is_claude {
@@ -566,22 +588,27 @@ async fn run_diagnostics(
         (false, None, Some("binary not found — skipping connection test".to_string()), cmd, None)
     };
 
+    // Probe output can echo tokens back (e.g. auth errors); mask them before
+    // the result reaches the UI
+    let extra: Vec<&str> = gh_token.as_deref().into_iter().collect();
+    let redact = |s: String| env_profiles::redact(&app, &s, &extra);
+
     DiagnosticsResult {
         platform,
         arch,
         copilot_binary_used: binary,
         copilot_binary_found,
-        copilot_version,
-        copilot_version_error,
+        copilot_version: copilot_version.map(redact),
+        copilot_version_error: copilot_version_error.map(redact),
         gh_token_set,
-        path_env,
+        path_env: redact(path_env),
         home_env,
         acp_ok,
         acp_elapsed_ms,
-        acp_error,
-        acp_command: Some(acp_command),
-        acp_stderr,
-        auth_status,
+        acp_error: acp_error.map(redact),
+        acp_command: Some(redact(acp_command)),
+        acp_stderr: acp_stderr.map(redact),
+        auth_status: auth_status.map(redact),
         auth_method,
     }
 }
@@ -647,6 +674,46 @@ fn save_comments(
     comments::save_comments(&conn, &markdown_path, &workspace_id, &comments_data)
 }
 
+#[tauri::command]
+fn comment_add(
+    markdown_path: String,
+    workspace_id: String,
+    comment: comments::NewComment,
+    db: tauri::State<comments::CommentsDb>,
+) -> Result<comments::Comment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    comments::add_comment(&conn, &markdown_path, &workspace_id, &comment)
+}
+
+#[tauri::command]
+fn comment_update(
+    id: String,
+    text: String,
+    db: tauri::State<comments::CommentsDb>,
+) -> Result<comments::Comment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    comments::update_comment_text(&conn, &id, &text)
+}
+
+#[tauri::command]
+fn comment_set_resolved(
+    id: String,
+    resolved: bool,
+    db: tauri::State<comments::CommentsDb>,
+) -> Result<comments::Comment, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    comments::set_comment_resolved(&conn, &id, resolved)
+}
+
+#[tauri::command]
+fn comment_delete(
+    id: String,
+    db: tauri::State<comments::CommentsDb>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    comments::delete_comment(&conn, &id)
+}
+
 #[tauri::command]
 fn count_unresolved_comments(
     file_paths: Vec<String>,
@@ -934,6 +1001,9 @@ pub fn run() {
         .manage(whisper::commands::TranscriberState(Mutex::new(None)))
         .manage(acp::commands::AcpState::default())
         .manage(acp::commands::AcpSessionStore::default())
+        .manage(env_profiles::EnvProfilesState::default())
+        .manage(checkpoints::CheckpointState::default())
+        .manage(plan_storage::PlanDirWatcher::default())
         .manage(whisper::watcher::WhisperWatcherState {
             models_watcher: Mutex::new(None),
             settings_watcher: Mutex::new(None),
@@ -987,7 +1057,18 @@ pub fn run() {
                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
             let conn = comments::init_db(&app_data)
                 .map_err(|e| Box::<dyn std::error::Error>::from(e))?;
+            match messages::recover_streaming_messages(&conn) {
+                Ok(0) => {}
+                Ok(n) => eprintln!("[db] Flagged {} interrupted streaming messages", n),
+                Err(e) => eprintln!("[db] Failed to recover streaming messages: {}", e),
+            }
+            match plan_runner::recover_runs(&conn) {
+                Ok(0) => {}
+                Ok(n) => eprintln!("[db] Flagged {} interrupted plan steps", n),
+                Err(e) => eprintln!("[db] Failed to recover plan runs: {}", e),
+            }
             app.manage(comments::CommentsDb(Mutex::new(conn)));
+            plan_storage::watch_all(app.handle());
 
             let shortcut_str = if let Ok(app_data_dir) = app.path().app_data_dir() {
                 let settings = whisper::model_manager::load_settings(&app_data_dir);
@@ -1089,6 +1170,10 @@ pub fn run() {
             install_cli_to_path,
             load_comments,
             save_comments,
+            comment_add,
+            comment_update,
+            comment_set_resolved,
+            comment_delete,
             count_unresolved_comments,
             hash_file,
             show_whisper_window,
@@ -1146,18 +1231,65 @@ pub fn run() {
             sessions::session_get,
             sessions::session_update_acp_id,
             sessions::session_update_plan_file_path,
-            sessions::session_update_phase,
+            phase::session_update_phase,
+            phase::session_phase_events,
             sessions::session_delete,
             sessions::session_update_acp_preferences,
+            sessions::session_list_filtered,
+            sessions::session_set_pinned,
+            sessions::session_set_archived,
+            sessions::session_add_tag,
+            sessions::session_remove_tag,
+            sessions::tag_list,
             sessions::workspace_acp_defaults_get,
             sessions::workspace_acp_defaults_set,
             sessions::forget_workspace_data,
+            compare::compare_create,
+            compare::compare_list,
+            compare::compare_get,
+            compare::compare_delete,
+            env_profiles::workspace_env_list,
+            env_profiles::workspace_env_set,
+            env_profiles::workspace_env_remove,
+            worktree::session_worktree_diff,
+            worktree::session_worktree_merge,
+            worktree::session_worktree_discard,
+            checkpoints::checkpoint_list,
+            checkpoints::checkpoint_restore,
+            tool_diffs::tool_diffs_list_pending,
+            tool_diffs::tool_diff_accept,
+            tool_diffs::tool_diff_reject,
+            bundle::session_export,
+            bundle::session_import,
+            history::session_fork,
+            history::session_rewind,
+            history::session_list_branches,
             plan_file::plan_write,
             plan_file::plan_read,
             plan_file::plan_path,
+            plan_file::plan_relocate,
+            plan_history::plan_revisions_list,
+            plan_history::plan_revision_read,
+            plan_history::plan_revision_diff,
+            plan_progress::plan_steps,
+            plan_progress::plan_step_set_status,
+            plan_runner::plan_run_state,
+            plan_runner::plan_run_start,
+            plan_runner::plan_run_approve,
+            plan_runner::plan_run_comment,
+            plan_runner::plan_run_retry,
+            plan_runner::plan_run_cancel,
+            plan_storage::workspace_plan_settings_get,
+            plan_storage::workspace_plan_settings_set,
+            plan_import::session_import_plan,
+            review::review_send_comments,
+            review::review_comment_feedback,
             messages::messages_list,
+            messages::messages_list_before,
+            messages::messages_list_after,
             messages::messages_count,
             messages::messages_delete_session,
+            messages::messages_search,
             run_diagnostics,
         ])
         .build(tauri::generate_context!())
@@ -0,0 +1,233 @@
+//! Block-level view of a Markdown document.
+//!
+//! Splits source text into the blocks a reader reviews — headings, list
+//! items, fenced code, tables, quotes and paragraphs — without a full parse.
+//! Each list item is its own block, so a checklist diffs item by item.
+//! Review comments instead address the blocks the viewer renders, which are
+//! found by parsing the document with comrak.
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, Options};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MarkdownBlock {
+    /// `heading`, `list_item`, `code`, `table`, `quote` or `paragraph`
+    pub kind: &'static str,
+    pub text: String,
+    /// One-based source lines covered by the block (inclusive)
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+fn is_fence(trimmed: &str) -> bool {
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+fn is_heading(trimmed: &str) -> bool {
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ')
+}
+
+pub(crate) fn is_list_item(trimmed: &str) -> bool {
+    if ["- ", "* ", "+ "].iter().any(|m| trimmed.starts_with(m)) {
+        return true;
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") "))
+}
+
+fn line_kind(trimmed: &str) -> &'static str {
+    if trimmed.starts_with('|') {
+        "table"
+    } else if trimmed.starts_with('>') {
+        "quote"
+    } else {
+        "paragraph"
+    }
+}
+
+pub fn split_blocks(markdown: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<MarkdownBlock> = None;
+    let mut in_fence = false;
+
+    for (idx, line) in markdown.lines().enumerate() {
+        let line_no = idx + 1;
+        let trimmed = line.trim_start();
+
+        if in_fence {
+            if let Some(block) = current.as_mut() {
+                block.text.push('\n');
+                block.text.push_str(line);
+                block.end_line = line_no;
+            }
+            if is_fence(trimmed) {
+                in_fence = false;
+                blocks.extend(current.take());
+            }
+            continue;
+        }
+
+        let starts_block = if trimmed.trim().is_empty() {
+            blocks.extend(current.take());
+            continue;
+        } else if is_fence(trimmed) {
+            in_fence = true;
+            Some("code")
+        } else if is_heading(trimmed) {
+            Some("heading")
+        } else if is_list_item(trimmed) {
+            Some("list_item")
+        } else {
+            None
+        };
+
+        match (starts_block, current.as_mut()) {
+            (None, Some(block)) => {
+                block.text.push('\n');
+                block.text.push_str(line);
+                block.end_line = line_no;
+            }
+            (kind, _) => {
+                blocks.extend(current.take());
+                current = Some(MarkdownBlock {
+                    kind: kind.unwrap_or_else(|| line_kind(trimmed)),
+                    text: line.to_string(),
+                    start_line: line_no,
+                    end_line: line_no,
+                });
+            }
+        }
+        // Headings never continue onto the next line
+        if current.as_ref().is_some_and(|b| b.kind == "heading") {
+            blocks.extend(current.take());
+        }
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// Options the viewer renders Markdown with, so blocks are parsed the way
+/// the page shows them.
+pub fn viewer_options() -> Options<'static> {
+    let mut options = Options::default();
+    options.extension.table = true;
+    options.extension.tasklist = true;
+    options.extension.strikethrough = true;
+    options.extension.autolink = true;
+    options.render.escape = true;
+    options
+}
+
+/// Source lines `start..=end` with trailing blank lines dropped.
+fn source_block(lines: &[&str], kind: &'static str, start: usize, end: usize) -> MarkdownBlock {
+    let mut end = end.min(lines.len()).max(start);
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    MarkdownBlock { kind, text: lines[start - 1..end].join("\n"), start_line: start, end_line: end }
+}
+
+/// Blocks keyed by the ids the Markdown viewer assigns them (`mkw-para-0`,
+/// `mkw-list-2`, ...), which is what review comments point at. The viewer
+/// numbers `p`, `li`, `pre` and `blockquote` elements in document order,
+/// skipping anything inside a list item except nested items, and headings
+/// by their position among the page's headings. Tables are not commentable
+/// and get no id.
+pub fn viewer_blocks(markdown: &str) -> Vec<(String, MarkdownBlock)> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &viewer_options());
+    let mut counters: HashMap<&str, usize> = HashMap::new();
+    let mut out = Vec::new();
+    let mut headings = 0;
+    for node in root.descendants() {
+        let in_item = node.ancestors().skip(1).any(|a| matches!(a.data.borrow().value, NodeValue::Item(_) | NodeValue::TaskItem(_)));
+        let ast = node.data.borrow();
+        let (start, end) = (ast.sourcepos.start.line, ast.sourcepos.end.line);
+        let (prefix, kind, end) = match &ast.value {
+            NodeValue::Heading(_) => {
+                out.push((format!("mkw-heading-{}", headings), source_block(&lines, "heading", start, end)));
+                headings += 1;
+                continue;
+            }
+            NodeValue::Item(_) | NodeValue::TaskItem(_) => {
+                // An item's own text stops where its nested list begins
+                let nested = node
+                    .children()
+                    .find(|c| matches!(c.data.borrow().value, NodeValue::List(_)))
+                    .map(|c| c.data.borrow().sourcepos.start.line);
+                ("list", "list_item", nested.map_or(end, |line| line.saturating_sub(1).max(start)))
+            }
+            _ if in_item => continue,
+            NodeValue::Paragraph => ("para", "paragraph", end),
+            NodeValue::CodeBlock(_) => ("code", "code", end),
+            NodeValue::BlockQuote => ("quote", "quote", end),
+            _ => continue,
+        };
+        let n = counters.entry(prefix).or_insert(0);
+        out.push((format!("mkw-{}-{}", prefix, n), source_block(&lines, kind, start, end)));
+        *n += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(markdown: &str) -> Vec<(String, usize, usize)> {
+        viewer_blocks(markdown).into_iter().map(|(id, b)| (id, b.start_line, b.end_line)).collect()
+    }
+
+    #[test]
+    fn list_item_code_is_part_of_the_item() {
+        let md = "- step one\n\n  ```sh\n  make\n  ```\n- step two\n  - nested\n\nAfter.\n";
+        assert_eq!(
+            ids(md),
+            vec![
+                ("mkw-list-0".to_string(), 1, 5),
+                ("mkw-list-1".to_string(), 6, 6),
+                ("mkw-list-2".to_string(), 7, 7),
+                ("mkw-para-0".to_string(), 9, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn quote_paragraphs_are_counted_as_paragraphs() {
+        let md = "Intro.\n\n> first\n>\n> second\n\nOutro.\n";
+        assert_eq!(
+            ids(md),
+            vec![
+                ("mkw-para-0".to_string(), 1, 1),
+                ("mkw-quote-0".to_string(), 3, 5),
+                ("mkw-para-1".to_string(), 3, 3),
+                ("mkw-para-2".to_string(), 5, 5),
+                ("mkw-para-3".to_string(), 7, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn headings_are_numbered_in_page_order() {
+        let md = "Title\n=====\n\n##### Deep\n\nSub\n---\n\n| a |\n|---|\n| 1 |\n";
+        assert_eq!(
+            ids(md),
+            vec![
+                ("mkw-heading-0".to_string(), 1, 2),
+                ("mkw-heading-1".to_string(), 4, 4),
+                ("mkw-heading-2".to_string(), 6, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_blocks_keeps_fences_whole() {
+        let blocks = split_blocks("# A\n```\n\n- x\n```\n- y\n");
+        let kinds: Vec<_> = blocks.iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, vec!["heading", "code", "list_item"]);
+    }
+}
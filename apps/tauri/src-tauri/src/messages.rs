@@ -12,7 +12,53 @@ pub struct MessageRecord {
     pub tool_call_id: Option<String>,
     pub tool_title: Option<String>,
     pub tool_status: Option<String>,
+    /// Latest known state of a tool call: the ACP `tool_call` payload with every
+    /// `tool_call_update` merged in (kind, rawInput, rawOutput, locations, content)
+    pub tool_payload: Option<serde_json::Value>,
+    /// Tool call timings in milliseconds since the epoch
+    pub started_at: Option<i64>,
+    pub completed_at: Option<i64>,
     pub created_at: i64,
+    /// `streaming` while an answer is still being received, `interrupted` when
+    /// the app or agent stopped before it finished; `None` once complete
+    pub stream_state: Option<String>,
+    /// Insert order across all messages; the pagination cursor
+    #[serde(default)]
+    pub seq: i64,
+    /// Every payload received for the tool call, oldest first; only filled when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_history: Option<Vec<ToolCallUpdate>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCallUpdate {
+    pub payload: serde_json::Value,
+    pub received_at: i64,
+}
+
+const MESSAGE_COLUMNS: &str = "id, session_id, role, content, message_type,
+                tool_call_id, tool_title, tool_status, tool_payload, started_at, completed_at, created_at, stream_state, seq";
+
+fn row_to_message(row: &rusqlite::Row) -> rusqlite::Result<MessageRecord> {
+    Ok(MessageRecord {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        message_type: row.get(4)?,
+        tool_call_id: row.get(5)?,
+        tool_title: row.get(6)?,
+        tool_status: row.get(7)?,
+        tool_payload: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        started_at: row.get(9)?,
+        completed_at: row.get(10)?,
+        created_at: row.get(11)?,
+        stream_state: row.get(12)?,
+        seq: row.get(13)?,
+        tool_history: None,
+    })
 }
 
 pub fn list_messages(
@@ -22,33 +68,21 @@ pub fn list_messages(
     limit: i64,
 ) -> Result<Vec<MessageRecord>, String> {
     let mut stmt = conn
-        .prepare(
-            "SELECT id, session_id, role, content, message_type,
-                    tool_call_id, tool_title, tool_status, created_at
+        .prepare(&format!(
+            "SELECT {}
              FROM (
                SELECT * FROM messages
                WHERE session_id = ?1
-               ORDER BY created_at DESC
+               ORDER BY seq DESC
                LIMIT ?2 OFFSET ?3
              )
-             ORDER BY created_at ASC",
-        )
+             ORDER BY seq ASC",
+            MESSAGE_COLUMNS
+        ))
         .map_err(|e| format!("Prepare error: {}", e))?;
 
     let rows = stmt
-        .query_map(params![session_id, limit, offset], |row| {
-            Ok(MessageRecord {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                message_type: row.get(4)?,
-                tool_call_id: row.get(5)?,
-                tool_title: row.get(6)?,
-                tool_status: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })
+        .query_map(params![session_id, limit, offset], row_to_message)
         .map_err(|e| format!("Query error: {}", e))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Row error: {}", e))?;
@@ -56,6 +90,58 @@ pub fn list_messages(
     Ok(rows)
 }
 
+/// One window of a session's messages in ascending order.
+#[derive(Debug, Serialize, Clone)]
+pub struct MessagePage {
+    pub messages: Vec<MessageRecord>,
+    /// Whether more messages exist beyond this window in the paging direction
+    pub has_more: bool,
+}
+
+fn query_page(conn: &Connection, sql: &str, session_id: &str, cursor: i64, limit: i64) -> Result<MessagePage, String> {
+    let limit = limit.max(1);
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Prepare error: {}", e))?;
+    let mut messages = stmt
+        .query_map(params![session_id, cursor, limit + 1], row_to_message)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit as usize);
+    Ok(MessagePage { messages, has_more })
+}
+
+/// Up to `limit` messages immediately before `before_seq` (or the latest ones
+/// when `None`), oldest first.
+pub fn list_messages_before(
+    conn: &Connection,
+    session_id: &str,
+    before_seq: Option<i64>,
+    limit: i64,
+) -> Result<MessagePage, String> {
+    let sql = format!(
+        "SELECT {} FROM messages WHERE session_id = ?1 AND seq < ?2 ORDER BY seq DESC LIMIT ?3",
+        MESSAGE_COLUMNS
+    );
+    let mut page = query_page(conn, &sql, session_id, before_seq.unwrap_or(i64::MAX), limit)?;
+    page.messages.reverse();
+    Ok(page)
+}
+
+/// Up to `limit` messages immediately after `after_seq`, oldest first.
+pub fn list_messages_after(
+    conn: &Connection,
+    session_id: &str,
+    after_seq: i64,
+    limit: i64,
+) -> Result<MessagePage, String> {
+    let sql = format!(
+        "SELECT {} FROM messages WHERE session_id = ?1 AND seq > ?2 ORDER BY seq ASC LIMIT ?3",
+        MESSAGE_COLUMNS
+    );
+    query_page(conn, &sql, session_id, after_seq, limit)
+}
+
 pub fn save_message(
     conn: &Connection,
     session_id: &str,
@@ -76,6 +162,9 @@ pub fn save_message(
         params![id, session_id, role, content, message_type, tool_call_id, tool_title, tool_status, now],
     )
     .map_err(|e| format!("Insert error: {}", e))?;
+    let seq: i64 = conn
+        .query_row("SELECT seq FROM messages WHERE id = ?1", params![id], |row| row.get(0))
+        .map_err(|e| format!("Query error after insert: {}", e))?;
 
     Ok(MessageRecord {
         id,
@@ -86,10 +175,115 @@ pub fn save_message(
         tool_call_id: tool_call_id.map(str::to_string),
         tool_title: tool_title.map(str::to_string),
         tool_status: tool_status.map(str::to_string),
+        tool_payload: None,
+        started_at: None,
+        completed_at: None,
         created_at: now,
+        stream_state: None,
+        seq,
+        tool_history: None,
     })
 }
 
+/// Inserts the row for an assistant or thinking message that is still
+/// streaming. Returns its id.
+pub fn begin_streaming_message(
+    conn: &Connection,
+    session_id: &str,
+    message_type: Option<&str>,
+    content: &str,
+) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO messages (id, session_id, role, content, message_type, stream_state, created_at)
+         VALUES (?1, ?2, 'assistant', ?3, ?4, 'streaming', ?5)",
+        params![id, session_id, content, message_type, crate::comments::now()],
+    )
+    .map_err(|e| format!("Insert error: {}", e))?;
+    Ok(id)
+}
+
+pub fn update_streaming_message(conn: &Connection, id: &str, content: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE messages SET content = ?1 WHERE id = ?2",
+        params![content, id],
+    )
+    .map_err(|e| format!("Update error: {}", e))?;
+    Ok(())
+}
+
+/// Writes the final text of a streamed message. `stream_state` is `None` for a
+/// completed message or `Some("interrupted")`.
+pub fn finish_streaming_message(
+    conn: &Connection,
+    id: &str,
+    content: &str,
+    stream_state: Option<&str>,
+) -> Result<MessageRecord, String> {
+    conn.execute(
+        "UPDATE messages SET content = ?1, stream_state = ?2 WHERE id = ?3",
+        params![content, stream_state, id],
+    )
+    .map_err(|e| format!("Update error: {}", e))?;
+    conn.query_row(
+        &format!("SELECT {} FROM messages WHERE id = ?1", MESSAGE_COLUMNS),
+        params![id],
+        row_to_message,
+    )
+    .map_err(|e| format!("Query error after update: {}", e))
+}
+
+/// Flags messages left `streaming` by a crash as `interrupted` so the partial
+/// text stays in the transcript; empty ones are dropped. Run at startup,
+/// before any agent is connected. Returns the number of rows flagged.
+pub fn recover_streaming_messages(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM messages WHERE stream_state = 'streaming' AND content = ''",
+        [],
+    )
+    .map_err(|e| format!("Delete error: {}", e))?;
+    conn.execute(
+        "UPDATE messages SET stream_state = 'interrupted' WHERE stream_state = 'streaming'",
+        [],
+    )
+    .map_err(|e| format!("Update error: {}", e))
+}
+
+/// Saves a user prompt idempotently on its client-generated id: a retried
+/// send with the same id returns the stored message instead of a new row.
+/// Returns the message and whether it was newly inserted.
+pub fn save_user_message(
+    conn: &Connection,
+    session_id: &str,
+    content: &str,
+    client_message_id: &str,
+) -> Result<(MessageRecord, bool), String> {
+    let id = Uuid::new_v4().to_string();
+    let now = crate::comments::now();
+
+    let inserted = conn
+        .execute(
+            "INSERT INTO messages (id, session_id, role, content, client_message_id, created_at)
+             VALUES (?1, ?2, 'user', ?3, ?4, ?5)
+             ON CONFLICT(session_id, client_message_id) WHERE client_message_id IS NOT NULL DO NOTHING",
+            params![id, session_id, content, client_message_id, now],
+        )
+        .map_err(|e| format!("Insert error: {}", e))?
+        > 0;
+
+    let record = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM messages WHERE session_id = ?1 AND client_message_id = ?2",
+                MESSAGE_COLUMNS
+            ),
+            params![session_id, client_message_id],
+            row_to_message,
+        )
+        .map_err(|e| format!("Query error after insert: {}", e))?;
+    Ok((record, inserted))
+}
+
 pub fn update_message_by_tool_call_id(
     conn: &Connection,
     session_id: &str,
@@ -114,33 +308,98 @@ pub fn update_message_by_tool_call_id(
     }
 
     conn.query_row(
-        "SELECT id, session_id, role, content, message_type,
-                tool_call_id, tool_title, tool_status, created_at
-         FROM messages
-         WHERE session_id = ?1 AND tool_call_id = ?2",
+        &format!("SELECT {} FROM messages WHERE session_id = ?1 AND tool_call_id = ?2", MESSAGE_COLUMNS),
         params![session_id, tool_call_id],
-        |row| {
-            Ok(MessageRecord {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                message_type: row.get(4)?,
-                tool_call_id: row.get(5)?,
-                tool_title: row.get(6)?,
-                tool_status: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        },
+        row_to_message,
+    )
+    .map_err(|e| format!("Query error after update: {}", e))
+}
+
+/// Merges a `tool_call` / `tool_call_update` payload into the tool message's
+/// stored payload, appends it to the update history and stamps the start and
+/// completion times. Returns `None` when no message has that tool call id.
+pub fn record_tool_update(
+    conn: &Connection,
+    session_id: &str,
+    tool_call_id: &str,
+    payload: &serde_json::Value,
+) -> Result<Option<MessageRecord>, String> {
+    let existing: Option<(String, Option<String>)> = match conn.query_row(
+        "SELECT id, tool_payload FROM messages WHERE session_id = ?1 AND tool_call_id = ?2",
+        params![session_id, tool_call_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ) {
+        Ok(row) => Some(row),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("Query error: {}", e)),
+    };
+    let Some((message_id, stored)) = existing else { return Ok(None) };
+
+    // Updates only carry the fields that changed, so merge them key by key
+    let mut merged = stored
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let (Some(target), Some(fields)) = (merged.as_object_mut(), payload.as_object()) {
+        for (key, value) in fields {
+            if key != "sessionUpdate" {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let finished = matches!(
+        payload.get("status").and_then(|s| s.as_str()),
+        Some("completed") | Some("failed")
+    );
+    conn.execute(
+        "UPDATE messages SET tool_payload = ?1,
+                started_at = COALESCE(started_at, ?2),
+                completed_at = CASE WHEN ?3 THEN COALESCE(completed_at, ?2) ELSE completed_at END
+         WHERE id = ?4",
+        params![merged.to_string(), now_ms, finished, message_id],
+    )
+    .map_err(|e| format!("Update tool payload error: {}", e))?;
+    conn.execute(
+        "INSERT INTO tool_call_updates (message_id, payload, received_at) VALUES (?1, ?2, ?3)",
+        params![message_id, payload.to_string(), now_ms],
+    )
+    .map_err(|e| format!("Insert tool_call_update error: {}", e))?;
+
+    conn.query_row(
+        &format!("SELECT {} FROM messages WHERE id = ?1", MESSAGE_COLUMNS),
+        params![message_id],
+        row_to_message,
     )
+    .map(Some)
     .map_err(|e| format!("Query error after update: {}", e))
 }
 
+pub fn list_tool_history(conn: &Connection, message_id: &str) -> Result<Vec<ToolCallUpdate>, String> {
+    let mut stmt = conn
+        .prepare("SELECT payload, received_at FROM tool_call_updates WHERE message_id = ?1 ORDER BY id ASC")
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![message_id], |row| {
+            let payload: String = row.get(0)?;
+            Ok(ToolCallUpdate {
+                payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                received_at: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+/// Text-based dedupe for prompts sent without a client message id.
 pub fn is_duplicate_user_message(conn: &Connection, session_id: &str, content: &str) -> bool {
     conn.query_row(
         "SELECT content FROM messages
          WHERE session_id = ?1 AND role = 'user'
-         ORDER BY created_at DESC LIMIT 1",
+         ORDER BY seq DESC LIMIT 1",
         params![session_id],
         |row| row.get::<_, String>(0),
     )
@@ -148,6 +407,45 @@ pub fn is_duplicate_user_message(conn: &Connection, session_id: &str, content: &
     .unwrap_or(false)
 }
 
+/// Returns the user prompts and plain assistant replies of a session in order,
+/// skipping thinking, tool and notice rows.
+pub fn list_chat_history(conn: &Connection, session_id: &str) -> Result<Vec<MessageRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM messages
+             WHERE session_id = ?1 AND message_type IS NULL
+             ORDER BY seq ASC",
+            MESSAGE_COLUMNS
+        ))
+        .map_err(|e| format!("Prepare error: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![session_id], row_to_message)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    Ok(rows)
+}
+
+/// Returns the most recent plain assistant reply (no thinking/tool/notice rows).
+pub fn last_assistant_message(conn: &Connection, session_id: &str) -> Result<Option<MessageRecord>, String> {
+    match conn.query_row(
+        &format!(
+            "SELECT {} FROM messages
+             WHERE session_id = ?1 AND role = 'assistant' AND message_type IS NULL
+             ORDER BY seq DESC LIMIT 1",
+            MESSAGE_COLUMNS
+        ),
+        params![session_id],
+        row_to_message,
+    ) {
+        Ok(record) => Ok(Some(record)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("Query error: {}", e)),
+    }
+}
+
 pub fn count_session_messages(conn: &Connection, session_id: &str) -> Result<i64, String> {
     conn.query_row(
         "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
@@ -157,6 +455,126 @@ pub fn count_session_messages(conn: &Connection, session_id: &str) -> Result<i64
     .map_err(|e| format!("Count error: {}", e))
 }
 
+// --- Full-text search ---
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchHit {
+    pub session_id: String,
+    pub session_name: String,
+    pub workspace_id: String,
+    pub provider: String,
+    /// `None` when the hit is the session's name or initial prompt
+    pub message_id: Option<String>,
+    pub role: Option<String>,
+    pub message_type: Option<String>,
+    /// Matching text with hits wrapped in `<mark>` / `</mark>`
+    pub snippet: String,
+    /// bm25 score; lower is a better match
+    pub rank: f64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct SearchFilters {
+    pub workspace_id: Option<String>,
+    pub provider: Option<String>,
+    /// Epoch seconds, inclusive
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Archived sessions are left out unless this is `Some(true)`
+    pub include_archived: Option<bool>,
+}
+
+/// Turns free text into an FTS5 query: every word must match, the last one as
+/// a prefix so results update while typing. Quoting keeps FTS operators in the
+/// input from being interpreted.
+fn to_fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", terms.join(" ")))
+}
+
+pub fn search_messages(
+    conn: &Connection,
+    query: &str,
+    filters: &SearchFilters,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<SearchHit>, String> {
+    let Some(fts_query) = to_fts_query(query) else { return Ok(Vec::new()) };
+
+    // Session-level hits get a small boost: a name match usually is the conversation
+    let sql = "
+        SELECT * FROM (
+            SELECT s.id, s.name, s.workspace_id, s.provider, m.id, m.role, m.message_type,
+                   snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16),
+                   bm25(messages_fts), m.created_at
+            FROM messages_fts
+            JOIN messages m ON m.search_id = messages_fts.rowid
+            JOIN sessions s ON s.id = m.session_id
+            WHERE messages_fts MATCH ?1
+              AND (?2 IS NULL OR s.workspace_id = ?2)
+              AND (?3 IS NULL OR s.provider = ?3)
+              AND (?4 IS NULL OR m.created_at >= ?4)
+              AND (?5 IS NULL OR m.created_at <= ?5)
+              AND (?8 OR s.archived = 0)
+            UNION ALL
+            SELECT s.id, s.name, s.workspace_id, s.provider, NULL, NULL, NULL,
+                   snippet(sessions_fts, -1, '<mark>', '</mark>', '…', 16),
+                   bm25(sessions_fts, 2.0, 1.0) * 1.5, s.created_at
+            FROM sessions_fts
+            JOIN sessions s ON s.search_id = sessions_fts.rowid
+            WHERE sessions_fts MATCH ?1
+              AND (?2 IS NULL OR s.workspace_id = ?2)
+              AND (?3 IS NULL OR s.provider = ?3)
+              AND (?4 IS NULL OR s.created_at >= ?4)
+              AND (?5 IS NULL OR s.created_at <= ?5)
+              AND (?8 OR s.archived = 0)
+        )
+        ORDER BY 9 ASC, 10 DESC
+        LIMIT ?6 OFFSET ?7";
+
+    let mut stmt = conn.prepare(sql)
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(
+            params![
+                fts_query,
+                filters.workspace_id,
+                filters.provider,
+                filters.from,
+                filters.to,
+                limit,
+                offset,
+                filters.include_archived.unwrap_or(false),
+            ],
+            |row| {
+                Ok(SearchHit {
+                    session_id: row.get(0)?,
+                    session_name: row.get(1)?,
+                    workspace_id: row.get(2)?,
+                    provider: row.get(3)?,
+                    message_id: row.get(4)?,
+                    role: row.get(5)?,
+                    message_type: row.get(6)?,
+                    snippet: row.get(7)?,
+                    rank: row.get(8)?,
+                    created_at: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Search error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    Ok(rows)
+}
+
 // --- Tauri commands ---
 
 use crate::comments::CommentsDb;
@@ -166,10 +584,39 @@ pub fn messages_list(
     session_id: String,
     offset: Option<i64>,
     limit: Option<i64>,
+    include_tool_history: Option<bool>,
     db: tauri::State<CommentsDb>,
 ) -> Result<Vec<MessageRecord>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    list_messages(&conn, &session_id, offset.unwrap_or(0), limit.unwrap_or(50))
+    let mut messages = list_messages(&conn, &session_id, offset.unwrap_or(0), limit.unwrap_or(50))?;
+    if include_tool_history.unwrap_or(false) {
+        for message in messages.iter_mut().filter(|m| m.tool_call_id.is_some()) {
+            message.tool_history = Some(list_tool_history(&conn, &message.id)?);
+        }
+    }
+    Ok(messages)
+}
+
+#[tauri::command]
+pub fn messages_list_before(
+    session_id: String,
+    before_seq: Option<i64>,
+    limit: Option<i64>,
+    db: tauri::State<CommentsDb>,
+) -> Result<MessagePage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_messages_before(&conn, &session_id, before_seq, limit.unwrap_or(50))
+}
+
+#[tauri::command]
+pub fn messages_list_after(
+    session_id: String,
+    after_seq: i64,
+    limit: Option<i64>,
+    db: tauri::State<CommentsDb>,
+) -> Result<MessagePage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_messages_after(&conn, &session_id, after_seq, limit.unwrap_or(50))
 }
 
 #[tauri::command]
@@ -191,3 +638,66 @@ pub fn messages_delete_session(
         .map_err(|e| format!("Delete error: {}", e))?;
     Ok(())
 }
+
+#[tauri::command]
+pub fn messages_search(
+    query: String,
+    filters: Option<SearchFilters>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<SearchHit>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    search_messages(&conn, &query, &filters.unwrap_or_default(), offset.unwrap_or(0), limit.unwrap_or(50))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::test_support::TestDb;
+
+    /// A session with messages "m0".."m{n-1}", and their seqs.
+    fn session_with_messages(n: usize) -> (TestDb, String, Vec<i64>) {
+        let conn = TestDb::new();
+        let id = conn.session();
+        let seqs = (0..n)
+            .map(|i| save_message(&conn, &id, "user", &format!("m{}", i), None, None, None, None).unwrap().seq)
+            .collect();
+        (conn, id, seqs)
+    }
+
+    fn contents(page: &MessagePage) -> Vec<&str> {
+        page.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_backwards_from_the_latest() {
+        let (conn, id, seqs) = session_with_messages(5);
+        let latest = list_messages_before(&conn, &id, None, 2).unwrap();
+        assert_eq!(contents(&latest), ["m3", "m4"]);
+        assert!(latest.has_more);
+
+        let older = list_messages_before(&conn, &id, Some(seqs[3]), 2).unwrap();
+        assert_eq!(contents(&older), ["m1", "m2"]);
+        assert!(older.has_more);
+
+        let oldest = list_messages_before(&conn, &id, Some(seqs[1]), 2).unwrap();
+        assert_eq!(contents(&oldest), ["m0"]);
+        assert!(!oldest.has_more);
+    }
+
+    #[test]
+    fn pages_forwards_after_a_seq() {
+        let (conn, id, seqs) = session_with_messages(4);
+        let next = list_messages_after(&conn, &id, seqs[0], 2).unwrap();
+        assert_eq!(contents(&next), ["m1", "m2"]);
+        assert!(next.has_more);
+
+        let last = list_messages_after(&conn, &id, seqs[2], 2).unwrap();
+        assert_eq!(contents(&last), ["m3"]);
+        assert!(!last.has_more);
+
+        // A limit below one still returns a message
+        assert_eq!(contents(&list_messages_after(&conn, &id, seqs[0], 0).unwrap()), ["m1"]);
+    }
+}
@@ -0,0 +1,315 @@
+//! Plan-phase state machine.
+//!
+//! A session moves `idle → planning → reviewing → executing → done`. The
+//! back-edges let a reviewer send a plan back for changes, let execution
+//! return to planning or review when it goes off course, and let a finished
+//! session start a new plan. Every accepted transition is recorded in
+//! `session_phase_events` so the UI can show when a plan was approved.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    Planning,
+    Reviewing,
+    Executing,
+    Done,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Idle,
+        Phase::Planning,
+        Phase::Reviewing,
+        Phase::Executing,
+        Phase::Done,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Idle => "idle",
+            Phase::Planning => "planning",
+            Phase::Reviewing => "reviewing",
+            Phase::Executing => "executing",
+            Phase::Done => "done",
+        }
+    }
+
+    /// Phases reachable from `self` in a single transition.
+    pub fn successors(self) -> &'static [Phase] {
+        match self {
+            Phase::Idle => &[Phase::Planning],
+            Phase::Planning => &[Phase::Reviewing, Phase::Idle],
+            Phase::Reviewing => &[Phase::Executing, Phase::Planning],
+            Phase::Executing => &[Phase::Done, Phase::Reviewing, Phase::Planning],
+            Phase::Done => &[Phase::Planning, Phase::Idle],
+        }
+    }
+
+    pub fn can_transition_to(self, to: Phase) -> bool {
+        self.successors().contains(&to)
+    }
+}
+
+impl FromStr for Phase {
+    type Err = PhaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Phase::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| PhaseError::UnknownPhase { phase: s.to_string() })
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Serialized as `{ kind, ... }` so the frontend can tell a rejected
+/// transition apart from a storage failure.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PhaseError {
+    UnknownPhase { phase: String },
+    InvalidTransition { from: String, to: String, allowed: Vec<String> },
+    SessionNotFound { session_id: String },
+    Storage { message: String },
+}
+
+impl fmt::Display for PhaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhaseError::UnknownPhase { phase } => write!(
+                f,
+                "Invalid phase: {}. Must be one of: {:?}",
+                phase,
+                Phase::ALL.map(Phase::as_str)
+            ),
+            PhaseError::InvalidTransition { from, to, allowed } => write!(
+                f,
+                "Cannot move from {} to {} (allowed: {})",
+                from,
+                to,
+                allowed.join(", ")
+            ),
+            PhaseError::SessionNotFound { session_id } => {
+                write!(f, "Session not found: {}", session_id)
+            }
+            PhaseError::Storage { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for PhaseError {}
+
+impl From<PhaseError> for String {
+    fn from(e: PhaseError) -> Self {
+        e.to_string()
+    }
+}
+
+fn storage(context: &str) -> impl Fn(rusqlite::Error) -> PhaseError + '_ {
+    move |e| PhaseError::Storage { message: format!("{}: {}", context, e) }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PhaseEvent {
+    pub id: i64,
+    pub session_id: String,
+    pub from_phase: String,
+    pub to_phase: String,
+    pub reason: Option<String>,
+    pub created_at: i64,
+}
+
+fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<PhaseEvent> {
+    Ok(PhaseEvent {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        from_phase: row.get(2)?,
+        to_phase: row.get(3)?,
+        reason: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Moves the session to `to`, recording the transition. Moving to the phase
+/// the session is already in is a no-op and returns `None`.
+pub fn transition(
+    conn: &Connection,
+    session_id: &str,
+    to: Phase,
+    reason: Option<&str>,
+) -> Result<Option<PhaseEvent>, PhaseError> {
+    let current: Option<String> = conn
+        .query_row(
+            "SELECT phase FROM sessions WHERE id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage("Query phase error"))?;
+    let from: Phase = current
+        .ok_or_else(|| PhaseError::SessionNotFound { session_id: session_id.to_string() })?
+        .parse()?;
+
+    if from == to {
+        return Ok(None);
+    }
+    if !from.can_transition_to(to) {
+        return Err(PhaseError::InvalidTransition {
+            from: from.to_string(),
+            to: to.to_string(),
+            allowed: from.successors().iter().map(|p| p.to_string()).collect(),
+        });
+    }
+
+    let now = crate::comments::now();
+    // Joins the caller's transaction when there is one
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction().map_err(storage("Transaction error"))?)
+    } else {
+        None
+    };
+    conn.execute(
+        "UPDATE sessions SET phase = ?1, updated_at = ?2 WHERE id = ?3",
+        params![to.as_str(), now, session_id],
+    )
+    .map_err(storage("Update phase error"))?;
+    conn.execute(
+        "INSERT INTO session_phase_events (session_id, from_phase, to_phase, reason, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![session_id, from.as_str(), to.as_str(), reason, now],
+    )
+    .map_err(storage("Insert phase event error"))?;
+    let id = conn.last_insert_rowid();
+    if let Some(tx) = tx {
+        tx.commit().map_err(storage("Commit error"))?;
+    }
+
+    Ok(Some(PhaseEvent {
+        id,
+        session_id: session_id.to_string(),
+        from_phase: from.to_string(),
+        to_phase: to.to_string(),
+        reason: reason.map(str::to_string),
+        created_at: now,
+    }))
+}
+
+pub fn list_events(conn: &Connection, session_id: &str) -> Result<Vec<PhaseEvent>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, from_phase, to_phase, reason, created_at
+             FROM session_phase_events WHERE session_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], row_to_event)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::Emitter;
+
+/// Returns the recorded event, or `None` when the session was already in `phase`.
+#[tauri::command]
+pub fn session_update_phase(
+    id: String,
+    phase: String,
+    reason: Option<String>,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<Option<PhaseEvent>, PhaseError> {
+    let to: Phase = phase.parse()?;
+    let conn = db.0.lock().map_err(|e| PhaseError::Storage { message: e.to_string() })?;
+    let event = transition(&conn, &id, to, reason.as_deref())?;
+    if let Some(event) = &event {
+        let _ = app.emit("session:phase-changed", event);
+    }
+    Ok(event)
+}
+
+#[tauri::command]
+pub fn session_phase_events(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<PhaseEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_events(&conn, &session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::test_support::TestDb;
+
+    #[test]
+    fn successors_matrix() {
+        use Phase::*;
+        let allowed = [
+            (Idle, Planning),
+            (Planning, Reviewing),
+            (Planning, Idle),
+            (Reviewing, Executing),
+            (Reviewing, Planning),
+            (Executing, Done),
+            (Executing, Reviewing),
+            (Executing, Planning),
+            (Done, Planning),
+            (Done, Idle),
+        ];
+        for from in Phase::ALL {
+            for to in Phase::ALL {
+                assert_eq!(from.can_transition_to(to), allowed.contains(&(from, to)), "{} -> {}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_every_phase_name() {
+        for phase in Phase::ALL {
+            assert_eq!(phase.as_str().parse::<Phase>(), Ok(phase));
+        }
+        assert!(matches!("paused".parse::<Phase>(), Err(PhaseError::UnknownPhase { .. })));
+    }
+
+    #[test]
+    fn transition_records_accepted_moves_only() {
+        let conn = TestDb::new();
+        let id = conn.session();
+        assert_eq!(transition(&conn, &id, Phase::Idle, None).unwrap().map(|e| e.id), None);
+
+        let event = transition(&conn, &id, Phase::Planning, Some("start")).unwrap().unwrap();
+        assert_eq!((event.from_phase.as_str(), event.to_phase.as_str()), ("idle", "planning"));
+
+        let err = transition(&conn, &id, Phase::Done, None).unwrap_err();
+        assert_eq!(
+            err,
+            PhaseError::InvalidTransition {
+                from: "planning".to_string(),
+                to: "done".to_string(),
+                allowed: vec!["reviewing".to_string(), "idle".to_string()],
+            }
+        );
+        assert_eq!(crate::sessions::get_session(&conn, &id).unwrap().phase, "planning");
+        assert_eq!(list_events(&conn, &id).unwrap().len(), 1);
+
+        assert!(matches!(
+            transition(&conn, "missing", Phase::Planning, None),
+            Err(PhaseError::SessionNotFound { .. })
+        ));
+    }
+}
@@ -1,6 +1,8 @@
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-fn get_plans_dir(app_data_dir: &PathBuf) -> PathBuf {
+pub(crate) fn get_plans_dir(app_data_dir: &PathBuf) -> PathBuf {
     app_data_dir.join("plans")
 }
 
@@ -8,35 +10,221 @@ pub fn get_plan_path(app_data_dir: &PathBuf, session_id: &str) -> PathBuf {
     get_plans_dir(app_data_dir).join(format!("{}.md", session_id))
 }
 
-fn ensure_plans_dir(app_data_dir: &PathBuf) -> Result<(), String> {
-    let dir = get_plans_dir(app_data_dir);
-    std::fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create plans directory: {}", e))
+pub fn delete_plan(app_data_dir: &PathBuf, session_id: &str) -> Result<(), String> {
+    let path = get_plan_path(app_data_dir, session_id);
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to delete plan: {}", e))?;
+    }
+    Ok(())
 }
 
-pub fn write_plan(app_data_dir: &PathBuf, session_id: &str, markdown: &str) -> Result<(), String> {
-    ensure_plans_dir(app_data_dir)?;
-    let path = get_plan_path(app_data_dir, session_id);
-    std::fs::write(&path, markdown)
-        .map_err(|e| format!("Failed to write plan: {}", e))
+/// Plan file of a session: the path recorded on the session (e.g. a plan the
+/// agent wrote elsewhere) or the app-data default.
+pub fn session_plan_path(app_data_dir: &PathBuf, session_id: &str, plan_file_path: Option<&str>) -> PathBuf {
+    plan_file_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_plan_path(app_data_dir, session_id))
 }
 
-pub fn read_plan(app_data_dir: &PathBuf, session_id: &str) -> Result<String, String> {
-    let path = get_plan_path(app_data_dir, session_id);
-    if !path.exists() {
-        return Ok(String::new());
+// --- Plan steps ---
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PlanStep {
+    /// Derived from the step's text, so it survives edits elsewhere in the plan
+    pub id: String,
+    /// `heading` or `task`
+    pub kind: &'static str,
+    pub title: String,
+    /// Heading level, or list indentation depth for tasks
+    pub depth: usize,
+    /// One-based line of the step in the plan file
+    pub line: usize,
+    pub parent_id: Option<String>,
+    /// Whether the task box is checked in the file (always false for headings)
+    pub checked: bool,
+    /// `pending`, `in_progress`, `done` or `blocked`
+    pub status: String,
+}
+
+/// Lowercased words of `text` with Markdown punctuation stripped.
+pub(crate) fn normalize_step_text(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '.' && c != '/' && c != '-' && c != '_')
+        .map(|w| w.trim_matches(|c: char| c == '.' || c == '-' || c == '_'))
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn heading_text(trimmed: &str) -> Option<(usize, &str)> {
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) || !trimmed[level..].starts_with(' ') {
+        return None;
     }
-    std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read plan: {}", e))
+    Some((level, trimmed[level..].trim().trim_end_matches('#').trim()))
 }
 
-pub fn delete_plan(app_data_dir: &PathBuf, session_id: &str) -> Result<(), String> {
-    let path = get_plan_path(app_data_dir, session_id);
-    if path.exists() {
-        std::fs::remove_file(&path)
-            .map_err(|e| format!("Failed to delete plan: {}", e))?;
+/// `(checked, text)` for a task-list line such as `- [x] Run tests`.
+fn task_text(trimmed: &str) -> Option<(bool, &str)> {
+    let rest = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|m| trimmed.strip_prefix(m))
+        .or_else(|| {
+            let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+            (digits > 0)
+                .then(|| trimmed[digits..].strip_prefix(". ").or_else(|| trimmed[digits..].strip_prefix(") ")))
+                .flatten()
+        })?;
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    Some((checked, rest[3..].trim()))
+}
+
+/// Headings that read like a step ("Step 2: …", "3. …", "Phase 1 — …").
+fn is_step_heading(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    lower.starts_with(|c: char| c.is_ascii_digit())
+        || ["step ", "phase ", "stage ", "milestone "].iter().any(|p| lower.starts_with(p))
+}
+
+/// Extracts steps from a plan: every task-list item, plus the headings that
+/// either look like steps or contain tasks. The top-level title is skipped.
+/// Statuses come from the checkboxes only; tracked progress is merged in by
+/// `plan_progress`.
+pub fn parse_plan_steps(markdown: &str) -> Vec<PlanStep> {
+    let mut steps: Vec<PlanStep> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    // Open headings by level, as indices into `candidates`
+    let mut headings: Vec<(usize, usize)> = Vec::new();
+    let mut candidates: Vec<(PlanStep, bool)> = Vec::new();
+    let mut in_fence = false;
+
+    let mut make_id = |text: &str| {
+        let normalized = normalize_step_text(text);
+        let hash = crate::checkpoints::hash_bytes(normalized.as_bytes());
+        let n = seen.entry(normalized).or_insert(0);
+        *n += 1;
+        if *n == 1 {
+            hash[..12].to_string()
+        } else {
+            format!("{}-{}", &hash[..12], n)
+        }
+    };
+
+    for (idx, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        if let Some((level, text)) = heading_text(trimmed) {
+            headings.retain(|(l, _)| *l < level);
+            if level == 1 {
+                continue;
+            }
+            let parent_id = headings.last().map(|(_, i)| candidates[*i].0.id.clone());
+            candidates.push((
+                PlanStep {
+                    id: make_id(text),
+                    kind: "heading",
+                    title: text.to_string(),
+                    depth: level,
+                    line: idx + 1,
+                    parent_id,
+                    checked: false,
+                    status: "pending".to_string(),
+                },
+                is_step_heading(text),
+            ));
+            headings.push((level, candidates.len() - 1));
+        } else if let Some((checked, text)) = task_text(trimmed) {
+            let depth = (line.len() - trimmed.len()) / 2;
+            // Nested tasks hang off the closest shallower task, top-level ones off the heading
+            let parent_id = candidates
+                .iter()
+                .rev()
+                .take_while(|(s, _)| s.kind == "task")
+                .find(|(s, _)| s.depth < depth)
+                .map(|(s, _)| s.id.clone())
+                .or_else(|| headings.last().map(|(_, i)| candidates[*i].0.id.clone()));
+            for (_, i) in &headings {
+                candidates[*i].1 = true;
+            }
+            candidates.push((
+                PlanStep {
+                    id: make_id(text),
+                    kind: "task",
+                    title: text.to_string(),
+                    depth,
+                    line: idx + 1,
+                    parent_id,
+                    checked,
+                    status: if checked { "done" } else { "pending" }.to_string(),
+                },
+                true,
+            ));
+        }
     }
-    Ok(())
+
+    // Parents that are not steps themselves are replaced by their closest step ancestor
+    let parents: HashMap<String, Option<String>> = candidates
+        .iter()
+        .map(|(s, _)| (s.id.clone(), s.parent_id.clone()))
+        .collect();
+    let kept: std::collections::HashSet<String> = candidates
+        .iter()
+        .filter(|(_, keep)| *keep)
+        .map(|(s, _)| s.id.clone())
+        .collect();
+    for (mut step, keep) in candidates {
+        if !keep {
+            continue;
+        }
+        while let Some(parent) = step.parent_id.take() {
+            if kept.contains(&parent) {
+                step.parent_id = Some(parent);
+                break;
+            }
+            step.parent_id = parents.get(&parent).cloned().flatten();
+        }
+        steps.push(step);
+    }
+    steps
+}
+
+/// Rewrites the task box on each given one-based line. Returns `None` when
+/// nothing changed.
+pub fn set_task_checks(markdown: &str, checks: &[(usize, bool)]) -> Option<String> {
+    let mut changed = false;
+    let mut out = String::with_capacity(markdown.len());
+    for (idx, line) in markdown.split_inclusive('\n').enumerate() {
+        let wanted = checks.iter().find(|(l, _)| *l == idx + 1).map(|(_, c)| *c);
+        let rewritten = wanted.and_then(|checked| {
+            let trimmed = line.trim_start();
+            let (was, _) = task_text(trimmed)?;
+            if was == checked {
+                return None;
+            }
+            let at = line.find(if was { "[x]" } else { "[ ]" }).or_else(|| line.find("[X]"))?;
+            Some(format!("{}{}{}", &line[..at], if checked { "[x]" } else { "[ ]" }, &line[at + 3..]))
+        });
+        match rewritten {
+            Some(new_line) => {
+                changed = true;
+                out.push_str(&new_line);
+            }
+            None => out.push_str(line),
+        }
+    }
+    changed.then_some(out)
 }
 
 // --- Tauri commands ---
@@ -45,32 +233,113 @@ pub fn delete_plan(app_data_dir: &PathBuf, session_id: &str) -> Result<(), Strin
 pub fn plan_write(
     session_id: String,
     markdown: String,
+    db: tauri::State<CommentsDb>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
     let app_data = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    write_plan(&app_data, &session_id, &markdown)
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::plan_storage::write_session_plan(&conn, &app_data, &session_id, &markdown)?;
+    if let Some(revision) = crate::plan_history::record_revision(&conn, &session_id, &markdown, "write")? {
+        let _ = app.emit("plan:revision-recorded", &revision);
+    }
+    let repo_dir = crate::plan_storage::session_repo_plans_dir(&conn, &session_id)?;
+    drop(conn);
+    if let Some(dir) = repo_dir {
+        crate::plan_storage::watch_dir(&app, &dir)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
 pub fn plan_read(
     session_id: String,
+    db: tauri::State<CommentsDb>,
     app: tauri::AppHandle,
 ) -> Result<String, String> {
     let app_data = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    read_plan(&app_data, &session_id)
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::plan_storage::read_session_plan(&conn, &app_data, &session_id)
 }
 
+/// Where the session's plan is currently read from.
 #[tauri::command]
 pub fn plan_path(
     session_id: String,
+    db: tauri::State<CommentsDb>,
     app: tauri::AppHandle,
 ) -> Result<String, String> {
     let app_data = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    let path = get_plan_path(&app_data, &session_id);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let path = crate::plan_storage::plan_path(&conn, &app_data, &session_id)?;
     Ok(path.to_string_lossy().to_string())
 }
 
-use tauri::Manager;
+/// Called when a session is loaded: moves its plan into the workspace's repo
+/// plans directory if the workspace keeps plans there and the plan has not
+/// been placed yet. Returns where the plan is stored.
+#[tauri::command]
+pub fn plan_relocate(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let path = crate::plan_storage::assign_plan_path(&conn, &app_data, &session_id)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+use crate::comments::CommentsDb;
+use tauri::{Emitter, Manager};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "# Title\n\n## Step 1: Setup\n- [x] Install deps\n- [ ] Configure\n  - [ ] Nested check\n\n## Notes\nFree text.\n\n## Rollout\n```\n- [ ] not a task\n```\n1. [ ] Deploy\n- [ ] Configure\n";
+
+    #[test]
+    fn parses_tasks_and_step_headings() {
+        let steps = parse_plan_steps(PLAN);
+        let summary: Vec<(&str, &str, usize, bool)> =
+            steps.iter().map(|s| (s.kind, s.title.as_str(), s.line, s.checked)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("heading", "Step 1: Setup", 3, false),
+                ("task", "Install deps", 4, true),
+                ("task", "Configure", 5, false),
+                ("task", "Nested check", 6, false),
+                ("heading", "Rollout", 11, false),
+                ("task", "Deploy", 15, false),
+                ("task", "Configure", 16, false),
+            ]
+        );
+        assert_eq!(steps[1].status, "done");
+        assert_eq!(steps[1].parent_id.as_deref(), Some(steps[0].id.as_str()));
+        assert_eq!(steps[3].parent_id.as_deref(), Some(steps[2].id.as_str()));
+        assert_eq!(steps[5].parent_id.as_deref(), Some(steps[4].id.as_str()));
+    }
+
+    #[test]
+    fn ids_survive_edits_and_disambiguate_repeats() {
+        let steps = parse_plan_steps(PLAN);
+        assert_ne!(steps[2].id, steps[6].id);
+        assert!(steps[6].id.ends_with("-2"));
+
+        let edited = parse_plan_steps(&PLAN.replace("Free text.", "Free text.\n\nMore notes.").replace("[x]", "[ ]"));
+        let ids: Vec<&str> = steps.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(edited.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn sets_task_checks() {
+        let markdown = "- [ ] a\n- [x] b\ntext\n";
+        assert_eq!(set_task_checks(markdown, &[(1, true), (2, false)]).as_deref(), Some("- [x] a\n- [ ] b\ntext\n"));
+        assert_eq!(set_task_checks(markdown, &[(1, false), (3, true)]), None);
+    }
+}
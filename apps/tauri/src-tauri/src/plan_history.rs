@@ -0,0 +1,304 @@
+//! Revision history of session plans.
+//!
+//! Every version of a plan written through `plan_write` or picked up by the
+//! file watcher is kept. Contents live once in `plan_blobs`, keyed by their
+//! SHA-256; `plan_revisions` records which hash a session's plan had and when.
+//! Writing the same content twice in a row does not create a new revision.
+
+use crate::markdown::{split_blocks, MarkdownBlock};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanRevision {
+    pub id: i64,
+    pub session_id: String,
+    pub hash: String,
+    /// `write`, `file`, `import`, `fork` or `progress` (a task checked off by step tracking)
+    pub source: String,
+    pub size: i64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BlockChange {
+    /// `unchanged`, `added`, `removed` or `modified`
+    pub status: &'static str,
+    pub old: Option<MarkdownBlock>,
+    pub new: Option<MarkdownBlock>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanDiff {
+    pub from_hash: String,
+    pub to_hash: String,
+    pub changes: Vec<BlockChange>,
+}
+
+const REVISION_COLUMNS: &str = "r.id, r.session_id, r.hash, r.source, length(b.content), r.created_at";
+
+fn row_to_revision(row: &rusqlite::Row) -> rusqlite::Result<PlanRevision> {
+    Ok(PlanRevision {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        hash: row.get(2)?,
+        source: row.get(3)?,
+        size: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Records `markdown` as the session's latest plan revision. Returns `None`
+/// when it matches the latest revision already stored.
+pub fn record_revision(
+    conn: &Connection,
+    session_id: &str,
+    markdown: &str,
+    source: &str,
+) -> Result<Option<PlanRevision>, String> {
+    let hash = crate::checkpoints::hash_bytes(markdown.as_bytes());
+    let latest: Option<String> = conn
+        .query_row(
+            "SELECT hash FROM plan_revisions WHERE session_id = ?1 ORDER BY id DESC LIMIT 1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Query revision error: {}", e))?;
+    if latest.as_deref() == Some(hash.as_str()) {
+        return Ok(None);
+    }
+
+    let now = crate::comments::now();
+    conn.execute(
+        "INSERT OR IGNORE INTO plan_blobs (hash, content, created_at) VALUES (?1, ?2, ?3)",
+        params![hash, markdown, now],
+    )
+    .map_err(|e| format!("Insert plan blob error: {}", e))?;
+    conn.execute(
+        "INSERT INTO plan_revisions (session_id, hash, source, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![session_id, hash, source, now],
+    )
+    .map_err(|e| format!("Insert plan revision error: {}", e))?;
+
+    Ok(Some(PlanRevision {
+        id: conn.last_insert_rowid(),
+        session_id: session_id.to_string(),
+        hash,
+        source: source.to_string(),
+        size: markdown.len() as i64,
+        created_at: now,
+    }))
+}
+
+/// Newest first.
+pub fn list_revisions(conn: &Connection, session_id: &str) -> Result<Vec<PlanRevision>, String> {
+    let sql = format!(
+        "SELECT {} FROM plan_revisions r JOIN plan_blobs b ON b.hash = r.hash
+         WHERE r.session_id = ?1 ORDER BY r.id DESC",
+        REVISION_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], row_to_revision)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+/// Content of a revision; only hashes recorded for the session are readable
+/// through it.
+pub fn read_revision(conn: &Connection, session_id: &str, hash: &str) -> Result<String, String> {
+    conn.query_row(
+        "SELECT b.content FROM plan_blobs b
+         WHERE b.hash = ?2 AND EXISTS (SELECT 1 FROM plan_revisions r WHERE r.session_id = ?1 AND r.hash = b.hash)",
+        params![session_id, hash],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Query revision error: {}", e))?
+    .ok_or_else(|| format!("Plan revision not found: {}", hash))
+}
+
+pub fn diff_blocks(old: &str, new: &str) -> Vec<BlockChange> {
+    let old_blocks = split_blocks(old);
+    let new_blocks = split_blocks(new);
+    let old_text: Vec<&str> = old_blocks.iter().map(|b| b.text.as_str()).collect();
+    let new_text: Vec<&str> = new_blocks.iter().map(|b| b.text.as_str()).collect();
+
+    let mut changes = Vec::new();
+    let mut old_pos = 0;
+    let unchanged = |changes: &mut Vec<BlockChange>, from: usize, to: usize, offset: isize| {
+        for i in from..to {
+            changes.push(BlockChange {
+                status: "unchanged",
+                old: Some(old_blocks[i].clone()),
+                new: Some(new_blocks[(i as isize + offset) as usize].clone()),
+            });
+        }
+    };
+    for hunk in crate::tool_diffs::compute_hunks(&old_text, &new_text) {
+        let offset = hunk.new_start as isize - hunk.old_start as isize;
+        unchanged(&mut changes, old_pos, hunk.old_start, offset);
+        // Within a changed run, blocks are paired up in order as edits; any
+        // surplus on either side was added or removed
+        let olds = &old_blocks[hunk.old_start..hunk.old_end];
+        let news = &new_blocks[hunk.new_start..hunk.new_end];
+        for i in 0..olds.len().max(news.len()) {
+            let (old, new) = (olds.get(i).cloned(), news.get(i).cloned());
+            let status = match (&old, &new) {
+                (Some(_), Some(_)) => "modified",
+                (Some(_), None) => "removed",
+                _ => "added",
+            };
+            changes.push(BlockChange { status, old, new });
+        }
+        old_pos = hunk.old_end;
+    }
+    let offset = new_blocks.len() as isize - old_blocks.len() as isize;
+    unchanged(&mut changes, old_pos, old_blocks.len(), offset);
+    changes
+}
+
+/// Diff between two revisions of the session's plan. `to_hash` defaults to
+/// the latest revision.
+pub fn diff_revisions(
+    conn: &Connection,
+    session_id: &str,
+    from_hash: &str,
+    to_hash: Option<&str>,
+) -> Result<PlanDiff, String> {
+    let to_hash = match to_hash {
+        Some(hash) => hash.to_string(),
+        None => list_revisions(conn, session_id)?
+            .into_iter()
+            .next()
+            .map(|r| r.hash)
+            .ok_or_else(|| "Plan has no revisions".to_string())?,
+    };
+    let old = read_revision(conn, session_id, from_hash)?;
+    let new = read_revision(conn, session_id, &to_hash)?;
+    Ok(PlanDiff {
+        from_hash: from_hash.to_string(),
+        changes: diff_blocks(&old, &new),
+        to_hash,
+    })
+}
+
+/// Drops blobs no revision refers to any more (after sessions are deleted).
+pub fn prune_blobs(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM plan_blobs WHERE hash NOT IN (SELECT hash FROM plan_revisions)",
+        [],
+    )
+    .map_err(|e| format!("Prune plan blobs error: {}", e))?;
+    Ok(())
+}
+
+/// Session whose plan lives at `path`: either its app-data plan file or the
+/// plan file recorded on the session. Paths are compared as the watcher
+/// reports them, without touching the filesystem.
+fn session_for_plan_path(conn: &Connection, app_data_dir: &PathBuf, path: &Path) -> Option<String> {
+    if path.parent() == Some(crate::plan_file::get_plans_dir(app_data_dir).as_path()) {
+        let id = path.file_stem()?.to_string_lossy().to_string();
+        return crate::sessions::get_session(conn, &id).ok().map(|s| s.id);
+    }
+    crate::plan_storage::session_for_path(conn, path)
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::{Emitter, Manager};
+
+/// Called from the file watcher: if `path` is a session's plan, records its
+/// current content as a revision.
+pub fn record_file_change(app: &tauri::AppHandle, path: &Path) {
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let Ok(app_data) = app.path().app_data_dir() else { return };
+    let Ok(conn) = db.0.lock() else { return };
+    let Some(session_id) = session_for_plan_path(&conn, &app_data, path) else { return };
+    let Ok(markdown) = std::fs::read_to_string(path) else { return };
+    match record_revision(&conn, &session_id, &markdown, "file") {
+        Ok(Some(revision)) => {
+            let _ = app.emit("plan:revision-recorded", &revision);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("[plan] Failed to record revision for {}: {}", session_id, e),
+    }
+}
+
+#[tauri::command]
+pub fn plan_revisions_list(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<PlanRevision>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_revisions(&conn, &session_id)
+}
+
+#[tauri::command]
+pub fn plan_revision_read(
+    session_id: String,
+    hash: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    read_revision(&conn, &session_id, &hash)
+}
+
+#[tauri::command]
+pub fn plan_revision_diff(
+    session_id: String,
+    from_hash: String,
+    to_hash: Option<String>,
+    db: tauri::State<CommentsDb>,
+) -> Result<PlanDiff, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    diff_revisions(&conn, &session_id, &from_hash, to_hash.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str) -> Vec<(&'static str, Option<String>, Option<String>)> {
+        diff_blocks(old, new)
+            .into_iter()
+            .map(|c| (c.status, c.old.map(|b| b.text), c.new.map(|b| b.text)))
+            .collect()
+    }
+
+    fn some(text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+
+    #[test]
+    fn changed_runs_are_paired_in_order() {
+        assert_eq!(
+            changes("- a\n- b\n- c\n- d\n", "- a\n- B\n- c\n- e\n- f\n"),
+            vec![
+                ("unchanged", some("- a"), some("- a")),
+                ("modified", some("- b"), some("- B")),
+                ("unchanged", some("- c"), some("- c")),
+                ("modified", some("- d"), some("- e")),
+                ("added", None, some("- f")),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_blocks_have_no_new_side() {
+        assert_eq!(
+            changes("# Plan\n\n- a\n- b\n- c\n", "# Plan\n\n- a\n- c\n"),
+            vec![
+                ("unchanged", some("# Plan"), some("# Plan")),
+                ("unchanged", some("- a"), some("- a")),
+                ("removed", some("- b"), None),
+                ("unchanged", some("- c"), some("- c")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Sessions started from a plan that already exists as a Markdown document.
+//!
+//! The document is either copied into the session's plan storage (its
+//! comments copied along with it) or linked in place, so the session edits
+//! the original file. The session skips planning: it starts in `reviewing`
+//! or `executing`, and its first prompt carries the plan's unresolved
+//! comments.
+
+use crate::comments::{Comment, CommentsData};
+use crate::phase::Phase;
+use crate::sessions::SessionRecord;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+fn validate_source(path: &str) -> Result<PathBuf, String> {
+    let path = std::fs::canonicalize(path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let is_markdown = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"));
+    if !is_markdown {
+        return Err(format!("Not a Markdown file: {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// The document's first `# ` heading, or its file name.
+fn default_name(markdown: &str, path: &Path) -> String {
+    markdown
+        .lines()
+        .find_map(|l| l.strip_prefix("# "))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Imported plan".to_string())
+}
+
+/// First prompt of an imported session. A plan under review is only read;
+/// an approved one is executed.
+pub fn seed_prompt(plan_path: &Path, markdown: &str, phase: Phase, comments: &[Comment]) -> String {
+    let mut prompt = match phase {
+        Phase::Executing => format!(
+            "Execute the plan in `{}`. It has already been reviewed and approved.",
+            plan_path.display()
+        ),
+        _ => format!(
+            "Read the plan in `{}`. It is being reviewed; do not make any changes until it is approved.",
+            plan_path.display()
+        ),
+    };
+    let sections = crate::review::comment_sections(markdown, comments);
+    if sections.is_empty() {
+        return prompt;
+    }
+    prompt.push_str(match phase {
+        Phase::Executing => "\n\nAddress these open review comments as you go:\n\n",
+        _ => "\n\nReviewers have left these open comments on it:\n\n",
+    });
+    prompt.push_str(&sections);
+    prompt
+}
+
+/// Copies the comments on `from` to `to` under fresh ids, keeping threads.
+fn copy_comments(conn: &Connection, from: &str, to: &str, workspace_id: &str) -> Result<(), String> {
+    let data = crate::comments::load_comments(conn, from)?;
+    if data.comments.is_empty() {
+        return Ok(());
+    }
+    let ids: HashMap<String, String> = data
+        .comments
+        .iter()
+        .map(|c| (c.id.clone(), uuid::Uuid::new_v4().to_string()))
+        .collect();
+    let comments = data
+        .comments
+        .into_iter()
+        .map(|c| Comment {
+            id: ids[&c.id].clone(),
+            parent_id: c.parent_id.as_ref().and_then(|p| ids.get(p).cloned()),
+            ..c
+        })
+        .collect();
+    crate::comments::save_comments(conn, to, workspace_id, &CommentsData { file_hash: data.file_hash, comments })
+}
+
+/// Moves a fresh session on to review, and to execution when `phase` is
+/// `executing`, recording each step of the way.
+fn skip_to(conn: &Connection, session_id: &str, phase: Phase) -> Result<Vec<crate::phase::PhaseEvent>, String> {
+    let mut steps = vec![(Phase::Planning, "plan imported"), (Phase::Reviewing, "plan imported")];
+    if phase == Phase::Executing {
+        steps.push((Phase::Executing, "imported plan approved"));
+    }
+    let mut events = Vec::new();
+    for (to, reason) in steps {
+        events.extend(crate::phase::transition(conn, session_id, to, Some(reason))?);
+    }
+    Ok(events)
+}
+
+pub struct ImportedPlan {
+    pub session: SessionRecord,
+    pub events: Vec<crate::phase::PhaseEvent>,
+    pub revision: Option<crate::plan_history::PlanRevision>,
+}
+
+/// Creates a session of `workspace_id` whose plan is the Markdown file at
+/// `source`. `mode` is `copy` or `link`.
+pub fn import_plan(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    workspace_id: &str,
+    source: &str,
+    mode: &str,
+    phase: Phase,
+    name: Option<&str>,
+    provider: &str,
+) -> Result<ImportedPlan, String> {
+    if mode != "copy" && mode != "link" {
+        return Err(format!("Invalid import mode: {}. Must be copy or link", mode));
+    }
+    if !matches!(phase, Phase::Reviewing | Phase::Executing) {
+        return Err(format!("Imported plans start in reviewing or executing, not {}", phase));
+    }
+    let source = validate_source(source)?;
+    let markdown = std::fs::read_to_string(&source).map_err(|e| format!("Failed to read plan: {}", e))?;
+    let source_key = source.to_string_lossy().to_string();
+    let comments = crate::comments::load_comments(conn, &source_key)?.comments;
+    let name = name
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| default_name(&markdown, &source));
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Transaction error: {}", e))?;
+    let session = crate::sessions::create_session(&tx, workspace_id, &name, "", provider)?;
+    let plan_path = if mode == "link" {
+        crate::sessions::update_plan_file_path(&tx, &session.id, &source_key)?;
+        source.clone()
+    } else {
+        crate::plan_storage::assign_plan_path(&tx, app_data_dir, &session.id)?
+    };
+    let result = (|| {
+        if mode == "copy" {
+            crate::plan_storage::write_session_plan(&tx, app_data_dir, &session.id, &markdown)?;
+            copy_comments(&tx, &source_key, &plan_path.to_string_lossy(), workspace_id)?;
+        }
+        let prompt = seed_prompt(&plan_path, &markdown, phase, &comments);
+        crate::sessions::update_initial_prompt(&tx, &session.id, &prompt)?;
+        let revision = crate::plan_history::record_revision(&tx, &session.id, &markdown, "import")?;
+        let events = skip_to(&tx, &session.id, phase)?;
+        Ok(ImportedPlan { session: crate::sessions::get_session(&tx, &session.id)?, events, revision })
+    })();
+    let result = result.and_then(|imported| {
+        tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+        Ok(imported)
+    });
+    if result.is_err() && mode == "copy" {
+        // The session is rolled back; don't leave its copy of the plan behind
+        let _ = std::fs::remove_file(&plan_path);
+    }
+    result
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::{Emitter, Manager};
+
+#[tauri::command]
+pub fn session_import_plan(
+    workspace_id: String,
+    path: String,
+    mode: String,
+    phase: String,
+    name: Option<String>,
+    provider: Option<String>,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<SessionRecord, String> {
+    let phase: Phase = phase.parse()?;
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let provider = provider.as_deref().unwrap_or("copilot");
+    let imported = import_plan(&conn, &app_data, &workspace_id, &path, &mode, phase, name.as_deref(), provider)?;
+    let repo_dir = crate::plan_storage::session_repo_plans_dir(&conn, &imported.session.id)?;
+    drop(conn);
+
+    if let Some(revision) = &imported.revision {
+        let _ = app.emit("plan:revision-recorded", revision);
+    }
+    for event in &imported.events {
+        let _ = app.emit("session:phase-changed", event);
+    }
+    if let (Some(dir), "copy") = (repo_dir, mode.as_str()) {
+        crate::plan_storage::watch_dir(&app, &dir)?;
+    }
+    Ok(imported.session)
+}
@@ -0,0 +1,489 @@
+//! Step progress while a plan is being executed.
+//!
+//! Steps come from `plan_file::parse_plan_steps`. While the session is in the
+//! `executing` phase, ACP `plan` updates (and Claude's `TodoWrite`) are matched
+//! against step titles, and tool calls touching a file a step mentions mark
+//! that step in progress; a failed tool call blocks it. Statuses are kept in
+//! `plan_step_status`, finished tasks are checked off in the plan file, and
+//! every change is emitted as `plan:step-progress`.
+
+use crate::plan_file::{normalize_step_text, parse_plan_steps, session_plan_path, set_task_checks, PlanStep};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+pub const STEP_STATUSES: [&str; 4] = ["pending", "in_progress", "done", "blocked"];
+
+/// Minimum word overlap (Dice coefficient) for an agent's entry to count as
+/// a plan step.
+const MATCH_THRESHOLD: f64 = 0.6;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StepProgressEvent {
+    pub session_id: String,
+    pub steps: Vec<PlanStep>,
+    /// Ids of the steps whose status changed
+    pub changed: Vec<String>,
+}
+
+fn load_statuses(conn: &Connection, session_id: &str) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT step_id, status FROM plan_step_status WHERE session_id = ?1")
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+/// Applies tracked statuses to parsed steps. A checked box always means done;
+/// headings with child steps summarize them, other headings keep their own.
+fn merge_statuses(steps: &mut [PlanStep], statuses: &HashMap<String, String>) {
+    for step in steps.iter_mut().filter(|s| s.kind == "task") {
+        if !step.checked {
+            step.status = match statuses.get(&step.id).map(String::as_str) {
+                Some("done") | None => "pending".to_string(),
+                Some(status) => status.to_string(),
+            };
+        }
+    }
+    // Children come after their heading, so walk backwards
+    for i in (0..steps.len()).rev() {
+        if steps[i].kind != "heading" {
+            continue;
+        }
+        let id = steps[i].id.clone();
+        let children: Vec<&str> = steps
+            .iter()
+            .filter(|s| s.parent_id.as_deref() == Some(id.as_str()))
+            .map(|s| s.status.as_str())
+            .collect();
+        if children.is_empty() {
+            steps[i].status = statuses.get(&id).cloned().unwrap_or_else(|| "pending".to_string());
+            continue;
+        }
+        steps[i].status = if children.iter().all(|s| *s == "done") {
+            "done"
+        } else if children.contains(&"blocked") {
+            "blocked"
+        } else if children.iter().any(|s| *s != "pending") {
+            "in_progress"
+        } else {
+            "pending"
+        }
+        .to_string();
+    }
+}
+
+pub(crate) struct PlanContext {
+    pub path: PathBuf,
+    pub markdown: String,
+    pub phase: String,
+}
+
+pub(crate) fn plan_context(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<PlanContext, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    let path = session_plan_path(app_data_dir, session_id, session.plan_file_path.as_deref());
+    let markdown = if path.exists() {
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read plan: {}", e))?
+    } else {
+        String::new()
+    };
+    Ok(PlanContext { path, markdown, phase: session.phase })
+}
+
+pub fn load_steps(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<Vec<PlanStep>, String> {
+    let ctx = plan_context(conn, app_data_dir, session_id)?;
+    steps_for(conn, session_id, &ctx.markdown)
+}
+
+/// Steps of `markdown` with the session's tracked statuses applied.
+pub(crate) fn steps_for(conn: &Connection, session_id: &str, markdown: &str) -> Result<Vec<PlanStep>, String> {
+    let mut steps = parse_plan_steps(markdown);
+    merge_statuses(&mut steps, &load_statuses(conn, session_id)?);
+    Ok(steps)
+}
+
+/// A plan file edit made alongside database changes, written by the caller
+/// once those changes are committed.
+pub struct PlanWrite {
+    path: PathBuf,
+    markdown: String,
+}
+
+impl PlanWrite {
+    /// The plan as it will read once written
+    pub fn markdown(&self) -> &str {
+        &self.markdown
+    }
+
+    pub fn apply(self) -> Result<(), String> {
+        std::fs::write(&self.path, &self.markdown).map_err(|e| format!("Failed to write plan: {}", e))
+    }
+}
+
+/// Sets step statuses and checks or unchecks the affected tasks in the plan
+/// file. Marking a heading done checks every task under it. Returns the
+/// merged steps and the ids whose status changed.
+pub fn set_statuses(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    session_id: &str,
+    updates: &[(String, &str)],
+    tool_call_id: Option<&str>,
+) -> Result<(Vec<PlanStep>, Vec<String>), String> {
+    let staged = stage_statuses(conn, app_data_dir, session_id, updates, tool_call_id)?;
+    if let Some(write) = staged.plan_write {
+        write.apply()?;
+    }
+    Ok((staged.steps, staged.changed))
+}
+
+/// Result of [`stage_statuses`].
+pub struct StagedStatuses {
+    pub steps: Vec<PlanStep>,
+    pub changed: Vec<String>,
+    pub plan_write: Option<PlanWrite>,
+}
+
+/// [`set_statuses`] for callers inside a transaction: the plan file edit is
+/// returned instead of written, so nothing reaches the disk before the
+/// statuses it reflects are committed.
+pub fn stage_statuses(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    session_id: &str,
+    updates: &[(String, &str)],
+    tool_call_id: Option<&str>,
+) -> Result<StagedStatuses, String> {
+    for (_, status) in updates {
+        if !STEP_STATUSES.contains(status) {
+            return Err(format!("Invalid step status: {}. Must be one of: {:?}", status, STEP_STATUSES));
+        }
+    }
+    let ctx = plan_context(conn, app_data_dir, session_id)?;
+    let mut before = parse_plan_steps(&ctx.markdown);
+    let statuses = load_statuses(conn, session_id)?;
+    merge_statuses(&mut before, &statuses);
+
+    let now = crate::comments::now();
+    let mut checks: Vec<(usize, bool)> = Vec::new();
+    for (step_id, status) in updates {
+        let step = before
+            .iter()
+            .find(|s| &s.id == step_id)
+            .ok_or_else(|| format!("Plan step not found: {}", step_id))?;
+        conn.execute(
+            "INSERT INTO plan_step_status (session_id, step_id, status, tool_call_id, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(session_id, step_id) DO UPDATE SET
+                status = excluded.status,
+                tool_call_id = COALESCE(excluded.tool_call_id, plan_step_status.tool_call_id),
+                updated_at = excluded.updated_at",
+            params![session_id, step_id, status, tool_call_id, now],
+        )
+        .map_err(|e| format!("Update step status error: {}", e))?;
+
+        let mut tasks = vec![step];
+        if step.kind == "heading" && *status == "done" {
+            let mut under: HashSet<&str> = HashSet::from([step.id.as_str()]);
+            for s in &before {
+                if s.parent_id.as_deref().is_some_and(|p| under.contains(p)) {
+                    under.insert(&s.id);
+                    tasks.push(s);
+                }
+            }
+        }
+        for task in tasks.into_iter().filter(|s| s.kind == "task") {
+            checks.push((task.line, *status == "done"));
+        }
+    }
+
+    let mut markdown = ctx.markdown;
+    let mut plan_write = None;
+    if let Some(updated) = set_task_checks(&markdown, &checks) {
+        crate::plan_history::record_revision(conn, session_id, &updated, "progress")?;
+        plan_write = Some(PlanWrite { path: ctx.path, markdown: updated.clone() });
+        markdown = updated;
+    }
+
+    let after = steps_for(conn, session_id, &markdown)?;
+    let previous: HashMap<&str, &str> = before.iter().map(|s| (s.id.as_str(), s.status.as_str())).collect();
+    let changed = after
+        .iter()
+        .filter(|s| previous.get(s.id.as_str()) != Some(&s.status.as_str()))
+        .map(|s| s.id.clone())
+        .collect();
+    Ok(StagedStatuses { steps: after, changed, plan_write })
+}
+
+fn words(text: &str) -> HashSet<String> {
+    normalize_step_text(text)
+        .split(' ')
+        .filter(|w| w.len() > 1)
+        .map(String::from)
+        .collect()
+}
+
+/// The step whose title best matches `text`, if any matches well enough.
+fn match_step<'a>(steps: &'a [PlanStep], text: &str) -> Option<&'a PlanStep> {
+    let target = words(text);
+    if target.is_empty() {
+        return None;
+    }
+    steps
+        .iter()
+        .map(|step| {
+            let title = words(&step.title);
+            let common = title.intersection(&target).count() as f64;
+            (step, 2.0 * common / (title.len() + target.len()) as f64)
+        })
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(step, _)| step)
+}
+
+/// Pending steps that mention one of `paths` (by full path or file name).
+fn steps_for_paths<'a>(steps: &'a [PlanStep], paths: &[String]) -> Vec<&'a PlanStep> {
+    let names: Vec<String> = paths
+        .iter()
+        .flat_map(|p| {
+            let name = std::path::Path::new(p)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase());
+            [Some(p.to_lowercase()), name]
+        })
+        .flatten()
+        .filter(|n| n.len() > 2)
+        .collect();
+    steps
+        .iter()
+        .filter(|s| s.kind == "task" && s.status == "pending")
+        .filter(|s| {
+            let title = s.title.to_lowercase();
+            names.iter().any(|n| title.contains(n.as_str()))
+        })
+        .collect()
+}
+
+// --- Hooks for provider connections ---
+
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Runs `f` against the current steps when the session is executing a plan,
+/// applies the updates it returns and emits the result.
+fn track(
+    app: &AppHandle,
+    session_id: &str,
+    tool_call_id: Option<&str>,
+    f: impl FnOnce(&Connection, &[PlanStep]) -> Vec<(String, &'static str)>,
+) {
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let Ok(app_data) = app.path().app_data_dir() else { return };
+    let Ok(conn) = db.0.lock() else { return };
+    let result = (|| {
+        let ctx = plan_context(&conn, &app_data, session_id)?;
+        if ctx.phase != "executing" || ctx.markdown.is_empty() {
+            return Ok(None);
+        }
+        let steps = load_steps(&conn, &app_data, session_id)?;
+        let updates: Vec<(String, &str)> = f(&conn, &steps)
+            .into_iter()
+            .filter(|(id, status)| steps.iter().any(|s| &s.id == id && s.status != *status))
+            .collect();
+        if updates.is_empty() {
+            return Ok(None);
+        }
+        set_statuses(&conn, &app_data, session_id, &updates, tool_call_id).map(Some)
+    })();
+    match result {
+        Ok(Some((steps, changed))) if !changed.is_empty() => {
+            let _ = app.emit("plan:step-progress", &StepProgressEvent {
+                session_id: session_id.to_string(),
+                steps,
+                changed,
+            });
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[plan] progress for {}: {}", session_id, e),
+    }
+}
+
+/// Applies the agent's own task list (ACP `plan` entries or Claude todos).
+/// Entries are `(content, status)` with ACP statuses; a `pending` entry never
+/// moves a step backwards.
+fn on_entries(app: &AppHandle, session_id: &str, entries: Vec<(String, String)>) {
+    track(app, session_id, None, |_, steps| {
+        entries
+            .iter()
+            .filter_map(|(content, status)| {
+                let status = match status.as_str() {
+                    "completed" => "done",
+                    "in_progress" => "in_progress",
+                    _ => return None,
+                };
+                let step = match_step(steps, content)?;
+                (step.status != "done").then(|| (step.id.clone(), status))
+            })
+            .collect()
+    });
+}
+
+fn entries_from(items: Option<&serde_json::Value>) -> Vec<(String, String)> {
+    items
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|e| {
+                    let content = e.get("content")?.as_str()?;
+                    let status = e.get("status")?.as_str()?;
+                    Some((content.to_string(), status.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Handles an ACP `plan` session update.
+pub fn on_acp_plan(app: &AppHandle, session_id: &str, payload: &serde_json::Value) {
+    on_entries(app, session_id, entries_from(payload.get("entries")));
+}
+
+fn on_tool_started(app: &AppHandle, session_id: &str, tool_call_id: &str, title: Option<&str>, paths: Vec<String>) {
+    track(app, session_id, Some(tool_call_id), |_, steps| {
+        let mut matched: Vec<(String, &'static str)> = steps_for_paths(steps, &paths)
+            .into_iter()
+            .map(|s| (s.id.clone(), "in_progress"))
+            .collect();
+        if matched.is_empty() {
+            if let Some(step) = title.and_then(|t| match_step(steps, t)).filter(|s| s.status == "pending") {
+                matched.push((step.id.clone(), "in_progress"));
+            }
+        }
+        matched
+    });
+}
+
+/// Handles an ACP `tool_call` update.
+pub fn on_acp_tool_call(app: &AppHandle, session_id: &str, payload: &serde_json::Value) {
+    let Some(tool_call_id) = payload.get("toolCallId").and_then(|v| v.as_str()) else { return };
+    let paths = payload
+        .get("locations")
+        .and_then(|l| l.as_array())
+        .map(|l| l.iter().filter_map(|loc| loc.get("path")?.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    let title = payload.get("title").and_then(|v| v.as_str());
+    on_tool_started(app, session_id, tool_call_id, title, paths);
+}
+
+/// Handles a Claude `tool_use` block; `TodoWrite` carries Claude's task list.
+pub fn on_claude_tool_use(app: &AppHandle, session_id: &str, tool_use_id: &str, name: &str, input: &serde_json::Value) {
+    if name == "TodoWrite" {
+        on_entries(app, session_id, entries_from(input.get("todos")));
+        return;
+    }
+    let paths = ["file_path", "notebook_path", "path"]
+        .iter()
+        .filter_map(|k| input.get(*k).and_then(|p| p.as_str()).map(String::from))
+        .collect();
+    on_tool_started(app, session_id, tool_use_id, None, paths);
+}
+
+/// A failed tool call blocks the steps it had put in progress.
+pub fn on_tool_call_finished(app: &AppHandle, session_id: &str, tool_call_id: &str, failed: bool) {
+    if !failed {
+        return;
+    }
+    track(app, session_id, None, |conn, steps| {
+        let ids: Vec<String> = conn
+            .prepare("SELECT step_id FROM plan_step_status WHERE session_id = ?1 AND tool_call_id = ?2")
+            .and_then(|mut stmt| {
+                stmt.query_map(params![session_id, tool_call_id], |row| row.get(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .unwrap_or_default();
+        steps
+            .iter()
+            .filter(|s| s.status == "in_progress" && ids.contains(&s.id))
+            .map(|s| (s.id.clone(), "blocked"))
+            .collect()
+    });
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn plan_steps(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<Vec<PlanStep>, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_steps(&conn, &app_data, &session_id)
+}
+
+/// Manually sets a step's status (e.g. unblocking it).
+#[tauri::command]
+pub fn plan_step_set_status(
+    session_id: String,
+    step_id: String,
+    status: String,
+    db: tauri::State<CommentsDb>,
+    app: tauri::AppHandle,
+) -> Result<Vec<PlanStep>, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (steps, changed) = set_statuses(&conn, &app_data, &session_id, &[(step_id, status.as_str())], None)?;
+    if !changed.is_empty() {
+        let _ = app.emit("plan:step-progress", &StepProgressEvent {
+            session_id,
+            steps: steps.clone(),
+            changed,
+        });
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "# Plan\n\n## Step 1: Build\n- [x] Compile\n- [ ] Link\n\n## Step 2: Ship\n- [ ] Upload\n\n## Step 3: Announce\n";
+
+    fn merged(statuses: &[(usize, &str)]) -> Vec<String> {
+        let mut steps = parse_plan_steps(PLAN);
+        let statuses = statuses.iter().map(|(i, s)| (steps[*i].id.clone(), s.to_string())).collect();
+        merge_statuses(&mut steps, &statuses);
+        steps.into_iter().map(|s| s.status).collect()
+    }
+
+    #[test]
+    fn checked_boxes_win_over_tracked_status() {
+        // Steps: Build, Compile, Link, Ship, Upload, Announce
+        assert_eq!(merged(&[(1, "blocked")])[1], "done");
+        // A tracked `done` without the box checked is not done
+        assert_eq!(merged(&[(2, "done")])[2], "pending");
+    }
+
+    #[test]
+    fn headings_summarize_their_children() {
+        assert_eq!(merged(&[]), ["in_progress", "done", "pending", "pending", "pending", "pending"]);
+        assert_eq!(merged(&[(2, "blocked")])[0], "blocked");
+        assert_eq!(merged(&[(4, "in_progress")])[3], "in_progress");
+    }
+
+    #[test]
+    fn childless_headings_keep_their_own_status() {
+        assert_eq!(merged(&[(5, "in_progress")])[5], "in_progress");
+        assert_eq!(merged(&[(3, "done")])[3], "pending");
+    }
+}
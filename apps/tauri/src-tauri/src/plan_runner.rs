@@ -0,0 +1,606 @@
+//! Step-gated plan execution.
+//!
+//! Instead of letting the agent run through the whole plan, the runner sends
+//! one prompt per top-level plan step, waits for the turn to end and then
+//! pauses until the user approves the step, comments on it (the agent gets
+//! the comment and redoes the step) or retries it. Approving the last step
+//! finishes the run and moves the session to `done`.
+//!
+//! The run (`plan_runs`) and every attempt at a step (`plan_run_steps`) are
+//! persisted, so a run interrupted by an app restart resumes waiting for the
+//! user's decision on the attempt that was in flight.
+
+use crate::phase::PhaseEvent;
+use crate::plan_file::PlanStep;
+use crate::plan_progress::PlanWrite;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanRun {
+    pub session_id: String,
+    /// `running`, `awaiting_review`, `completed` or `cancelled`
+    pub status: String,
+    pub current_step_id: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StepAttempt {
+    pub id: i64,
+    pub step_id: String,
+    pub attempt: i64,
+    /// How the agent's turn ended: `running`, `finished`, `failed` or `interrupted`
+    pub outcome: String,
+    /// The user's decision: `approved`, `commented` or `retried`
+    pub decision: Option<String>,
+    pub comment: Option<String>,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanRunState {
+    pub run: Option<PlanRun>,
+    /// The steps the runner walks through, in order
+    pub steps: Vec<PlanStep>,
+    pub attempts: Vec<StepAttempt>,
+}
+
+/// The next prompt to send, decided while holding the database lock and sent
+/// after releasing it.
+pub struct PendingPrompt {
+    pub attempt_id: i64,
+    pub text: String,
+}
+
+/// What a run command decided inside its transaction; applied only once the
+/// transaction has committed.
+#[derive(Default)]
+pub struct RunUpdate {
+    pub prompt: Option<PendingPrompt>,
+    /// The phase transition the command made, if any
+    pub phase_event: Option<PhaseEvent>,
+    /// Checked-off tasks to write to the plan file
+    pub plan_write: Option<PlanWrite>,
+}
+
+fn row_to_run(row: &rusqlite::Row) -> rusqlite::Result<PlanRun> {
+    Ok(PlanRun {
+        session_id: row.get(0)?,
+        status: row.get(1)?,
+        current_step_id: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+fn row_to_attempt(row: &rusqlite::Row) -> rusqlite::Result<StepAttempt> {
+    Ok(StepAttempt {
+        id: row.get(0)?,
+        step_id: row.get(1)?,
+        attempt: row.get(2)?,
+        outcome: row.get(3)?,
+        decision: row.get(4)?,
+        comment: row.get(5)?,
+        error: row.get(6)?,
+        started_at: row.get(7)?,
+        finished_at: row.get(8)?,
+    })
+}
+
+pub fn get_run(conn: &Connection, session_id: &str) -> Result<Option<PlanRun>, String> {
+    conn.query_row(
+        "SELECT session_id, status, current_step_id, created_at, updated_at FROM plan_runs WHERE session_id = ?1",
+        params![session_id],
+        row_to_run,
+    )
+    .optional()
+    .map_err(|e| format!("Query plan run error: {}", e))
+}
+
+fn list_attempts(conn: &Connection, session_id: &str) -> Result<Vec<StepAttempt>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, step_id, attempt, outcome, decision, comment, error, started_at, finished_at
+             FROM plan_run_steps WHERE session_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], row_to_attempt)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+fn latest_attempt(conn: &Connection, session_id: &str) -> Result<Option<StepAttempt>, String> {
+    Ok(list_attempts(conn, session_id)?.pop())
+}
+
+fn set_run_status(conn: &Connection, session_id: &str, status: &str, current_step_id: Option<&str>) -> Result<(), String> {
+    conn.execute(
+        "UPDATE plan_runs SET status = ?1, current_step_id = ?2, updated_at = ?3 WHERE session_id = ?4",
+        params![status, current_step_id, crate::comments::now(), session_id],
+    )
+    .map_err(|e| format!("Update plan run error: {}", e))?;
+    Ok(())
+}
+
+/// Top-level steps: each step heading, or each task when the plan has no
+/// step headings.
+fn run_steps(steps: &[PlanStep]) -> Vec<PlanStep> {
+    steps.iter().filter(|s| s.parent_id.is_none()).cloned().collect()
+}
+
+pub fn load_state(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<PlanRunState, String> {
+    let steps = crate::plan_progress::load_steps(conn, app_data_dir, session_id)?;
+    Ok(PlanRunState {
+        run: get_run(conn, session_id)?,
+        steps: run_steps(&steps),
+        attempts: list_attempts(conn, session_id)?,
+    })
+}
+
+/// The part of the plan belonging to `step`: its heading and everything up
+/// to the next heading of the same or a higher level, or just the task item.
+fn step_section(markdown: &str, step: &PlanStep) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let start = step.line - 1;
+    let mut end = start + 1;
+    while end < lines.len() {
+        let trimmed = lines[end].trim_start();
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        let is_heading = level > 0 && trimmed[level..].starts_with(' ');
+        let ends = match step.kind {
+            "heading" => is_heading && level <= step.depth,
+            // A task runs until the next item at its depth or shallower
+            _ => is_heading || (crate::markdown::is_list_item(trimmed) && (lines[end].len() - trimmed.len()) / 2 <= step.depth),
+        };
+        if ends {
+            break;
+        }
+        end += 1;
+    }
+    lines[start..end].join("\n").trim_end().to_string()
+}
+
+fn step_prompt(markdown: &str, steps: &[PlanStep], step: &PlanStep, feedback: Option<&str>) -> String {
+    let position = steps.iter().position(|s| s.id == step.id).unwrap_or(0) + 1;
+    let mut prompt = format!(
+        "Execute step {} of {} of the plan: {}\n\n{}\n\nOnly work on this step. When it is done, stop and summarize what you changed.",
+        position,
+        steps.len(),
+        step.title,
+        step_section(markdown, step)
+    );
+    if let Some(feedback) = feedback {
+        prompt.push_str(&format!("\n\nReview feedback on your previous attempt at this step:\n\n{}", feedback));
+    }
+    prompt
+}
+
+/// Records a new attempt at `step` of the plan `markdown` and builds its prompt.
+fn begin_attempt(
+    conn: &Connection,
+    session_id: &str,
+    markdown: &str,
+    step: &PlanStep,
+    feedback: Option<&str>,
+) -> Result<PendingPrompt, String> {
+    let steps = run_steps(&crate::plan_progress::steps_for(conn, session_id, markdown)?);
+    let attempt: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM plan_run_steps WHERE session_id = ?1 AND step_id = ?2",
+            params![session_id, step.id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Query attempts error: {}", e))?;
+    conn.execute(
+        "INSERT INTO plan_run_steps (session_id, step_id, attempt, outcome, started_at)
+         VALUES (?1, ?2, ?3, 'running', ?4)",
+        params![session_id, step.id, attempt + 1, crate::comments::now()],
+    )
+    .map_err(|e| format!("Insert attempt error: {}", e))?;
+    let attempt_id = conn.last_insert_rowid();
+    set_run_status(conn, session_id, "running", Some(&step.id))?;
+    Ok(PendingPrompt { attempt_id, text: step_prompt(markdown, &steps, step, feedback) })
+}
+
+/// First step of the plan `markdown` that is not done yet; `None` when the
+/// whole plan is done.
+fn next_step(conn: &Connection, session_id: &str, markdown: &str) -> Result<Option<PlanStep>, String> {
+    let steps = crate::plan_progress::steps_for(conn, session_id, markdown)?;
+    Ok(run_steps(&steps).into_iter().find(|s| s.status != "done"))
+}
+
+/// Starts (or restarts) a run at the first unfinished step. A session still
+/// in review is moved to `executing`.
+pub fn start(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<RunUpdate, String> {
+    if let Some(run) = get_run(conn, session_id)? {
+        if run.status == "running" || run.status == "awaiting_review" {
+            return Err("A step-gated run is already in progress for this session".to_string());
+        }
+    }
+    let session = crate::sessions::get_session(conn, session_id)?;
+    let mut phase_event = None;
+    if session.phase == "reviewing" {
+        phase_event = crate::phase::transition(
+            conn, session_id, crate::phase::Phase::Executing, Some("step-gated execution started"),
+        )?;
+    } else if session.phase != "executing" {
+        return Err(format!("Cannot execute a plan in the {} phase", session.phase));
+    }
+    let ctx = crate::plan_progress::plan_context(conn, app_data_dir, session_id)?;
+    if run_steps(&crate::plan_progress::steps_for(conn, session_id, &ctx.markdown)?).is_empty() {
+        return Err("The plan has no steps".to_string());
+    }
+
+    let now = crate::comments::now();
+    conn.execute("DELETE FROM plan_run_steps WHERE session_id = ?1", params![session_id])
+        .map_err(|e| format!("Reset attempts error: {}", e))?;
+    conn.execute(
+        "INSERT INTO plan_runs (session_id, status, current_step_id, created_at, updated_at)
+         VALUES (?1, 'running', NULL, ?2, ?2)
+         ON CONFLICT(session_id) DO UPDATE SET status = 'running', current_step_id = NULL, created_at = ?2, updated_at = ?2",
+        params![session_id, now],
+    )
+    .map_err(|e| format!("Insert plan run error: {}", e))?;
+    let mut update = advance(conn, session_id, &ctx.markdown)?;
+    // A plan with every step already done goes straight on to `done`
+    update.phase_event = update.phase_event.or(phase_event);
+    Ok(update)
+}
+
+/// Moves on to the next unfinished step of the plan `markdown`, or completes
+/// the run.
+fn advance(conn: &Connection, session_id: &str, markdown: &str) -> Result<RunUpdate, String> {
+    match next_step(conn, session_id, markdown)? {
+        Some(step) => Ok(RunUpdate {
+            prompt: Some(begin_attempt(conn, session_id, markdown, &step, None)?),
+            ..Default::default()
+        }),
+        None => {
+            set_run_status(conn, session_id, "completed", None)?;
+            let phase_event = crate::phase::transition(
+                conn, session_id, crate::phase::Phase::Done, Some("all plan steps approved"),
+            )?;
+            Ok(RunUpdate { phase_event, ..Default::default() })
+        }
+    }
+}
+
+/// Records how the agent's turn for an attempt ended and pauses for review.
+/// Ignored when the run was cancelled or restarted meanwhile.
+pub fn finish_attempt(conn: &Connection, session_id: &str, attempt_id: i64, error: Option<&str>) -> Result<(), String> {
+    let updated = conn
+        .execute(
+            "UPDATE plan_run_steps SET outcome = ?1, error = ?2, finished_at = ?3
+             WHERE id = ?4 AND outcome = 'running'",
+            params![if error.is_some() { "failed" } else { "finished" }, error, crate::comments::now(), attempt_id],
+        )
+        .map_err(|e| format!("Update attempt error: {}", e))?;
+    if updated > 0 {
+        conn.execute(
+            "UPDATE plan_runs SET status = 'awaiting_review', updated_at = ?1 WHERE session_id = ?2 AND status = 'running'",
+            params![crate::comments::now(), session_id],
+        )
+        .map_err(|e| format!("Update plan run error: {}", e))?;
+    }
+    Ok(())
+}
+
+/// The attempt waiting for the user's decision.
+fn awaiting(conn: &Connection, session_id: &str) -> Result<StepAttempt, String> {
+    let run = get_run(conn, session_id)?.ok_or("No step-gated run for this session")?;
+    if run.status != "awaiting_review" {
+        return Err(format!("The run is {}, not awaiting review", run.status));
+    }
+    latest_attempt(conn, session_id)?.ok_or_else(|| "The run has no attempts".to_string())
+}
+
+fn decide(conn: &Connection, attempt_id: i64, decision: &str, comment: Option<&str>) -> Result<(), String> {
+    conn.execute(
+        "UPDATE plan_run_steps SET decision = ?1, comment = ?2 WHERE id = ?3",
+        params![decision, comment, attempt_id],
+    )
+    .map_err(|e| format!("Update attempt error: {}", e))?;
+    Ok(())
+}
+
+/// Approves the current step: it is checked off in the plan and the next step
+/// starts. The next step is picked from the plan as it will be written.
+pub fn approve(conn: &Connection, app_data_dir: &PathBuf, session_id: &str, comment: Option<&str>) -> Result<RunUpdate, String> {
+    let attempt = awaiting(conn, session_id)?;
+    decide(conn, attempt.id, "approved", comment)?;
+    let staged =
+        crate::plan_progress::stage_statuses(conn, app_data_dir, session_id, &[(attempt.step_id, "done")], None)?;
+    let mut update = match &staged.plan_write {
+        Some(write) => advance(conn, session_id, write.markdown())?,
+        None => {
+            let ctx = crate::plan_progress::plan_context(conn, app_data_dir, session_id)?;
+            advance(conn, session_id, &ctx.markdown)?
+        }
+    };
+    update.plan_write = staged.plan_write;
+    Ok(update)
+}
+
+/// Sends the current step again, with the reviewer's comment when given.
+pub fn redo(conn: &Connection, app_data_dir: &PathBuf, session_id: &str, comment: Option<&str>) -> Result<PendingPrompt, String> {
+    let attempt = awaiting(conn, session_id)?;
+    decide(conn, attempt.id, if comment.is_some() { "commented" } else { "retried" }, comment)?;
+    let ctx = crate::plan_progress::plan_context(conn, app_data_dir, session_id)?;
+    let steps = crate::plan_progress::steps_for(conn, session_id, &ctx.markdown)?;
+    let step = steps
+        .iter()
+        .find(|s| s.id == attempt.step_id)
+        .ok_or_else(|| "The current step is no longer in the plan".to_string())?;
+    begin_attempt(conn, session_id, &ctx.markdown, step, comment)
+}
+
+pub fn cancel(conn: &Connection, session_id: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE plan_run_steps SET outcome = 'interrupted', finished_at = ?1 WHERE session_id = ?2 AND outcome = 'running'",
+        params![crate::comments::now(), session_id],
+    )
+    .map_err(|e| format!("Update attempt error: {}", e))?;
+    set_run_status(conn, session_id, "cancelled", None)
+}
+
+/// Called at startup: attempts whose turn was cut off by the app exiting are
+/// flagged and their runs wait for the user to retry or approve.
+pub fn recover_runs(conn: &Connection) -> Result<usize, String> {
+    let now = crate::comments::now();
+    let n = conn
+        .execute(
+            "UPDATE plan_run_steps SET outcome = 'interrupted', finished_at = ?1 WHERE outcome = 'running'",
+            params![now],
+        )
+        .map_err(|e| format!("Recover attempts error: {}", e))?;
+    conn.execute(
+        "UPDATE plan_runs SET status = 'awaiting_review', updated_at = ?1 WHERE status = 'running'",
+        params![now],
+    )
+    .map_err(|e| format!("Recover plan runs error: {}", e))?;
+    Ok(n)
+}
+
+// --- Tauri commands ---
+
+use crate::acp::commands::{send_session_prompt, AcpSessionStore};
+use crate::acp::connection::PromptOptions;
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Emitter, Manager};
+
+fn emit_state(app: &AppHandle, session_id: &str) {
+    let (Some(db), Ok(app_data)) = (app.try_state::<CommentsDb>(), app.path().app_data_dir()) else { return };
+    let Ok(conn) = db.0.lock() else { return };
+    match load_state(&conn, &app_data, session_id) {
+        Ok(state) => {
+            let _ = app.emit("plan-run:updated", &state);
+        }
+        Err(e) => eprintln!("[plan] Failed to load run state for {}: {}", session_id, e),
+    }
+}
+
+/// Applies a committed run update: writes the plan file, reports a phase
+/// change (the UI follows them through `session:phase-changed`) and sends
+/// the next prompt.
+fn apply(app: &AppHandle, session_id: &str, update: RunUpdate) {
+    if let Some(write) = update.plan_write {
+        if let Err(e) = write.apply() {
+            eprintln!("[plan] {}", e);
+        }
+    }
+    if let Some(event) = &update.phase_event {
+        let _ = app.emit("session:phase-changed", event);
+    }
+    dispatch(app, session_id, update.prompt);
+}
+
+/// Sends the step prompt in the background; the run pauses for review once
+/// the agent's turn ends.
+fn dispatch(app: &AppHandle, session_id: &str, prompt: Option<PendingPrompt>) {
+    emit_state(app, session_id);
+    let Some(prompt) = prompt else { return };
+    let app = app.clone();
+    let session_id = session_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let store = app.state::<AcpSessionStore>();
+        let options = PromptOptions { allow_duplicate: true, ..Default::default() };
+        let result = send_session_prompt(&store, &app, session_id.clone(), prompt.text, options).await;
+        if let Err(e) = &result {
+            eprintln!("[plan] Step prompt failed for {}: {}", session_id, e);
+        }
+        if let Some(db) = app.try_state::<CommentsDb>() {
+            if let Ok(conn) = db.0.lock() {
+                if let Err(e) = finish_attempt(&conn, &session_id, prompt.attempt_id, result.err().as_deref()) {
+                    eprintln!("[plan] {}", e);
+                }
+            }
+        }
+        emit_state(&app, &session_id);
+    });
+}
+
+fn with_run<T>(
+    app: &AppHandle,
+    db: &tauri::State<CommentsDb>,
+    f: impl FnOnce(&Connection, &PathBuf) -> Result<T, String>,
+) -> Result<T, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Transaction error: {}", e))?;
+    let result = f(&tx, &app_data)?;
+    tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn plan_run_state(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<PlanRunState, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_state(&conn, &app_data, &session_id)
+}
+
+/// Starts a step-gated run; progress is reported through `plan-run:updated`.
+#[tauri::command]
+pub fn plan_run_start(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let update = with_run(&app, &db, |conn, app_data| start(conn, app_data, &session_id))?;
+    apply(&app, &session_id, update);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn plan_run_approve(
+    session_id: String,
+    comment: Option<String>,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let update = with_run(&app, &db, |conn, app_data| approve(conn, app_data, &session_id, comment.as_deref()))?;
+    apply(&app, &session_id, update);
+    Ok(())
+}
+
+/// Redoes the current step, passing `comment` to the agent as review feedback.
+#[tauri::command]
+pub fn plan_run_comment(
+    session_id: String,
+    comment: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let prompt = with_run(&app, &db, |conn, app_data| redo(conn, app_data, &session_id, Some(&comment)))?;
+    dispatch(&app, &session_id, Some(prompt));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn plan_run_retry(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let prompt = with_run(&app, &db, |conn, app_data| redo(conn, app_data, &session_id, None))?;
+    dispatch(&app, &session_id, Some(prompt));
+    Ok(())
+}
+
+/// Stops gating; a prompt already sent keeps running but its result is ignored.
+#[tauri::command]
+pub fn plan_run_cancel(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<(), String> {
+    with_run(&app, &db, |conn, _| cancel(conn, &session_id))?;
+    emit_state(&app, &session_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::test_support::TestDb;
+    use crate::phase::Phase;
+
+    /// A session in review whose plan has two tasks.
+    fn reviewing_session(conn: &TestDb) -> String {
+        let id = conn.session();
+        crate::plan_storage::write_session_plan(conn, &conn.dir, &id, "- [ ] first\n- [ ] second\n").unwrap();
+        for phase in [Phase::Planning, Phase::Reviewing] {
+            crate::phase::transition(conn, &id, phase, None).unwrap();
+        }
+        id
+    }
+
+    fn run_status(conn: &TestDb, id: &str) -> String {
+        get_run(conn, id).unwrap().unwrap().status
+    }
+
+    #[test]
+    fn start_moves_the_session_to_executing_once() {
+        let conn = TestDb::new();
+        let id = reviewing_session(&conn);
+        let update = start(&conn, &conn.dir, &id).unwrap();
+        assert!(update.prompt.unwrap().text.contains("step 1 of 2"));
+        assert_eq!(update.phase_event.unwrap().to_phase, "executing");
+        assert_eq!(crate::sessions::get_session(&conn, &id).unwrap().phase, "executing");
+        assert_eq!(run_status(&conn, &id), "running");
+
+        assert!(start(&conn, &conn.dir, &id).is_err());
+    }
+
+    #[test]
+    fn finishing_a_cancelled_attempt_is_ignored() {
+        let conn = TestDb::new();
+        let id = reviewing_session(&conn);
+        let attempt_id = start(&conn, &conn.dir, &id).unwrap().prompt.unwrap().attempt_id;
+        cancel(&conn, &id).unwrap();
+        finish_attempt(&conn, &id, attempt_id, None).unwrap();
+
+        assert_eq!(run_status(&conn, &id), "cancelled");
+        assert_eq!(latest_attempt(&conn, &id).unwrap().unwrap().outcome, "interrupted");
+    }
+
+    #[test]
+    fn approving_the_last_step_finishes_the_run() {
+        let conn = TestDb::new();
+        let id = reviewing_session(&conn);
+        let first = start(&conn, &conn.dir, &id).unwrap().prompt.unwrap();
+        finish_attempt(&conn, &id, first.attempt_id, None).unwrap();
+        let update = approve(&conn, &conn.dir, &id, None).unwrap();
+        update.plan_write.unwrap().apply().unwrap();
+        let second = update.prompt.unwrap();
+        assert!(second.text.contains("step 2 of 2"));
+
+        finish_attempt(&conn, &id, second.attempt_id, None).unwrap();
+        let update = approve(&conn, &conn.dir, &id, None).unwrap();
+        assert!(update.prompt.is_none());
+        assert_eq!(update.phase_event.unwrap().to_phase, "done");
+        assert_eq!(run_status(&conn, &id), "completed");
+        assert!(update.plan_write.unwrap().markdown().contains("- [x] second"));
+    }
+
+    #[test]
+    fn redo_numbers_attempts_per_step() {
+        let conn = TestDb::new();
+        let id = reviewing_session(&conn);
+        let first = start(&conn, &conn.dir, &id).unwrap().prompt.unwrap();
+        finish_attempt(&conn, &id, first.attempt_id, Some("tests failed")).unwrap();
+        let again = redo(&conn, &conn.dir, &id, Some("fix the tests")).unwrap();
+        assert!(again.text.contains("fix the tests"));
+
+        let attempts = list_attempts(&conn, &id).unwrap();
+        let numbers: Vec<_> = attempts.iter().map(|a| (a.attempt, a.decision.as_deref())).collect();
+        assert_eq!(numbers, [(1, Some("commented")), (2, None)]);
+        assert_eq!(attempts[0].step_id, attempts[1].step_id);
+    }
+
+    #[test]
+    fn recovered_runs_wait_for_review() {
+        let conn = TestDb::new();
+        let id = reviewing_session(&conn);
+        start(&conn, &conn.dir, &id).unwrap();
+        assert_eq!(recover_runs(&conn).unwrap(), 1);
+
+        assert_eq!(run_status(&conn, &id), "awaiting_review");
+        assert_eq!(latest_attempt(&conn, &id).unwrap().unwrap().outcome, "interrupted");
+        assert_eq!(recover_runs(&conn).unwrap(), 0);
+    }
+}
@@ -0,0 +1,528 @@
+//! Where session plans are stored.
+//!
+//! By default a plan lives in `<app_data>/plans/<session_id>.md`. A workspace
+//! can instead keep plans inside the repository, under a relative directory
+//! such as `docs/plans`, named after the session (`<slug>.md`) so they are
+//! visible to git. The chosen location is recorded in
+//! `sessions.plan_file_path`, and review comments follow the file when it is
+//! moved between locations.
+//!
+//! Repo plan directories are watched: a plan renamed inside the directory is
+//! followed, and one moved out or deleted is reported as missing.
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::sessions::SessionRecord;
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+pub const DEFAULT_PLANS_DIR: &str = "docs/plans";
+const MAX_SLUG_LEN: usize = 60;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanStorageSettings {
+    /// `app_data` or `repo`
+    pub location: String,
+    /// Directory relative to the workspace root, used when `location` is `repo`
+    pub plans_dir: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MovedPlan {
+    pub session_id: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PlanMigration {
+    pub moved: Vec<MovedPlan>,
+    /// Sessions whose plan lives elsewhere (e.g. a file the agent wrote) and
+    /// was left in place
+    pub skipped: Vec<String>,
+    /// Sessions whose plan could not be moved and still points at its old path
+    pub failed: Vec<String>,
+}
+
+/// Lowercase ASCII words joined by dashes, e.g. "Fix: login (v2)" → "fix-login-v2".
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let mut slug: String = slug.chars().take(MAX_SLUG_LEN).collect();
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        "plan".to_string()
+    } else {
+        slug
+    }
+}
+
+fn validate_plans_dir(dir: &str) -> Result<String, String> {
+    let dir = dir.trim().trim_end_matches('/');
+    let path = Path::new(dir);
+    if dir.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Plans directory must be a relative path inside the workspace: {}", dir));
+    }
+    Ok(dir.to_string())
+}
+
+pub fn get_settings(conn: &Connection, workspace_id: &str) -> Result<PlanStorageSettings, String> {
+    let dir: Option<String> = conn
+        .query_row(
+            "SELECT plans_dir FROM workspace_plan_settings WHERE workspace_id = ?1",
+            params![workspace_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Query plan settings error: {}", e))?;
+    Ok(match dir {
+        Some(plans_dir) => PlanStorageSettings { location: "repo".to_string(), plans_dir },
+        None => PlanStorageSettings { location: "app_data".to_string(), plans_dir: DEFAULT_PLANS_DIR.to_string() },
+    })
+}
+
+/// Absolute plans directory of a workspace that stores plans in the repo.
+fn repo_plans_dir(conn: &Connection, workspace_id: &str) -> Result<Option<PathBuf>, String> {
+    let settings = get_settings(conn, workspace_id)?;
+    if settings.location != "repo" {
+        return Ok(None);
+    }
+    let workspace = crate::sessions::get_workspace(conn, workspace_id)?;
+    Ok(Some(Path::new(&workspace.path).join(settings.plans_dir)))
+}
+
+pub(crate) fn session_repo_plans_dir(conn: &Connection, session_id: &str) -> Result<Option<PathBuf>, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    repo_plans_dir(conn, &session.workspace_id)
+}
+
+/// Whether the session's plan is one this module placed: the app-data file
+/// or a file in the workspace's repo plans directory. Anything else was
+/// pointed at by the agent or the user.
+pub(crate) fn is_managed_plan(conn: &Connection, app_data_dir: &PathBuf, session: &SessionRecord) -> Result<bool, String> {
+    let Some(recorded) = session.plan_file_path.as_deref().map(Path::new) else {
+        return Ok(true);
+    };
+    if recorded == crate::plan_file::get_plan_path(app_data_dir, &session.id) {
+        return Ok(true);
+    }
+    let repo_dir = repo_plans_dir(conn, &session.workspace_id)?;
+    Ok(repo_dir.is_some_and(|d| recorded.parent() == Some(d.as_path())))
+}
+
+/// A file name in `dir` for the session's plan that no other session uses
+/// and that does not exist yet.
+fn unique_plan_path(conn: &Connection, dir: &Path, session_id: &str, name: &str) -> Result<PathBuf, String> {
+    let slug = slugify(name);
+    let short_id: String = session_id.chars().take(8).collect();
+    for candidate in [format!("{}.md", slug), format!("{}-{}.md", slug, short_id)] {
+        let path = dir.join(candidate);
+        let taken: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sessions WHERE plan_file_path = ?1 AND id != ?2)",
+                params![path.to_string_lossy(), session_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Query plan path error: {}", e))?;
+        if !taken && !path.exists() {
+            return Ok(path);
+        }
+    }
+    Ok(dir.join(format!("{}-{}.md", slug, session_id)))
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    if std::fs::rename(from, to).is_err() {
+        // Across filesystems (app data and the repo may be on different mounts)
+        std::fs::copy(from, to).map_err(|e| format!("Failed to copy plan to {}: {}", to.display(), e))?;
+        std::fs::remove_file(from).map_err(|e| format!("Failed to remove {}: {}", from.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Points the session and the comments on its plan at the plan's new path.
+pub fn relocate_plan(conn: &Connection, session_id: &str, from: &str, to: &str) -> Result<(), String> {
+    crate::sessions::update_plan_file_path(conn, session_id, to)?;
+    conn.execute("UPDATE comments SET file_path = ?1 WHERE file_path = ?2", params![to, from])
+        .map_err(|e| format!("Move comments error: {}", e))?;
+    conn.execute("UPDATE OR REPLACE file_hashes SET file_path = ?1 WHERE file_path = ?2", params![to, from])
+        .map_err(|e| format!("Move file hash error: {}", e))?;
+    Ok(())
+}
+
+fn move_plan(conn: &Connection, session_id: &str, from: &Path, to: &Path) -> Result<MovedPlan, String> {
+    move_file(from, to)?;
+    let (from, to) = (from.to_string_lossy().to_string(), to.to_string_lossy().to_string());
+    relocate_plan(conn, session_id, &from, &to)?;
+    Ok(MovedPlan { session_id: session_id.to_string(), from, to })
+}
+
+/// Where the session's plan is read from: its recorded path, or the
+/// app-data default.
+pub fn plan_path(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<PathBuf, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    Ok(crate::plan_file::session_plan_path(app_data_dir, session_id, session.plan_file_path.as_deref()))
+}
+
+/// Where the session's plan is written. In a workspace that stores plans in
+/// the repo, a session without a recorded path gets a slugged file there and
+/// a plan already in app data is moved over.
+pub fn assign_plan_path(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<PathBuf, String> {
+    let session = crate::sessions::get_session(conn, session_id)?;
+    let app_data_path = crate::plan_file::get_plan_path(app_data_dir, session_id);
+    if let Some(recorded) = session.plan_file_path.as_deref().map(PathBuf::from).filter(|p| *p != app_data_path) {
+        return Ok(recorded);
+    }
+    let Some(dir) = repo_plans_dir(conn, &session.workspace_id)? else {
+        return Ok(app_data_path);
+    };
+    let target = unique_plan_path(conn, &dir, session_id, &session.name)?;
+    if app_data_path.exists() {
+        move_plan(conn, session_id, &app_data_path, &target)?;
+    } else {
+        // Comments may already be keyed by the app-data path (e.g. an import)
+        relocate_plan(conn, session_id, &app_data_path.to_string_lossy(), &target.to_string_lossy())?;
+    }
+    Ok(target)
+}
+
+pub fn read_session_plan(conn: &Connection, app_data_dir: &PathBuf, session_id: &str) -> Result<String, String> {
+    let path = plan_path(conn, app_data_dir, session_id)?;
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read plan: {}", e))
+}
+
+pub fn write_session_plan(conn: &Connection, app_data_dir: &PathBuf, session_id: &str, markdown: &str) -> Result<PathBuf, String> {
+    let path = assign_plan_path(conn, app_data_dir, session_id)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create plans directory: {}", e))?;
+    }
+    std::fs::write(&path, markdown).map_err(|e| format!("Failed to write plan: {}", e))?;
+    crate::sessions::update_plan_file_path(conn, session_id, &path.to_string_lossy())?;
+    Ok(path)
+}
+
+/// Changes where the workspace stores plans. With `migrate`, existing plans
+/// are pointed at the new location (comments included) and listed as moved;
+/// the files themselves are moved by `move_plan_files` once this is
+/// committed. Plans stored anywhere else are left alone.
+pub fn set_settings(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    workspace_id: &str,
+    location: &str,
+    plans_dir: Option<&str>,
+    migrate: bool,
+) -> Result<PlanMigration, String> {
+    let previous_dir = repo_plans_dir(conn, workspace_id)?;
+    match location {
+        "repo" => {
+            let dir = validate_plans_dir(plans_dir.unwrap_or(DEFAULT_PLANS_DIR))?;
+            conn.execute(
+                "INSERT INTO workspace_plan_settings (workspace_id, plans_dir) VALUES (?1, ?2)
+                 ON CONFLICT(workspace_id) DO UPDATE SET plans_dir = excluded.plans_dir",
+                params![workspace_id, dir],
+            )
+            .map_err(|e| format!("Update plan settings error: {}", e))?;
+        }
+        "app_data" => {
+            conn.execute("DELETE FROM workspace_plan_settings WHERE workspace_id = ?1", params![workspace_id])
+                .map_err(|e| format!("Update plan settings error: {}", e))?;
+        }
+        other => return Err(format!("Invalid plan location: {}. Must be app_data or repo", other)),
+    }
+
+    let mut report = PlanMigration::default();
+    if !migrate {
+        return Ok(report);
+    }
+    let new_dir = repo_plans_dir(conn, workspace_id)?;
+    let mut stmt = conn
+        .prepare("SELECT id, plan_file_path FROM sessions WHERE workspace_id = ?1")
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let sessions = stmt
+        .query_map(params![workspace_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    for (session_id, recorded) in sessions {
+        let app_data_path = crate::plan_file::get_plan_path(app_data_dir, &session_id);
+        let current = recorded.map(PathBuf::from).unwrap_or_else(|| app_data_path.clone());
+        if !current.exists() {
+            continue;
+        }
+        let in_app_data = current == app_data_path;
+        let in_previous_repo = previous_dir.as_ref().is_some_and(|d| current.parent() == Some(d.as_path()));
+        if !in_app_data && !in_previous_repo {
+            report.skipped.push(session_id);
+            continue;
+        }
+        let target = match &new_dir {
+            Some(dir) if current.parent() == Some(dir.as_path()) => continue,
+            Some(dir) => {
+                let name = crate::sessions::get_session(conn, &session_id)?.name;
+                unique_plan_path(conn, dir, &session_id, &name)?
+            }
+            None if in_app_data => continue,
+            None => app_data_path,
+        };
+        let (from, to) = (current.to_string_lossy().to_string(), target.to_string_lossy().to_string());
+        relocate_plan(conn, &session_id, &from, &to)?;
+        report.moved.push(MovedPlan { session_id, from, to });
+    }
+    Ok(report)
+}
+
+/// Moves the files of a committed migration. A plan that cannot be moved is
+/// pointed back at its old path and reported as failed instead of moved.
+pub fn move_plan_files(conn: &Connection, report: &mut PlanMigration) {
+    for plan in std::mem::take(&mut report.moved) {
+        match move_file(Path::new(&plan.from), Path::new(&plan.to)) {
+            Ok(()) => report.moved.push(plan),
+            Err(e) => {
+                eprintln!("[plan] Failed to move plan of session {}: {}", plan.session_id, e);
+                if let Err(e) = relocate_plan(conn, &plan.session_id, &plan.to, &plan.from) {
+                    eprintln!("[plan] Failed to restore plan path of session {}: {}", plan.session_id, e);
+                }
+                report.failed.push(plan.session_id);
+            }
+        }
+    }
+}
+
+/// Session whose recorded plan path is `path`.
+pub(crate) fn session_for_path(conn: &Connection, path: &Path) -> Option<String> {
+    conn.query_row(
+        "SELECT id FROM sessions WHERE plan_file_path = ?1",
+        params![path.to_string_lossy()],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+// --- Directory watcher ---
+
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Plans renamed away whose new name has not been reported yet, kept so the
+/// two halves of a split rename can be paired.
+const MAX_PENDING_RENAMES: usize = 16;
+
+/// Watches the repo plan directories of workspaces that store plans in the repo.
+#[derive(Default)]
+pub struct PlanDirWatcher {
+    watcher: Mutex<Option<notify::RecommendedWatcher>>,
+    dirs: Mutex<HashSet<PathBuf>>,
+    /// Old paths of renamed plans with the rename's tracker, if any. inotify
+    /// reports both halves of a rename with a shared tracker; FSEvents reports
+    /// each path as `RenameMode::Any` without one.
+    renamed_from: Mutex<VecDeque<(Option<usize>, PathBuf)>>,
+}
+
+impl PlanDirWatcher {
+    fn remember_rename(&self, tracker: Option<usize>, from: &Path) {
+        let Ok(mut pending) = self.renamed_from.lock() else { return };
+        pending.retain(|(_, p)| p != from);
+        if pending.len() == MAX_PENDING_RENAMES {
+            pending.pop_front();
+        }
+        pending.push_back((tracker, from.to_path_buf()));
+    }
+
+    /// The old path of the plan renamed to `to`: the one with the same
+    /// tracker, or without a tracker the latest untracked rename in the same
+    /// directory.
+    fn take_rename(&self, tracker: Option<usize>, to: &Path) -> Option<PathBuf> {
+        let mut pending = self.renamed_from.lock().ok()?;
+        let index = match tracker {
+            Some(_) => pending.iter().position(|(t, _)| *t == tracker),
+            None => pending.iter().rposition(|(t, p)| t.is_none() && p.parent() == to.parent()),
+        }?;
+        pending.remove(index).map(|(_, path)| path)
+    }
+}
+
+/// Follows a plan renamed from `from` to `to`. Returns false when `from` is
+/// not a plan.
+fn follow_rename(app: &AppHandle, db: &CommentsDb, from: &Path, to: &Path) -> bool {
+    let Ok(conn) = db.0.lock() else { return false };
+    let Some(session_id) = session_for_path(&conn, from) else { return false };
+    let (from, to) = (from.to_string_lossy().to_string(), to.to_string_lossy().to_string());
+    match relocate_plan(&conn, &session_id, &from, &to) {
+        Ok(()) => {
+            eprintln!("[plan] Plan of session {} moved to {}", session_id, to);
+            let _ = app.emit("plan:moved", MovedPlan { session_id, from, to });
+        }
+        Err(e) => eprintln!("[plan] Failed to follow moved plan {}: {}", from, e),
+    }
+    true
+}
+
+/// Reports `path` as missing if it is a plan. Returns whether it was one.
+fn report_missing(app: &AppHandle, db: &CommentsDb, path: &Path) -> bool {
+    let Ok(conn) = db.0.lock() else { return false };
+    let Some(session_id) = session_for_path(&conn, path) else { return false };
+    eprintln!("[plan] Plan of session {} is gone: {}", session_id, path.display());
+    let _ = app.emit("plan:missing", serde_json::json!({
+        "sessionId": session_id,
+        "path": path.to_string_lossy(),
+    }));
+    true
+}
+
+/// One half of a rename: a path that no longer exists is the old name, one
+/// that does is the new name.
+fn handle_rename_half(app: &AppHandle, db: &CommentsDb, watcher: &PlanDirWatcher, tracker: Option<usize>, path: &Path) {
+    if !path.exists() {
+        if report_missing(app, db, path) {
+            watcher.remember_rename(tracker, path);
+        }
+        return;
+    }
+    match watcher.take_rename(tracker, path) {
+        Some(from) if follow_rename(app, db, &from, path) => {}
+        _ => crate::plan_history::record_file_change(app, path),
+    }
+}
+
+fn handle_event(app: &AppHandle, event: Event) {
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let Some(watcher) = app.try_state::<PlanDirWatcher>() else { return };
+    let tracker = event.attrs.tracker();
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let (from, to) = (&event.paths[0], &event.paths[1]);
+            if tracker.is_some() {
+                // Already followed if the halves were reported too
+                watcher.take_rename(tracker, to);
+            }
+            if !follow_rename(app, &db, from, to) {
+                // Editors save by renaming a temporary file over the plan
+                crate::plan_history::record_file_change(app, to);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            for path in &event.paths {
+                handle_rename_half(app, &db, &watcher, tracker, path);
+            }
+        }
+        EventKind::Remove(_) => {
+            for path in event.paths.iter().filter(|p| !p.exists()) {
+                report_missing(app, &db, path);
+            }
+        }
+        EventKind::Modify(_) => {
+            for path in &event.paths {
+                crate::plan_history::record_file_change(app, path);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Starts watching `dir` (created if needed) for plan moves and deletions.
+pub fn watch_dir(app: &AppHandle, dir: &Path) -> Result<(), String> {
+    let Some(state) = app.try_state::<PlanDirWatcher>() else { return Ok(()) };
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut dirs = state.dirs.lock().map_err(|e| e.to_string())?;
+    if dirs.contains(dir) {
+        return Ok(());
+    }
+    let mut guard = state.watcher.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        let handle = app.clone();
+        let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            if let Ok(event) = res {
+                handle_event(&handle, event);
+            }
+        })
+        .map_err(|e| format!("Failed to create plan watcher: {}", e))?;
+        *guard = Some(watcher);
+    }
+    if let Some(watcher) = guard.as_mut() {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+    }
+    dirs.insert(dir.to_path_buf());
+    Ok(())
+}
+
+/// Called at startup: watches the plan directories of all repo-backed workspaces.
+pub fn watch_all(app: &AppHandle) {
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let dirs: Vec<PathBuf> = {
+        let Ok(conn) = db.0.lock() else { return };
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT w.path, s.plans_dir FROM workspace_plan_settings s JOIN workspaces w ON w.id = s.workspace_id",
+        ) else {
+            return;
+        };
+        let Ok(rows) = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))) else {
+            return;
+        };
+        rows.flatten().map(|(root, dir)| Path::new(&root).join(dir)).collect()
+    };
+    for dir in dirs.iter().filter(|d| d.parent().is_some_and(|p| p.exists())) {
+        if let Err(e) = watch_dir(app, dir) {
+            eprintln!("[plan] {}", e);
+        }
+    }
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn workspace_plan_settings_get(
+    workspace_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<PlanStorageSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_settings(&conn, &workspace_id)
+}
+
+#[tauri::command]
+pub fn workspace_plan_settings_set(
+    workspace_id: String,
+    location: String,
+    plans_dir: Option<String>,
+    migrate: Option<bool>,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<PlanMigration, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let (report, dir) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let tx = conn.unchecked_transaction().map_err(|e| format!("Transaction error: {}", e))?;
+        let mut report = set_settings(&tx, &app_data, &workspace_id, &location, plans_dir.as_deref(), migrate.unwrap_or(true))?;
+        let dir = repo_plans_dir(&tx, &workspace_id)?;
+        tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+        move_plan_files(&conn, &mut report);
+        (report, dir)
+    };
+    if let Some(dir) = dir {
+        watch_dir(&app, &dir)?;
+    }
+    Ok(report)
+}
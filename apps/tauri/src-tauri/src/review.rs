@@ -0,0 +1,444 @@
+//! Sending plan review comments to the agent.
+//!
+//! The unresolved comments on a session's plan go out as one prompt, each
+//! quoting the blocks it is about. Every comment sent is linked to the
+//! prompt's message in `comment_feedback`, along with the text it quoted at
+//! the time. When the agent's turn ends, a comment whose quoted text no
+//! longer appears anywhere in the plan is taken as addressed: it is resolved
+//! and the feedback row records the turn that addressed it.
+
+use crate::comments::Comment;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const QUOTE_LIMIT: usize = 200;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommentFeedback {
+    pub comment_id: String,
+    pub session_id: String,
+    /// Id of the prompt message, once it has been saved
+    pub message_id: Option<String>,
+    /// Ordinal of the prompt among the session's user messages
+    pub turn: Option<i64>,
+    /// `sent`, `addressed`, `unchanged`, `unknown` (nothing was quoted to
+    /// compare against) or `failed`
+    pub status: String,
+    pub sent_at: i64,
+    pub addressed_at: Option<i64>,
+}
+
+/// The commented blocks' text, or the commented range of it, quoted and
+/// trimmed to a readable length.
+fn quote_blocks(markdown: &str, comment: &Comment) -> String {
+    let blocks = crate::markdown::viewer_blocks(markdown);
+    comment
+        .block_ids
+        .iter()
+        .filter_map(|id| blocks.iter().find(|(bid, _)| bid == id))
+        .map(|(id, block)| {
+            let range = comment.ranges.iter().find(|r| r.block_id == *id);
+            let text = range.map_or(block.text.as_str(), |r| r.quote.as_str()).trim();
+            let text = match text.char_indices().nth(QUOTE_LIMIT) {
+                Some((cut, _)) => format!("{}...", &text[..cut]),
+                None => text.to_string(),
+            };
+            text.lines().map(|l| format!("> {}", l)).collect::<Vec<_>>().join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n>\n")
+}
+
+/// `## Comment N` sections for the unresolved threads, in the layout the
+/// review panel uses, each followed by its replies.
+pub(crate) fn comment_sections(markdown: &str, comments: &[Comment]) -> String {
+    comments
+        .iter()
+        .filter(|c| c.parent_id.is_none() && !c.resolved)
+        .enumerate()
+        .map(|(idx, comment)| {
+            let quoted = quote_blocks(markdown, comment);
+            let about = if quoted.is_empty() {
+                String::new()
+            } else {
+                format!("About the block(s):\n{}\n\n", quoted)
+            };
+            let mut section = format!("## Comment {}\n{}Message: {}", idx + 1, about, comment.text.trim());
+            let replies: Vec<String> = comments
+                .iter()
+                .filter(|r| r.parent_id.as_deref() == Some(comment.id.as_str()))
+                .map(|r| format!("- {}: {}", r.author.as_deref().unwrap_or(&r.author_kind), r.text.trim()))
+                .collect();
+            if !replies.is_empty() {
+                section.push_str(&format!("\nReplies:\n{}", replies.join("\n")));
+            }
+            section
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+const REPLY_PREFIX: &str = "reply to comment ";
+
+pub fn review_prompt(plan_path: &Path, markdown: &str, comments: &[Comment]) -> String {
+    format!(
+        "Please revise the plan in `{}` to address these review comments:\n\n{}\n\n\
+         If a comment does not call for a change to the plan, answer it instead \
+         with a line `Reply to comment N: <your answer>`.",
+        plan_path.display(),
+        comment_sections(markdown, comments)
+    )
+}
+
+/// `(N, answer)` for each `Reply to comment N: ...` line of the agent's
+/// response, tolerating list markers and bold around the prefix.
+pub fn parse_replies(response: &str) -> Vec<(usize, String)> {
+    response
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start_matches(['-', '*', '>', ' ']);
+            let prefix = line.get(..REPLY_PREFIX.len())?;
+            if !prefix.eq_ignore_ascii_case(REPLY_PREFIX) {
+                return None;
+            }
+            let rest = &line[REPLY_PREFIX.len()..];
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            let n: usize = rest[..digits].parse().ok()?;
+            let answer = rest[digits..].strip_prefix(':')?.trim_start_matches('*').trim();
+            (!answer.is_empty()).then(|| (n, answer.to_string()))
+        })
+        .collect()
+}
+
+/// The text the comment is about in each of its blocks: the commented range
+/// if there is one, otherwise the whole block.
+fn quoted_texts(markdown: &str, comment: &Comment) -> HashMap<String, String> {
+    crate::markdown::viewer_blocks(markdown)
+        .into_iter()
+        .filter(|(id, _)| comment.block_ids.contains(id))
+        .map(|(id, block)| {
+            let range = comment.ranges.iter().find(|r| r.block_id == id);
+            let text = range.map_or(block.text.as_str(), |r| r.quote.as_str()).trim().to_string();
+            (id, text)
+        })
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+pub struct PendingReview {
+    pub prompt: String,
+    pub comment_ids: Vec<String>,
+}
+
+/// Builds the review prompt for the session's plan and links its unresolved
+/// comments to `client_message_id`. Returns `None` when nothing is unresolved.
+pub fn prepare(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    session_id: &str,
+    client_message_id: &str,
+) -> Result<Option<PendingReview>, String> {
+    let path = crate::plan_storage::plan_path(conn, app_data_dir, session_id)?;
+    let markdown = crate::plan_storage::read_session_plan(conn, app_data_dir, session_id)?;
+    let comments: Vec<Comment> = crate::comments::load_comments(conn, &path.to_string_lossy())?
+        .comments;
+    let threads: Vec<&Comment> = comments.iter().filter(|c| c.parent_id.is_none() && !c.resolved).collect();
+    if threads.is_empty() {
+        return Ok(None);
+    }
+
+    let now = crate::comments::now();
+    let plan_hash = crate::checkpoints::hash_bytes(markdown.as_bytes());
+    for comment in &threads {
+        let snapshot = serde_json::to_string(&quoted_texts(&markdown, comment))
+            .map_err(|e| format!("Serialize blocks error: {}", e))?;
+        conn.execute(
+            "INSERT INTO comment_feedback (comment_id, session_id, client_message_id, blocks_json, plan_hash, status, sent_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'sent', ?6)",
+            params![comment.id, session_id, client_message_id, snapshot, plan_hash, now],
+        )
+        .map_err(|e| format!("Insert comment feedback error: {}", e))?;
+    }
+    Ok(Some(PendingReview {
+        prompt: review_prompt(&path, &markdown, &comments),
+        comment_ids: threads.into_iter().map(|c| c.id.clone()).collect(),
+    }))
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewOutcome {
+    pub session_id: String,
+    pub message_id: Option<String>,
+    pub turn: Option<i64>,
+    /// Comments whose quoted text the agent rewrote; these were resolved
+    pub addressed: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Comments that quoted nothing, so whether they were addressed is unknown
+    pub unknown: Vec<String>,
+    /// Replies the agent posted instead of changing the plan
+    pub replies: Vec<Comment>,
+}
+
+/// After the agent's turn: links the feedback rows to the saved message and
+/// resolves the comments whose quoted text was rewritten. A comment is
+/// addressed when none of the text it quoted is left anywhere in the plan, so
+/// editing one of several blocks, or moving a block, does not count. One that
+/// quoted nothing (its blocks could not be found) is marked unknown and left
+/// unresolved.
+pub fn complete(
+    conn: &Connection,
+    app_data_dir: &PathBuf,
+    session_id: &str,
+    client_message_id: &str,
+    error: Option<&str>,
+) -> Result<ReviewOutcome, String> {
+    let message: Option<(String, i64)> = conn
+        .query_row(
+            "SELECT m.id, (SELECT COUNT(*) FROM messages u
+                           WHERE u.session_id = m.session_id AND u.role = 'user' AND u.seq <= m.seq)
+             FROM messages m WHERE m.session_id = ?1 AND m.client_message_id = ?2",
+            params![session_id, client_message_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .ok();
+    let (message_id, turn) = message.unzip();
+    let mut outcome = ReviewOutcome {
+        session_id: session_id.to_string(),
+        message_id: message_id.clone(),
+        turn,
+        addressed: Vec::new(),
+        unchanged: Vec::new(),
+        unknown: Vec::new(),
+        replies: Vec::new(),
+    };
+
+    if let Some(e) = error {
+        conn.execute(
+            "UPDATE comment_feedback SET status = 'failed', message_id = ?3, turn = ?4
+             WHERE session_id = ?1 AND client_message_id = ?2",
+            params![session_id, client_message_id, message_id, turn],
+        )
+        .map_err(|e| format!("Update comment feedback error: {}", e))?;
+        eprintln!("[review] Review prompt for {} failed: {}", session_id, e);
+        return Ok(outcome);
+    }
+
+    let markdown = crate::plan_storage::read_session_plan(conn, app_data_dir, session_id)?;
+    let current: Vec<String> = crate::markdown::viewer_blocks(&markdown)
+        .into_iter()
+        .map(|(_, block)| block.text.trim().to_string())
+        .collect();
+    let mut stmt = conn
+        .prepare(
+            "SELECT f.comment_id, f.blocks_json, c.resolved FROM comment_feedback f
+             JOIN comments c ON c.id = f.comment_id
+             WHERE f.session_id = ?1 AND f.client_message_id = ?2 ORDER BY f.rowid",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id, client_message_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    post_replies(conn, session_id, client_message_id, &rows.iter().map(|r| r.0.clone()).collect::<Vec<_>>(), &mut outcome)?;
+
+    let now = crate::comments::now();
+    for (comment_id, blocks_json, resolved) in rows {
+        let snapshot: HashMap<String, String> = serde_json::from_str(&blocks_json).unwrap_or_default();
+        let status = if snapshot.is_empty() {
+            "unknown"
+        } else if snapshot.values().all(|text| !current.iter().any(|block| block.contains(text.as_str()))) {
+            "addressed"
+        } else {
+            "unchanged"
+        };
+        let addressed_at = (status == "addressed").then_some(now);
+        conn.execute(
+            "UPDATE comment_feedback SET status = ?4, message_id = ?5, turn = ?6, addressed_at = ?7
+             WHERE comment_id = ?1 AND session_id = ?2 AND client_message_id = ?3",
+            params![comment_id, session_id, client_message_id, status, message_id, turn, addressed_at],
+        )
+        .map_err(|e| format!("Update comment feedback error: {}", e))?;
+        match status {
+            "addressed" => {
+                if !resolved {
+                    conn.execute("UPDATE comments SET resolved = 1 WHERE id = ?1", params![comment_id])
+                        .map_err(|e| format!("Resolve comment error: {}", e))?;
+                }
+                outcome.addressed.push(comment_id)
+            }
+            "unknown" => outcome.unknown.push(comment_id),
+            _ => outcome.unchanged.push(comment_id),
+        }
+    }
+    Ok(outcome)
+}
+
+/// Posts the agent's `Reply to comment N` answers from its response to the
+/// prompt as replies; `N` counts the comments in the order they were sent.
+fn post_replies(
+    conn: &Connection,
+    session_id: &str,
+    client_message_id: &str,
+    comment_ids: &[String],
+    outcome: &mut ReviewOutcome,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT content FROM messages
+             WHERE session_id = ?1 AND role = 'assistant' AND message_type IS NULL
+               AND seq > (SELECT seq FROM messages WHERE session_id = ?1 AND client_message_id = ?2)
+             ORDER BY seq",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let response = stmt
+        .query_map(params![session_id, client_message_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?
+        .join("\n");
+    let provider = crate::sessions::get_session(conn, session_id)?.provider;
+    for (n, answer) in parse_replies(&response) {
+        let Some(parent_id) = n.checked_sub(1).and_then(|i| comment_ids.get(i)) else { continue };
+        let reply = crate::comments::NewComment {
+            text: answer,
+            parent_id: Some(parent_id.clone()),
+            author: Some(provider.clone()),
+            author_kind: Some("agent".to_string()),
+            ..Default::default()
+        };
+        outcome.replies.push(crate::comments::add_comment(conn, "", "", &reply)?);
+    }
+    Ok(())
+}
+
+pub fn list_feedback(conn: &Connection, session_id: &str) -> Result<Vec<CommentFeedback>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT comment_id, session_id, message_id, turn, status, sent_at, addressed_at
+             FROM comment_feedback WHERE session_id = ?1 ORDER BY sent_at, rowid",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id], |row| {
+            Ok(CommentFeedback {
+                comment_id: row.get(0)?,
+                session_id: row.get(1)?,
+                message_id: row.get(2)?,
+                turn: row.get(3)?,
+                status: row.get(4)?,
+                sent_at: row.get(5)?,
+                addressed_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
+// --- Tauri commands ---
+
+use crate::acp::commands::{send_session_prompt, AcpSessionStore};
+use crate::acp::connection::PromptOptions;
+use crate::comments::CommentsDb;
+use crate::phase::Phase;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Sends the plan's unresolved comments to the agent as one prompt. Returns
+/// the ids of the comments sent; the outcome follows as `review:completed`
+/// once the agent's turn ends.
+#[tauri::command]
+pub fn review_send_comments(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    let app_data = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let client_message_id = uuid::Uuid::new_v4().to_string();
+    let (pending, event) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let tx = conn.unchecked_transaction().map_err(|e| format!("Transaction error: {}", e))?;
+        let Some(pending) = prepare(&tx, &app_data, &session_id, &client_message_id)? else {
+            return Err("The plan has no unresolved comments".to_string());
+        };
+        // Comments on a plan under review send it back for changes
+        let session = crate::sessions::get_session(&tx, &session_id)?;
+        let event = if session.phase == Phase::Reviewing.as_str() {
+            crate::phase::transition(&tx, &session_id, Phase::Planning, Some("review comments sent"))?
+        } else {
+            None
+        };
+        tx.commit().map_err(|e| format!("Commit error: {}", e))?;
+        (pending, event)
+    };
+    if let Some(event) = &event {
+        let _ = app.emit("session:phase-changed", event);
+    }
+
+    let comment_ids = pending.comment_ids.clone();
+    tauri::async_runtime::spawn(async move {
+        let store = app.state::<AcpSessionStore>();
+        let options = PromptOptions {
+            client_message_id: Some(client_message_id.clone()),
+            ..Default::default()
+        };
+        let result = send_session_prompt(&store, &app, session_id.clone(), pending.prompt, options).await;
+        let Some(db) = app.try_state::<CommentsDb>() else { return };
+        let Ok(conn) = db.0.lock() else { return };
+        match complete(&conn, &app_data, &session_id, &client_message_id, result.err().as_deref()) {
+            Ok(outcome) => {
+                let _ = app.emit("review:completed", &outcome);
+            }
+            Err(e) => eprintln!("[review] Failed to check addressed comments for {}: {}", session_id, e),
+        }
+    });
+    Ok(comment_ids)
+}
+
+#[tauri::command]
+pub fn review_comment_feedback(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<CommentFeedback>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_feedback(&conn, &session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_reply_lines() {
+        let response = "I updated the plan.\n\n\
+            Reply to comment 1: The cache stays, it is needed for retries.\n\
+            - **Reply to comment 3:** Out of scope for now.\n\
+            > reply to comment 2:   Done elsewhere.\n";
+        assert_eq!(
+            parse_replies(response),
+            vec![
+                (1, "The cache stays, it is needed for retries.".to_string()),
+                (3, "Out of scope for now.".to_string()),
+                (2, "Done elsewhere.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lines_that_are_not_replies() {
+        let response = "Reply to comment: missing number\n\
+            Reply to comment 4 without a colon\n\
+            Reply to comment 5:\n\
+            We reply to comment 6: mid-sentence\n\
+            Réply to comment 7: accented\n";
+        assert!(parse_replies(response).is_empty());
+    }
+}
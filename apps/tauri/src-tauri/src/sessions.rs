@@ -12,11 +12,28 @@ pub struct SessionRecord {
     pub plan_file_path: Option<String>,
     pub phase: String,
     pub acp_preferences_json: String,
+    pub comparison_id: Option<String>,
+    /// Dedicated git worktree the agent runs in, when the session is isolated
+    pub worktree_path: Option<String>,
+    pub worktree_branch: Option<String>,
+    /// Session and message this session was forked from
+    pub forked_from_session_id: Option<String>,
+    pub forked_from_message_id: Option<String>,
+    /// Hidden from the default session list and counts (archived by the user,
+    /// or history kept by a rewind)
+    #[serde(default)]
+    pub archived: bool,
+    /// Listed before unpinned sessions
+    #[serde(default)]
+    pub pinned: bool,
+    /// Tag names, sorted
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
-fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<SessionRecord> {
+pub(crate) fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<SessionRecord> {
     Ok(SessionRecord {
         id: row.get(0)?,
         workspace_id: row.get(1)?,
@@ -27,16 +44,28 @@ fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<SessionRecord> {
         plan_file_path: row.get(6)?,
         phase: row.get(7)?,
         acp_preferences_json: row.get(8)?,
-        created_at: row.get(9)?,
-        updated_at: row.get(10)?,
+        comparison_id: row.get(9)?,
+        worktree_path: row.get(10)?,
+        worktree_branch: row.get(11)?,
+        forked_from_session_id: row.get(12)?,
+        forked_from_message_id: row.get(13)?,
+        archived: row.get::<_, i32>(14)? != 0,
+        pinned: row.get::<_, i32>(15)? != 0,
+        tags: serde_json::from_str(&row.get::<_, String>(18)?).unwrap_or_default(),
+        created_at: row.get(16)?,
+        updated_at: row.get(17)?,
     })
 }
 
-const SESSION_COLUMNS: &str = "id, workspace_id, acp_session_id, provider, name, initial_prompt, plan_file_path, phase, acp_preferences_json, created_at, updated_at";
+pub const VALID_PROVIDERS: [&str; 3] = ["copilot", "claude", "openai"];
+
+pub(crate) const SESSION_COLUMNS: &str = "id, workspace_id, acp_session_id, provider, name, initial_prompt, plan_file_path, phase, acp_preferences_json, comparison_id, worktree_path, worktree_branch, forked_from_session_id, forked_from_message_id, archived, pinned, created_at, updated_at,
+    (SELECT json_group_array(name) FROM (SELECT t.name FROM session_tags st JOIN tags t ON t.id = st.tag_id
+        WHERE st.session_id = sessions.id ORDER BY t.name))";
 
 pub fn list_sessions(conn: &Connection, workspace_id: &str) -> Result<Vec<SessionRecord>, String> {
     let sql = format!(
-        "SELECT {} FROM sessions WHERE workspace_id = ?1 ORDER BY updated_at DESC",
+        "SELECT {} FROM sessions WHERE workspace_id = ?1 AND archived = 0 ORDER BY pinned DESC, updated_at DESC",
         SESSION_COLUMNS
     );
     let mut stmt = conn.prepare(&sql)
@@ -51,6 +80,88 @@ pub fn list_sessions(conn: &Connection, workspace_id: &str) -> Result<Vec<Sessio
     Ok(rows)
 }
 
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct SessionFilters {
+    /// Only sessions carrying all of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub pinned: Option<bool>,
+    /// `None` and `Some(false)` exclude archived sessions, `Some(true)` lists only archived ones
+    pub archived: Option<bool>,
+    pub provider: Option<String>,
+    pub phase: Option<String>,
+}
+
+/// Sort keys for `list_sessions_filtered`; pinned sessions always come first.
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSort {
+    #[default]
+    UpdatedDesc,
+    CreatedDesc,
+    CreatedAsc,
+    NameAsc,
+}
+
+impl SessionSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            SessionSort::UpdatedDesc => "updated_at DESC",
+            SessionSort::CreatedDesc => "created_at DESC",
+            SessionSort::CreatedAsc => "created_at ASC",
+            SessionSort::NameAsc => "name COLLATE NOCASE ASC",
+        }
+    }
+}
+
+pub fn list_sessions_filtered(
+    conn: &Connection,
+    workspace_id: &str,
+    filters: &SessionFilters,
+    sort: SessionSort,
+) -> Result<Vec<SessionRecord>, String> {
+    let mut clauses = vec!["workspace_id = ?".to_string(), "archived = ?".to_string()];
+    let mut values: Vec<Box<dyn rusqlite::ToSql>> = vec![
+        Box::new(workspace_id.to_string()),
+        Box::new(filters.archived.unwrap_or(false) as i32),
+    ];
+    if let Some(pinned) = filters.pinned {
+        clauses.push("pinned = ?".to_string());
+        values.push(Box::new(pinned as i32));
+    }
+    if let Some(provider) = &filters.provider {
+        clauses.push("provider = ?".to_string());
+        values.push(Box::new(provider.clone()));
+    }
+    if let Some(phase) = &filters.phase {
+        clauses.push("phase = ?".to_string());
+        values.push(Box::new(phase.clone()));
+    }
+    for tag in &filters.tags {
+        clauses.push(
+            "EXISTS (SELECT 1 FROM session_tags st JOIN tags t ON t.id = st.tag_id
+                     WHERE st.session_id = sessions.id AND t.name = ?)".to_string(),
+        );
+        values.push(Box::new(normalize_tag(tag)?));
+    }
+
+    let sql = format!(
+        "SELECT {} FROM sessions WHERE {} ORDER BY pinned DESC, {}",
+        SESSION_COLUMNS,
+        clauses.join(" AND "),
+        sort.order_by()
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let params: Vec<&dyn rusqlite::ToSql> = values.iter().map(|v| v.as_ref()).collect();
+    let rows = stmt
+        .query_map(params.as_slice(), row_to_session)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
 pub fn get_session(conn: &Connection, id: &str) -> Result<SessionRecord, String> {
     let sql = format!("SELECT {} FROM sessions WHERE id = ?1", SESSION_COLUMNS);
     conn.query_row(&sql, params![id], row_to_session)
@@ -64,9 +175,8 @@ pub fn create_session(
     initial_prompt: &str,
     provider: &str,
 ) -> Result<SessionRecord, String> {
-    let valid_providers = ["copilot", "claude"];
-    if !valid_providers.contains(&provider) {
-        return Err(format!("Invalid provider: {}. Must be one of: {:?}", provider, valid_providers));
+    if !VALID_PROVIDERS.contains(&provider) {
+        return Err(format!("Invalid provider: {}. Must be one of: {:?}", provider, VALID_PROVIDERS));
     }
     let id = uuid::Uuid::new_v4().to_string();
     let now = crate::comments::now();
@@ -95,30 +205,63 @@ pub fn update_session_acp_id(
     Ok(())
 }
 
-pub fn update_phase(conn: &Connection, id: &str, phase: &str) -> Result<(), String> {
-    let valid = ["idle", "planning", "reviewing", "executing", "done"];
-    if !valid.contains(&phase) {
-        return Err(format!("Invalid phase: {}. Must be one of: {:?}", phase, valid));
-    }
+pub fn update_plan_file_path(conn: &Connection, id: &str, plan_file_path: &str) -> Result<(), String> {
     let now = crate::comments::now();
     conn.execute(
-        "UPDATE sessions SET phase = ?1, updated_at = ?2 WHERE id = ?3",
-        params![phase, now, id],
+        "UPDATE sessions SET plan_file_path = ?1, updated_at = ?2 WHERE id = ?3",
+        params![plan_file_path, now, id],
     )
-    .map_err(|e| format!("Update phase error: {}", e))?;
+    .map_err(|e| format!("Update plan_file_path error: {}", e))?;
     Ok(())
 }
 
-pub fn update_plan_file_path(conn: &Connection, id: &str, plan_file_path: &str) -> Result<(), String> {
+pub fn update_initial_prompt(conn: &Connection, id: &str, initial_prompt: &str) -> Result<(), String> {
     let now = crate::comments::now();
     conn.execute(
-        "UPDATE sessions SET plan_file_path = ?1, updated_at = ?2 WHERE id = ?3",
-        params![plan_file_path, now, id],
+        "UPDATE sessions SET initial_prompt = ?1, updated_at = ?2 WHERE id = ?3",
+        params![initial_prompt, now, id],
     )
-    .map_err(|e| format!("Update plan_file_path error: {}", e))?;
+    .map_err(|e| format!("Update initial_prompt error: {}", e))?;
     Ok(())
 }
 
+pub fn update_worktree(
+    conn: &Connection,
+    id: &str,
+    worktree_path: Option<&str>,
+    worktree_branch: Option<&str>,
+) -> Result<(), String> {
+    let now = crate::comments::now();
+    conn.execute(
+        "UPDATE sessions SET worktree_path = ?1, worktree_branch = ?2, updated_at = ?3 WHERE id = ?4",
+        params![worktree_path, worktree_branch, now, id],
+    )
+    .map_err(|e| format!("Update worktree error: {}", e))?;
+    Ok(())
+}
+
+/// Stores context to prepend to the next prompt sent to a fresh agent session
+/// (e.g. the replayed history of a fork). `None` clears it.
+pub fn set_context_seed(conn: &Connection, id: &str, seed: Option<&str>) -> Result<(), String> {
+    conn.execute(
+        "UPDATE sessions SET context_seed = ?1 WHERE id = ?2",
+        params![seed, id],
+    )
+    .map_err(|e| format!("Update context seed error: {}", e))?;
+    Ok(())
+}
+
+/// Returns and clears the session's pending context seed.
+pub fn take_context_seed(conn: &Connection, id: &str) -> Result<Option<String>, String> {
+    let seed: Option<String> = conn
+        .query_row("SELECT context_seed FROM sessions WHERE id = ?1", params![id], |row| row.get(0))
+        .map_err(|e| format!("Session not found: {}", e))?;
+    if seed.is_some() {
+        set_context_seed(conn, id, None)?;
+    }
+    Ok(seed)
+}
+
 pub fn update_acp_preferences(conn: &Connection, id: &str, json: &str) -> Result<(), String> {
     let now = crate::comments::now();
     conn.execute(
@@ -152,10 +295,95 @@ pub fn set_workspace_acp_defaults(conn: &Connection, workspace_path: &str, json:
     Ok(())
 }
 
+pub fn set_pinned(conn: &Connection, id: &str, pinned: bool) -> Result<(), String> {
+    conn.execute(
+        "UPDATE sessions SET pinned = ?1 WHERE id = ?2",
+        params![pinned as i32, id],
+    )
+    .map_err(|e| format!("Update pinned error: {}", e))?;
+    Ok(())
+}
+
+pub fn set_archived(conn: &Connection, id: &str, archived: bool) -> Result<(), String> {
+    conn.execute(
+        "UPDATE sessions SET archived = ?1 WHERE id = ?2",
+        params![archived as i32, id],
+    )
+    .map_err(|e| format!("Update archived error: {}", e))?;
+    Ok(())
+}
+
+/// Tags are trimmed and case-insensitive (stored lowercase).
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+    Ok(tag)
+}
+
+pub fn add_tag(conn: &Connection, id: &str, tag: &str) -> Result<(), String> {
+    let tag = normalize_tag(tag)?;
+    conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])
+        .map_err(|e| format!("Insert tag error: {}", e))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO session_tags (session_id, tag_id)
+         SELECT ?1, id FROM tags WHERE name = ?2",
+        params![id, tag],
+    )
+    .map_err(|e| format!("Tag session error: {}", e))?;
+    Ok(())
+}
+
+/// Removes the tag from the session; tags no session uses any more are dropped.
+pub fn remove_tag(conn: &Connection, id: &str, tag: &str) -> Result<(), String> {
+    let tag = normalize_tag(tag)?;
+    conn.execute(
+        "DELETE FROM session_tags WHERE session_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+        params![id, tag],
+    )
+    .map_err(|e| format!("Untag session error: {}", e))?;
+    prune_tags(conn)
+}
+
+fn prune_tags(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM session_tags)",
+        [],
+    )
+    .map_err(|e| format!("Prune tags error: {}", e))?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagCount {
+    pub name: String,
+    /// Non-archived sessions of the workspace carrying the tag
+    pub count: i64,
+}
+
+pub fn list_tags(conn: &Connection, workspace_id: &str) -> Result<Vec<TagCount>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name, COUNT(*) FROM tags t
+             JOIN session_tags st ON st.tag_id = t.id
+             JOIN sessions s ON s.id = st.session_id
+             WHERE s.workspace_id = ?1 AND s.archived = 0
+             GROUP BY t.id ORDER BY t.name",
+        )
+        .map_err(|e| format!("Prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![workspace_id], |row| Ok(TagCount { name: row.get(0)?, count: row.get(1)? }))
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows)
+}
+
 pub fn delete_session(conn: &Connection, id: &str) -> Result<(), String> {
     conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])
         .map_err(|e| format!("Delete error: {}", e))?;
-    Ok(())
+    prune_tags(conn)
 }
 
 pub fn count_sessions_batch(conn: &Connection, workspace_ids: &[String]) -> Result<Vec<(String, i64)>, String> {
@@ -169,7 +397,7 @@ pub fn count_sessions_batch(conn: &Connection, workspace_ids: &[String]) -> Resu
     for chunk in workspace_ids.chunks(MAX_VARS) {
         let placeholders: Vec<String> = (1..=chunk.len()).map(|i| format!("?{}", i)).collect();
         let sql = format!(
-            "SELECT workspace_id, COUNT(*) FROM sessions WHERE workspace_id IN ({}) GROUP BY workspace_id HAVING COUNT(*) > 0",
+            "SELECT workspace_id, COUNT(*) FROM sessions WHERE workspace_id IN ({}) AND archived = 0 GROUP BY workspace_id HAVING COUNT(*) > 0",
             placeholders.join(", ")
         );
         let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare error: {}", e))?;
@@ -235,6 +463,14 @@ pub fn upsert_workspace(conn: &Connection, id: &str, path: &str, display_name: &
     ).map_err(|e| format!("Fetch workspace error: {}", e))
 }
 
+pub fn get_workspace(conn: &Connection, id: &str) -> Result<WorkspaceRecord, String> {
+    conn.query_row(
+        "SELECT id, path, display_name, workspace_type, last_accessed, created_at FROM workspaces WHERE id = ?1",
+        params![id],
+        row_to_workspace,
+    ).map_err(|e| format!("Workspace not found: {}", e))
+}
+
 pub fn touch_workspace(conn: &Connection, id: &str) -> Result<(), String> {
     let now = crate::comments::now();
     conn.execute("UPDATE workspaces SET last_accessed = ?1 WHERE id = ?2", params![now, id])
@@ -271,17 +507,104 @@ pub fn session_list(
     list_sessions(&conn, &workspace_id)
 }
 
+#[tauri::command]
+pub fn session_list_filtered(
+    workspace_id: String,
+    filters: Option<SessionFilters>,
+    sort: Option<SessionSort>,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<SessionRecord>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_sessions_filtered(&conn, &workspace_id, &filters.unwrap_or_default(), sort.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn session_set_pinned(
+    id: String,
+    pinned: bool,
+    db: tauri::State<CommentsDb>,
+) -> Result<SessionRecord, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_pinned(&conn, &id, pinned)?;
+    get_session(&conn, &id)
+}
+
+#[tauri::command]
+pub fn session_set_archived(
+    id: String,
+    archived: bool,
+    db: tauri::State<CommentsDb>,
+) -> Result<SessionRecord, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_archived(&conn, &id, archived)?;
+    get_session(&conn, &id)
+}
+
+#[tauri::command]
+pub fn session_add_tag(
+    id: String,
+    tag: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<SessionRecord, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_tag(&conn, &id, &tag)?;
+    get_session(&conn, &id)
+}
+
+#[tauri::command]
+pub fn session_remove_tag(
+    id: String,
+    tag: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<SessionRecord, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    remove_tag(&conn, &id, &tag)?;
+    get_session(&conn, &id)
+}
+
+#[tauri::command]
+pub fn tag_list(
+    workspace_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<TagCount>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_tags(&conn, &workspace_id)
+}
+
 #[tauri::command]
 pub fn session_create(
     workspace_id: String,
     name: String,
     initial_prompt: String,
     provider: Option<String>,
+    isolate_worktree: Option<bool>,
     db: tauri::State<CommentsDb>,
 ) -> Result<SessionRecord, String> {
+    let (session, workspace) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let p = provider.as_deref().unwrap_or("copilot");
+        let session = create_session(&conn, &workspace_id, &name, &initial_prompt, p)?;
+        if !isolate_worktree.unwrap_or(false) {
+            return Ok(session);
+        }
+        let workspace = get_workspace(&conn, &workspace_id)?;
+        (session, workspace)
+    };
+
+    // git can take a while on large repositories; don't hold the DB meanwhile
+    let created = crate::worktree::create_worktree(&workspace.path, &session.id, &name);
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let p = provider.as_deref().unwrap_or("copilot");
-    create_session(&conn, &workspace_id, &name, &initial_prompt, p)
+    match created {
+        Ok((path, branch)) => {
+            update_worktree(&conn, &session.id, Some(&path), Some(&branch))?;
+            get_session(&conn, &session.id)
+        }
+        Err(e) => {
+            // Don't leave a half-created session behind
+            let _ = delete_session(&conn, &session.id);
+            Err(e)
+        }
+    }
 }
 
 #[tauri::command]
@@ -303,16 +626,6 @@ pub fn session_update_acp_id(
     update_session_acp_id(&conn, &id, &acp_session_id)
 }
 
-#[tauri::command]
-pub fn session_update_phase(
-    id: String,
-    phase: String,
-    db: tauri::State<CommentsDb>,
-) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    update_phase(&conn, &id, &phase)
-}
-
 #[tauri::command]
 pub fn session_update_plan_file_path(
     id: String,
@@ -329,9 +642,24 @@ pub fn session_delete(
     db: tauri::State<CommentsDb>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
+    let worktree = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        get_session(&conn, &id).ok().and_then(|session| {
+            let workspace = get_workspace(&conn, &session.workspace_id).ok()?;
+            Some((workspace.path, session.worktree_path?, session.worktree_branch?))
+        })
+    };
+
+    // git can take a while on large repositories; don't hold the DB meanwhile
+    if let Some((repo, path, branch)) = worktree {
+        if let Err(e) = crate::worktree::remove_worktree(&repo, &path, &branch) {
+            eprintln!("[worktree] Failed to remove worktree for session {}: {}", id, e);
+        }
+    }
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     // CASCADE handles messages deletion automatically
     delete_session(&conn, &id)?;
+    crate::plan_history::prune_blobs(&conn)?;
     let app_data = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
     crate::plan_file::delete_plan(&app_data, &id)
@@ -366,6 +694,7 @@ pub fn forget_workspace_data(
             // Delete sessions (CASCADE deletes messages)
             conn.execute("DELETE FROM sessions WHERE workspace_id = ?1", params![workspace_id])
                 .map_err(|e| format!("Delete sessions error: {}", e))?;
+            crate::plan_history::prune_blobs(&conn)?;
 
             // Delete workspace ACP defaults
             conn.execute("DELETE FROM workspace_acp_defaults WHERE workspace_path = ?1", params![workspace_path])
@@ -377,6 +706,9 @@ pub fn forget_workspace_data(
             for id in &session_ids {
                 let _ = crate::plan_file::delete_plan(&app_data, id);
             }
+
+            // Drop the workspace's env profile (variables and secrets)
+            crate::env_profiles::remove_workspace_profile(&app, &workspace_path)?;
         }
         "file" => {
             crate::comments::delete_comments_for_file(&conn, &workspace_path)?;
@@ -0,0 +1,469 @@
+//! Reviewable diffs from agent tool calls.
+//!
+//! ACP `tool_call` / `tool_call_update` payloads can carry `diff` content items
+//! (`path`, `oldText`, `newText`). Each one is stored in `tool_call_diffs`,
+//! keyed by the tool message it belongs to. By the time a diff arrives the
+//! agent has already written `newText`, so accepting only records the
+//! decision while rejecting writes the old text back. Decisions can be made
+//! for the whole file or per hunk; the file on disk always reflects the old
+//! side of rejected hunks and the new side of everything else. Hunks are
+//! computed once, when a diff is recorded, and stored with it.
+//!
+//! Claude's editing tools carry no diff items, so once one succeeds each file
+//! it touched is diffed against the content its checkpoint saved before the
+//! call.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Above this many line pairs the changed region is reported as a single hunk
+/// instead of running the quadratic line diff.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffHunk {
+    pub index: usize,
+    /// Zero-based first line of the hunk in the old and new text
+    pub old_start: usize,
+    pub new_start: usize,
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
+    /// `pending`, `accepted` or `rejected`
+    pub status: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolCallDiff {
+    pub id: String,
+    pub message_id: String,
+    pub session_id: String,
+    pub turn_message_id: Option<String>,
+    pub tool_call_id: String,
+    pub path: String,
+    /// `None` when the tool call created the file
+    pub old_text: Option<String>,
+    pub new_text: String,
+    /// `pending`, `accepted`, `rejected` or `partial`
+    pub status: String,
+    pub hunks: Vec<DiffHunk>,
+    pub created_at: i64,
+}
+
+// --- Line diff ---
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Hunk {
+    pub old_start: usize,
+    pub old_end: usize,
+    pub new_start: usize,
+    pub new_end: usize,
+}
+
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Contiguous changed regions between `old` and `new`, by line.
+pub(crate) fn compute_hunks(old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    if a.is_empty() && b.is_empty() {
+        return Vec::new();
+    }
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        return vec![Hunk { old_start: prefix, old_end: prefix + a.len(), new_start: prefix, new_end: prefix + b.len() }];
+    }
+
+    // LCS table over the trimmed middle, then walk it to find changed runs
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if a[i] == b[j] {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            if let Some(h) = current.take() {
+                hunks.push(h);
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let h = current.get_or_insert(Hunk { old_start: prefix + i, old_end: prefix + i, new_start: prefix + j, new_end: prefix + j });
+        if j < m && (i == n || lcs[i * (m + 1) + j + 1] >= lcs[(i + 1) * (m + 1) + j]) {
+            j += 1;
+            h.new_end = prefix + j;
+        } else {
+            i += 1;
+            h.old_end = prefix + i;
+        }
+    }
+    if let Some(h) = current {
+        hunks.push(h);
+    }
+    hunks
+}
+
+/// Builds the file content for the given per-hunk decisions: rejected hunks
+/// take the old lines, all others the new lines.
+fn compose(old: &[&str], new: &[&str], hunks: &[Hunk], decisions: &[String]) -> String {
+    let mut out = String::new();
+    let mut old_pos = 0;
+    for (hunk, decision) in hunks.iter().zip(decisions) {
+        out.extend(old[old_pos..hunk.old_start].iter().copied());
+        if decision == "rejected" {
+            out.extend(old[hunk.old_start..hunk.old_end].iter().copied());
+        } else {
+            out.extend(new[hunk.new_start..hunk.new_end].iter().copied());
+        }
+        old_pos = hunk.old_end;
+    }
+    out.extend(old[old_pos..].iter().copied());
+    out
+}
+
+fn overall_status(decisions: &[String]) -> &'static str {
+    if decisions.iter().any(|d| d == "pending") {
+        "pending"
+    } else if decisions.iter().all(|d| d == "accepted") {
+        "accepted"
+    } else if decisions.iter().all(|d| d == "rejected") {
+        "rejected"
+    } else {
+        "partial"
+    }
+}
+
+// --- Storage ---
+
+/// A stored diff with its hunk ranges and decisions, both JSON arrays.
+type DiffRow = (ToolCallDiff, String, String);
+
+fn row_to_diff(row: &rusqlite::Row) -> rusqlite::Result<DiffRow> {
+    Ok((
+        ToolCallDiff {
+            id: row.get(0)?,
+            message_id: row.get(1)?,
+            session_id: row.get(2)?,
+            turn_message_id: row.get(3)?,
+            tool_call_id: row.get(4)?,
+            path: row.get(5)?,
+            old_text: row.get(6)?,
+            new_text: row.get(7)?,
+            status: row.get(8)?,
+            hunks: Vec::new(),
+            created_at: row.get(11)?,
+        },
+        row.get(9)?,
+        row.get(10)?,
+    ))
+}
+
+const DIFF_COLUMNS: &str = "id, message_id, session_id, turn_message_id, tool_call_id, path, old_text, new_text, status, hunks, hunk_decisions, created_at";
+
+/// Fills in the stored hunks with their decisions (a JSON array of statuses).
+fn with_hunks((mut diff, hunks_json, decisions_json): DiffRow) -> ToolCallDiff {
+    let old_text = diff.old_text.clone().unwrap_or_default();
+    let (old, new) = (split_lines(&old_text), split_lines(&diff.new_text));
+    let hunks: Vec<Hunk> = serde_json::from_str(&hunks_json).unwrap_or_default();
+    let decisions: Vec<String> = serde_json::from_str(&decisions_json).unwrap_or_default();
+    diff.hunks = hunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, h)| DiffHunk {
+            index,
+            old_start: h.old_start,
+            new_start: h.new_start,
+            old_lines: old[h.old_start..h.old_end].iter().map(|s| s.to_string()).collect(),
+            new_lines: new[h.new_start..h.new_end].iter().map(|s| s.to_string()).collect(),
+            status: decisions.get(index).cloned().unwrap_or_else(|| "pending".to_string()),
+        })
+        .collect();
+    diff
+}
+
+/// Stores the `diff` content items of a tool call payload against the tool
+/// message with that `toolCallId`. Re-sent diffs replace the stored text while
+/// still undecided.
+pub fn record_diffs(conn: &Connection, session_id: &str, payload: &serde_json::Value) -> Result<usize, String> {
+    let Some(tool_call_id) = payload.get("toolCallId").and_then(|v| v.as_str()) else { return Ok(0) };
+    let Some(items) = payload.get("content").and_then(|c| c.as_array()) else { return Ok(0) };
+    let diffs: Vec<(&str, Option<&str>, &str)> = items
+        .iter()
+        .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("diff"))
+        .filter_map(|item| {
+            let path = item.get("path")?.as_str()?;
+            let new_text = item.get("newText")?.as_str()?;
+            Some((path, item.get("oldText").and_then(|t| t.as_str()), new_text))
+        })
+        .collect();
+    insert_diffs(conn, session_id, tool_call_id, &diffs)
+}
+
+/// Stores the diffs of a successful Claude editing tool call from the
+/// before-content of its checkpoint and the files as they are now.
+pub fn record_checkpoint_diffs(conn: &Connection, session_id: &str, tool_call_id: &str) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT f.path, f.before_content
+             FROM checkpoints c JOIN checkpoint_files f ON f.checkpoint_id = c.id
+             WHERE c.session_id = ?1 AND c.tool_call_id = ?2
+             ORDER BY f.path",
+        )
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let files = stmt
+        .query_map(params![session_id, tool_call_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<Vec<u8>>>(1)?))
+        })
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+
+    // Deleted and non-UTF-8 files can't be reviewed as text diffs
+    let texts: Vec<(String, Option<String>, String)> = files
+        .into_iter()
+        .filter_map(|(path, before)| {
+            let new_text = std::fs::read_to_string(&path).ok()?;
+            let old_text = match before {
+                Some(bytes) => Some(String::from_utf8(bytes).ok()?),
+                None => None,
+            };
+            (old_text.as_deref() != Some(new_text.as_str())).then_some((path, old_text, new_text))
+        })
+        .collect();
+    let diffs: Vec<(&str, Option<&str>, &str)> = texts
+        .iter()
+        .map(|(path, old_text, new_text)| (path.as_str(), old_text.as_deref(), new_text.as_str()))
+        .collect();
+    insert_diffs(conn, session_id, tool_call_id, &diffs)
+}
+
+fn insert_diffs(
+    conn: &Connection,
+    session_id: &str,
+    tool_call_id: &str,
+    diffs: &[(&str, Option<&str>, &str)],
+) -> Result<usize, String> {
+    if diffs.is_empty() {
+        return Ok(0);
+    }
+
+    let message_id: String = conn
+        .query_row(
+            "SELECT id FROM messages WHERE session_id = ?1 AND tool_call_id = ?2
+             ORDER BY seq DESC LIMIT 1",
+            params![session_id, tool_call_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Tool message not found for {}: {}", tool_call_id, e))?;
+    let turn = crate::checkpoints::current_turn(conn, session_id);
+    let now = crate::comments::now();
+
+    for (path, old_text, new_text) in diffs {
+        let hunks = compute_hunks(&split_lines(old_text.unwrap_or_default()), &split_lines(new_text));
+        let hunks_json = serde_json::to_string(&hunks).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO tool_call_diffs
+                (id, message_id, session_id, turn_message_id, tool_call_id, path, old_text, new_text, status, hunks, hunk_decisions, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'pending', ?9, '[]', ?10)
+             ON CONFLICT(message_id, path) DO UPDATE SET old_text = excluded.old_text, new_text = excluded.new_text, hunks = excluded.hunks
+             WHERE tool_call_diffs.status = 'pending' AND tool_call_diffs.hunk_decisions = '[]'",
+            params![uuid::Uuid::new_v4().to_string(), message_id, session_id, turn, tool_call_id, path, old_text, new_text, hunks_json, now],
+        )
+        .map_err(|e| format!("Insert tool_call_diff error: {}", e))?;
+    }
+    Ok(diffs.len())
+}
+
+pub fn get_diff(conn: &Connection, id: &str) -> Result<ToolCallDiff, String> {
+    let sql = format!("SELECT {} FROM tool_call_diffs WHERE id = ?1", DIFF_COLUMNS);
+    conn.query_row(&sql, params![id], row_to_diff)
+        .map(with_hunks)
+        .map_err(|e| format!("Diff not found: {}", e))
+}
+
+/// Undecided diffs for a turn (the latest turn when `turn_message_id` is None).
+pub fn list_pending(conn: &Connection, session_id: &str, turn_message_id: Option<&str>) -> Result<Vec<ToolCallDiff>, String> {
+    let turn = match turn_message_id {
+        Some(id) => Some(id.to_string()),
+        None => crate::checkpoints::current_turn(conn, session_id),
+    };
+    let sql = format!(
+        "SELECT {} FROM tool_call_diffs
+         WHERE session_id = ?1 AND turn_message_id IS ?2 AND status = 'pending'
+         ORDER BY created_at ASC, rowid ASC",
+        DIFF_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("Query prepare error: {}", e))?;
+    let rows = stmt
+        .query_map(params![session_id, turn], row_to_diff)
+        .map_err(|e| format!("Query error: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(rows.into_iter().map(with_hunks).collect())
+}
+
+/// Rewrites `current` from the `from` state to the `to` state. Agents may
+/// send whole files or just the edited fragment, so fall back to replacing a
+/// unique occurrence of `from`.
+fn transform(current: &str, from: &str, to: &str) -> Option<String> {
+    if current == from {
+        return Some(to.to_string());
+    }
+    if !from.is_empty() && current.matches(from).count() == 1 {
+        return Some(current.replacen(from, to, 1));
+    }
+    None
+}
+
+/// Applies `decision` to one hunk (or all undecided hunks when `hunk_index`
+/// is None) and brings the file on disk in line with the new decisions.
+pub fn decide(
+    conn: &Connection,
+    id: &str,
+    hunk_index: Option<usize>,
+    decision: &str,
+) -> Result<ToolCallDiff, String> {
+    let diff = get_diff(conn, id)?;
+    let mut decisions: Vec<String> = diff.hunks.iter().map(|h| h.status.clone()).collect();
+    match hunk_index {
+        Some(i) if i >= decisions.len() => return Err(format!("Hunk {} out of range", i)),
+        Some(i) => decisions[i] = decision.to_string(),
+        None => {
+            for d in decisions.iter_mut().filter(|d| *d == "pending") {
+                *d = decision.to_string();
+            }
+        }
+    }
+
+    let old_text = diff.old_text.clone().unwrap_or_default();
+    let (old, new) = (split_lines(&old_text), split_lines(&diff.new_text));
+    let hunks: Vec<Hunk> = diff
+        .hunks
+        .iter()
+        .map(|h| Hunk {
+            old_start: h.old_start,
+            old_end: h.old_start + h.old_lines.len(),
+            new_start: h.new_start,
+            new_end: h.new_start + h.new_lines.len(),
+        })
+        .collect();
+    let previous: Vec<String> = diff.hunks.iter().map(|h| h.status.clone()).collect();
+    let before = compose(&old, &new, &hunks, &previous);
+    let after = compose(&old, &new, &hunks, &decisions);
+
+    if before != after {
+        let root = crate::checkpoints::session_root(conn, &diff.session_id)
+            .ok_or_else(|| format!("Session not found: {}", diff.session_id))?;
+        let path = if Path::new(&diff.path).is_absolute() {
+            Path::new(&diff.path).to_path_buf()
+        } else {
+            root.join(&diff.path)
+        };
+        let all_rejected = decisions.iter().all(|d| d == "rejected");
+        if diff.old_text.is_none() && all_rejected {
+            // The tool call created the file; rejecting it removes the file
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            }
+        } else {
+            let current = std::fs::read_to_string(&path).unwrap_or_default();
+            let updated = transform(&current, &before, &after).ok_or_else(|| {
+                format!("{} changed since the agent edited it; resolve it manually", diff.path)
+            })?;
+            std::fs::write(&path, updated)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+    }
+
+    let decisions_json = serde_json::to_string(&decisions).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE tool_call_diffs SET hunk_decisions = ?1, status = ?2 WHERE id = ?3",
+        params![decisions_json, overall_status(&decisions), id],
+    )
+    .map_err(|e| format!("Update tool_call_diff error: {}", e))?;
+    get_diff(conn, id)
+}
+
+/// Connection hook for `tool_call` / `tool_call_update` payloads.
+pub fn on_tool_call_payload(app: &AppHandle, session_id: &str, payload: &serde_json::Value) {
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let Ok(conn) = db.0.lock() else { return };
+    match record_diffs(&conn, session_id, payload) {
+        Ok(0) => {}
+        Ok(n) => eprintln!("[tool_diffs] session={} recorded {} diff(s)", session_id, n),
+        Err(e) => eprintln!("[tool_diffs] {}", e),
+    }
+}
+
+/// Connection hook for a Claude `tool_result`; only successful calls changed
+/// anything worth reviewing.
+pub fn on_claude_tool_result(app: &AppHandle, session_id: &str, tool_use_id: &str, is_error: bool) {
+    if is_error {
+        return;
+    }
+    let Some(db) = app.try_state::<CommentsDb>() else { return };
+    let Ok(conn) = db.0.lock() else { return };
+    match record_checkpoint_diffs(&conn, session_id, tool_use_id) {
+        Ok(0) => {}
+        Ok(n) => eprintln!("[tool_diffs] session={} recorded {} diff(s)", session_id, n),
+        Err(e) => eprintln!("[tool_diffs] {}", e),
+    }
+}
+
+// --- Tauri commands ---
+
+use crate::comments::CommentsDb;
+use tauri::{AppHandle, Manager};
+
+#[tauri::command]
+pub fn tool_diffs_list_pending(
+    session_id: String,
+    turn_message_id: Option<String>,
+    db: tauri::State<CommentsDb>,
+) -> Result<Vec<ToolCallDiff>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    list_pending(&conn, &session_id, turn_message_id.as_deref())
+}
+
+/// Accepts a whole file (`hunk_index` omitted) or a single hunk.
+#[tauri::command]
+pub fn tool_diff_accept(
+    id: String,
+    hunk_index: Option<usize>,
+    db: tauri::State<CommentsDb>,
+) -> Result<ToolCallDiff, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    decide(&conn, &id, hunk_index, "accepted")
+}
+
+/// Rejects a whole file (`hunk_index` omitted) or a single hunk, writing the
+/// old text back to disk.
+#[tauri::command]
+pub fn tool_diff_reject(
+    id: String,
+    hunk_index: Option<usize>,
+    db: tauri::State<CommentsDb>,
+) -> Result<ToolCallDiff, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    decide(&conn, &id, hunk_index, "rejected")
+}
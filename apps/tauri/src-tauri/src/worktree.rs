@@ -0,0 +1,259 @@
+//! Git worktree isolation for agent sessions.
+//!
+//! An isolated session gets its own worktree under
+//! `<workspace>/.arandu/worktrees/<id>` on a branch `arandu/<slug>-<id>`, so
+//! several sessions can execute plans in the same repository without
+//! overwriting each other's edits. When the session is done its changes can be
+//! reviewed as a diff, merged back into the workspace's current branch, or
+//! discarded.
+
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+const WORKTREES_DIR: &str = ".arandu/worktrees";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WorktreeDiff {
+    pub branch: String,
+    /// Commit the worktree branched from (merge base with the workspace HEAD)
+    pub base: String,
+    /// `git diff --stat` summary
+    pub stat: String,
+    /// Unified diff of all committed and uncommitted changes, including new files
+    pub patch: String,
+}
+
+/// Runs git in `cwd` and returns trimmed stdout, or stderr as the error.
+fn git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    git_with_index(cwd, None, args)
+}
+
+/// Like [`git`], optionally against another index file than the worktree's.
+fn git_with_index(cwd: &Path, index: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(cwd);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(format!("git {} failed: {}", args.join(" "), stderr))
+    }
+}
+
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug: Vec<&str> = slug.split('-').filter(|s| !s.is_empty()).collect();
+    let slug = slug.join("-");
+    if slug.is_empty() {
+        "session".to_string()
+    } else {
+        slug.chars().take(40).collect::<String>().trim_end_matches('-').to_string()
+    }
+}
+
+/// Keeps the workspace's `.arandu/` out of `git status` in the main checkout
+/// without touching the tracked `.gitignore`. The workspace may be a
+/// subdirectory of the repository, so the pattern is anchored at its path
+/// relative to the repository root.
+fn ensure_excluded(workspace: &Path) -> Result<(), String> {
+    let common_dir = git(workspace, &["rev-parse", "--git-common-dir"])?;
+    let prefix = git(workspace, &["rev-parse", "--show-prefix"])?;
+    let pattern = format!("/{}.arandu/", prefix);
+    let info_dir = workspace.join(common_dir).join("info");
+    std::fs::create_dir_all(&info_dir)
+        .map_err(|e| format!("Failed to create git info dir: {}", e))?;
+    let exclude = info_dir.join("exclude");
+    let content = std::fs::read_to_string(&exclude).unwrap_or_default();
+    if content.lines().any(|l| l.trim() == pattern) {
+        return Ok(());
+    }
+    let mut updated = content;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(&pattern);
+    updated.push('\n');
+    std::fs::write(&exclude, updated)
+        .map_err(|e| format!("Failed to update git exclude: {}", e))
+}
+
+/// Creates a worktree and branch for the session. Returns `(path, branch)`.
+pub fn create_worktree(workspace_path: &str, session_id: &str, session_name: &str) -> Result<(String, String), String> {
+    let workspace = Path::new(workspace_path);
+    git(workspace, &["rev-parse", "--show-toplevel"])
+        .map_err(|_| format!("{} is not a git repository", workspace_path))?;
+    git(workspace, &["rev-parse", "--verify", "HEAD"])
+        .map_err(|_| "Repository has no commits to branch from".to_string())?;
+
+    let short_id: String = session_id.chars().filter(|c| *c != '-').take(8).collect();
+    let branch = format!("arandu/{}-{}", slugify(session_name), short_id);
+    let path = workspace.join(WORKTREES_DIR).join(&short_id);
+    let path_str = path.to_string_lossy().to_string();
+
+    ensure_excluded(workspace)?;
+    git(workspace, &["worktree", "add", "-b", &branch, &path_str, "HEAD"])?;
+    eprintln!("[worktree] Created {} on branch {}", path_str, branch);
+
+    Ok((path_str, branch))
+}
+
+fn merge_base(workspace: &Path, branch: &str) -> Result<String, String> {
+    git(workspace, &["merge-base", "HEAD", branch])
+}
+
+/// Returns `(stat, patch)` of the worktree against `base`, staged into `index`.
+fn diff_with_index(worktree: &Path, index: &Path, base: &str) -> Result<(String, String), String> {
+    let index = Some(index);
+    git_with_index(worktree, index, &["read-tree", "HEAD"])?;
+    git_with_index(worktree, index, &["add", "--all"])?;
+    let stat = git_with_index(worktree, index, &["diff", "--cached", "--stat", base])?;
+    let patch = git_with_index(worktree, index, &["diff", "--cached", base])?;
+    Ok((stat, patch))
+}
+
+pub fn diff_worktree(workspace_path: &str, worktree_path: &str, branch: &str) -> Result<WorktreeDiff, String> {
+    let workspace = Path::new(workspace_path);
+    let worktree = Path::new(worktree_path);
+    let base = merge_base(workspace, branch)?;
+
+    // Stage everything, new files included, into a throwaway index so the
+    // worktree's own index is left exactly as the agent left it
+    let index = std::env::temp_dir().join(format!(
+        "arandu-diff-{}-{}.index",
+        std::process::id(),
+        branch.replace('/', "-")
+    ));
+    let result = diff_with_index(worktree, &index, &base);
+    let _ = std::fs::remove_file(&index);
+    let (stat, patch) = result?;
+
+    Ok(WorktreeDiff { branch: branch.to_string(), base, stat, patch })
+}
+
+/// Removes the worktree directory and deletes its branch.
+pub fn remove_worktree(workspace_path: &str, worktree_path: &str, branch: &str) -> Result<(), String> {
+    let workspace = Path::new(workspace_path);
+    if Path::new(worktree_path).exists() {
+        git(workspace, &["worktree", "remove", "--force", worktree_path])?;
+    } else {
+        git(workspace, &["worktree", "prune"])?;
+    }
+    // The branch may already be gone (e.g. deleted by hand)
+    if git(workspace, &["rev-parse", "--verify", &format!("refs/heads/{}", branch)]).is_ok() {
+        git(workspace, &["branch", "-D", branch])?;
+    }
+    eprintln!("[worktree] Removed {} ({})", worktree_path, branch);
+    Ok(())
+}
+
+/// Commits pending worktree changes, merges the branch into the workspace's
+/// current branch and removes the worktree. A conflicting merge is aborted and
+/// the worktree is kept so nothing is lost.
+pub fn merge_worktree(workspace_path: &str, worktree_path: &str, branch: &str, session_name: &str) -> Result<(), String> {
+    let workspace = Path::new(workspace_path);
+    let worktree = Path::new(worktree_path);
+
+    git(worktree, &["add", "--all"])?;
+    if !git(worktree, &["status", "--porcelain"])?.is_empty() {
+        git(worktree, &["commit", "-m", &format!("{} (agent session)", session_name)])?;
+    }
+
+    let message = format!("Merge agent session \"{}\"", session_name);
+    if let Err(e) = git(workspace, &["merge", "--no-ff", "-m", &message, branch]) {
+        let _ = git(workspace, &["merge", "--abort"]);
+        return Err(format!("Merge failed, worktree kept for manual resolution: {}", e));
+    }
+
+    remove_worktree(workspace_path, worktree_path, branch)
+}
+
+/// Working directory for a session's agent: its worktree when it has one,
+/// otherwise the workspace path it was connected with.
+pub fn session_cwd(app: &AppHandle, session_id: &str, workspace_path: String) -> String {
+    let Some(db) = app.try_state::<CommentsDb>() else { return workspace_path };
+    let Ok(conn) = db.0.lock() else { return workspace_path };
+    match crate::sessions::get_session(&conn, session_id) {
+        Ok(session) => session
+            .worktree_path
+            .filter(|p| Path::new(p).is_dir())
+            .unwrap_or(workspace_path),
+        Err(_) => workspace_path,
+    }
+}
+
+// --- Tauri commands ---
+
+use crate::acp::commands::AcpSessionStore;
+use crate::comments::CommentsDb;
+use crate::sessions::SessionRecord;
+use tauri::{AppHandle, Manager};
+
+/// Loads an isolated session with its workspace path and worktree details.
+fn load_isolated(db: &CommentsDb, session_id: &str) -> Result<(SessionRecord, String, String, String), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let session = crate::sessions::get_session(&conn, session_id)?;
+    let (Some(path), Some(branch)) = (session.worktree_path.clone(), session.worktree_branch.clone()) else {
+        return Err("Session has no worktree".to_string());
+    };
+    let workspace = crate::sessions::get_workspace(&conn, &session.workspace_id)?;
+    Ok((session, workspace.path, path, branch))
+}
+
+fn require_done(session: &SessionRecord) -> Result<(), String> {
+    if session.phase != "done" {
+        return Err(format!("Session must be done to finish its worktree (phase: {})", session.phase));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn session_worktree_diff(
+    session_id: String,
+    db: tauri::State<CommentsDb>,
+) -> Result<WorktreeDiff, String> {
+    let (_, workspace_path, path, branch) = load_isolated(&db, &session_id)?;
+    diff_worktree(&workspace_path, &path, &branch)
+}
+
+#[tauri::command]
+pub async fn session_worktree_merge(
+    session_id: String,
+    db: tauri::State<'_, CommentsDb>,
+    store: tauri::State<'_, AcpSessionStore>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let (session, workspace_path, path, branch) = load_isolated(&db, &session_id)?;
+    require_done(&session)?;
+    crate::acp::commands::disconnect_session(&store, &app, &session_id, "Worktree merged").await;
+
+    merge_worktree(&workspace_path, &path, &branch, &session.name)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::sessions::update_worktree(&conn, &session_id, None, None)
+}
+
+#[tauri::command]
+pub async fn session_worktree_discard(
+    session_id: String,
+    db: tauri::State<'_, CommentsDb>,
+    store: tauri::State<'_, AcpSessionStore>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let (session, workspace_path, path, branch) = load_isolated(&db, &session_id)?;
+    require_done(&session)?;
+    crate::acp::commands::disconnect_session(&store, &app, &session_id, "Worktree discarded").await;
+
+    remove_worktree(&workspace_path, &path, &branch)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::sessions::update_worktree(&conn, &session_id, None, None)
+}
@@ -1,19 +1,54 @@
 import { describe, it, expect, vi, beforeEach } from "vitest";
 import { renderHook, act } from "@testing-library/react";
 import { useComments } from "@/hooks/useComments";
+import type { Comment } from "@/types";
 
 const mockInvoke = globalThis.__TAURI__.core.invoke as ReturnType<typeof vi.fn>;
 
+// Stands in for the per-comment commands, keeping the saved comments by id
+type Args = {
+  id?: string;
+  resolved?: boolean;
+  comment?: { text: string; block_ids?: string[]; parent_id?: string };
+};
+
+let stored: Map<string, Comment>;
+let nextId: number;
+
+function backend(cmd: string, args?: Args) {
+  if (cmd === "load_comments") {
+    return Promise.resolve({ file_hash: "abc123", comments: [...stored.values()] });
+  }
+  if (cmd === "hash_file") return Promise.resolve("abc123");
+  if (cmd === "comment_add") {
+    const comment: Comment = {
+      id: `c${nextId++}`,
+      block_ids: args?.comment?.block_ids ?? [],
+      text: args?.comment?.text ?? "",
+      timestamp: Date.now(),
+      resolved: false,
+      parent_id: args?.comment?.parent_id,
+    };
+    stored.set(comment.id, comment);
+    return Promise.resolve(comment);
+  }
+  if (cmd === "comment_set_resolved") {
+    const comment = { ...stored.get(args?.id ?? "")!, resolved: !!args?.resolved };
+    stored.set(comment.id, comment);
+    return Promise.resolve(comment);
+  }
+  if (cmd === "comment_delete") {
+    stored.delete(args?.id ?? "");
+    return Promise.resolve();
+  }
+  return Promise.resolve();
+}
+
 beforeEach(() => {
+  stored = new Map();
+  nextId = 1;
   mockInvoke.mockReset();
-  mockInvoke.mockImplementation((cmd: string) => {
-    if (cmd === "load_comments") {
-      return Promise.resolve({ file_hash: "abc123", comments: [] });
-    }
-    if (cmd === "hash_file") return Promise.resolve("abc123");
-    if (cmd === "save_comments") return Promise.resolve();
-    return Promise.resolve();
-  });
+  mockInvoke.mockImplementation(backend);
 });
 
 describe("useComments", () => {
@@ -183,8 +218,8 @@ describe("useComments", () => {
       result.current.toggleBlockSelection("mkw-para-1", true);
     });
 
-    act(() => {
-      result.current.addComment("This needs revision");
+    await act(async () => {
+      await result.current.addComment("This needs revision");
     });
 
     expect(result.current.comments).toHaveLength(1);
@@ -206,8 +241,8 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("New comment");
+    await act(async () => {
+      await result.current.addComment("New comment");
     });
 
     expect(result.current.isPanelOpen).toBe(true);
@@ -220,8 +255,8 @@ describe("useComments", () => {
       await result.current.loadComments("/test/file.md");
     });
 
-    act(() => {
-      result.current.addComment("No blocks");
+    await act(async () => {
+      await result.current.addComment("No blocks");
     });
 
     expect(result.current.comments).toHaveLength(0);
@@ -237,21 +272,21 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("Fix");
+    await act(async () => {
+      await result.current.addComment("Fix");
     });
 
     const id = result.current.comments[0].id;
     expect(result.current.unresolvedCount).toBe(1);
 
-    act(() => {
-      result.current.resolveComment(id);
+    await act(async () => {
+      await result.current.resolveComment(id);
     });
     expect(result.current.comments[0].resolved).toBe(true);
     expect(result.current.unresolvedCount).toBe(0);
 
-    act(() => {
-      result.current.resolveComment(id);
+    await act(async () => {
+      await result.current.resolveComment(id);
     });
     expect(result.current.comments[0].resolved).toBe(false);
     expect(result.current.unresolvedCount).toBe(1);
@@ -267,14 +302,14 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("To delete");
+    await act(async () => {
+      await result.current.addComment("To delete");
     });
 
     const id = result.current.comments[0].id;
 
-    act(() => {
-      result.current.deleteComment(id);
+    await act(async () => {
+      await result.current.deleteComment(id);
     });
     expect(result.current.comments).toHaveLength(0);
   });
@@ -289,8 +324,8 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("First comment");
+    await act(async () => {
+      await result.current.addComment("First comment");
     });
 
     const review = result.current.generateReview();
@@ -309,13 +344,13 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("Resolved");
+    await act(async () => {
+      await result.current.addComment("Resolved");
     });
 
     const id = result.current.comments[0].id;
-    act(() => {
-      result.current.resolveComment(id);
+    await act(async () => {
+      await result.current.resolveComment(id);
     });
 
     const review = result.current.generateReview();
@@ -362,19 +397,107 @@ describe("useComments", () => {
     act(() => {
       result.current.toggleBlockSelection("mkw-para-0", false);
     });
-    act(() => {
-      result.current.addComment("Comment A");
+    await act(async () => {
+      await result.current.addComment("Comment A");
     });
 
     act(() => {
       result.current.toggleBlockSelection("mkw-para-1", false);
     });
-    act(() => {
-      result.current.addComment("Comment B");
+    await act(async () => {
+      await result.current.addComment("Comment B");
     });
 
     expect(result.current.commentsByBlock("mkw-para-0")).toHaveLength(1);
     expect(result.current.commentsByBlock("mkw-para-1")).toHaveLength(1);
     expect(result.current.commentsByBlock("mkw-para-2")).toHaveLength(0);
   });
+
+  it("saves each change through its own command", async () => {
+    const { result } = renderHook(() => useComments("ws-1"));
+
+    await act(async () => {
+      await result.current.loadComments("/test/file.md");
+    });
+
+    act(() => {
+      result.current.toggleBlockSelection("mkw-para-0", false);
+    });
+    await act(async () => {
+      await result.current.addComment("  Fix  ");
+    });
+    const id = result.current.comments[0].id;
+
+    expect(mockInvoke).toHaveBeenCalledWith("comment_add", {
+      markdownPath: "/test/file.md",
+      workspaceId: "ws-1",
+      comment: { text: "Fix", block_ids: ["mkw-para-0"] },
+    });
+
+    await act(async () => {
+      await result.current.resolveComment(id);
+    });
+    expect(mockInvoke).toHaveBeenCalledWith("comment_set_resolved", { id, resolved: true });
+
+    await act(async () => {
+      await result.current.deleteComment(id);
+    });
+    expect(mockInvoke).toHaveBeenCalledWith("comment_delete", { id });
+    expect(mockInvoke).not.toHaveBeenCalledWith("save_comments", expect.anything());
+  });
+
+  it("resolves every open thread, leaving replies alone", async () => {
+    stored.set("a", { id: "a", block_ids: ["mkw-para-0"], text: "A", timestamp: 1, resolved: false });
+    stored.set("b", { id: "b", block_ids: ["mkw-para-1"], text: "B", timestamp: 2, resolved: true });
+    stored.set("r", { id: "r", block_ids: [], text: "reply", timestamp: 3, resolved: false, parent_id: "a" });
+    const { result } = renderHook(() => useComments());
+
+    await act(async () => {
+      await result.current.loadComments("/test/file.md");
+    });
+    await act(async () => {
+      await result.current.resolveAll();
+    });
+
+    const resolveCalls = mockInvoke.mock.calls.filter(([cmd]) => cmd === "comment_set_resolved");
+    expect(resolveCalls).toEqual([["comment_set_resolved", { id: "a", resolved: true }]]);
+    expect(result.current.unresolvedCount).toBe(0);
+  });
+
+  it("keeps a reply added by the agent when the user resolves its thread", async () => {
+    stored.set("a", { id: "a", block_ids: ["mkw-para-0"], text: "A", timestamp: 1, resolved: false });
+    const { result } = renderHook(() => useComments());
+
+    await act(async () => {
+      await result.current.loadComments("/test/file.md");
+    });
+    // Posted by the backend after the comments were loaded
+    stored.set("r", { id: "r", block_ids: [], text: "done", timestamp: 2, resolved: false, parent_id: "a" });
+
+    await act(async () => {
+      await result.current.resolveComment("a");
+    });
+
+    expect(stored.has("r")).toBe(true);
+  });
+
+  it("leaves the comment in place when deleting fails", async () => {
+    stored.set("a", { id: "a", block_ids: ["mkw-para-0"], text: "A", timestamp: 1, resolved: false });
+    const { result } = renderHook(() => useComments());
+
+    await act(async () => {
+      await result.current.loadComments("/test/file.md");
+    });
+    mockInvoke.mockImplementation((cmd: string, args?: Args) =>
+      cmd === "comment_delete" ? Promise.reject("Comment not found: a") : backend(cmd, args)
+    );
+    const consoleErrorSpy = vi.spyOn(console, "error").mockImplementation(() => {});
+
+    await act(async () => {
+      await result.current.deleteComment("a");
+    });
+    consoleErrorSpy.mockRestore();
+
+    expect(result.current.comments.map((c) => c.id)).toEqual(["a"]);
+  });
 });
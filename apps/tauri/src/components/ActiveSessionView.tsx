@@ -118,10 +118,11 @@ export function ActiveSessionView({
   // Core ACP actions using new per-session commands
   const sendPrompt = useCallback(async (text: string) => {
     try {
+      const clientMessageId = crypto.randomUUID();
       if (!text.startsWith("/")) {
-        sessionMessages.addOptimisticUserMessage(text);
+        sessionMessages.addOptimisticUserMessage(text, clientMessageId);
       }
-      await invoke("acp_session_send_prompt", { sessionId: session.id, text });
+      await invoke("acp_session_send_prompt", { sessionId: session.id, text, clientMessageId });
     } catch (e) {
       setErrors((prev) => [...prev, String(e)]);
       updateSessionEntry(session.id, { isStreaming: false });
@@ -291,12 +292,17 @@ export function ActiveSessionView({
           ? `${session.name}\n\n${session.initial_prompt}`
           : session.initial_prompt;
         plan.startPlanning(acpId, prompt);
+      } else if (isNewSession && session.initial_prompt) {
+        // A session imported from an existing plan starts past planning with
+        // a seeded prompt; forks and bundle imports arrive with history
+        const count = await invoke<number>("messages_count", { sessionId: session.id });
+        if (count === 0) plan.startImportedPlan(session.initial_prompt);
       }
     } catch (e) {
       console.error("[ActiveSessionView] init error:", e);
       setErrors((prev) => [...prev, String(e)]);
     }
-  }, [sessionConn, workspacePath, session.acp_session_id, session.id, session.provider, session.phase, session.initial_prompt, session.name, plan.startPlanning]);
+  }, [sessionConn, workspacePath, session.acp_session_id, session.id, session.provider, session.phase, session.initial_prompt, session.name, plan.startPlanning, plan.startImportedPlan]);
 
   // Auto-init: only for new sessions (no acp_session_id yet).
   // Existing sessions require manual reconnection.
@@ -385,14 +391,18 @@ export function ActiveSessionView({
     }
   }, [plan.planFilePath, plan.locatePlan]);
 
-  const handlePhaseSelect = useCallback((phase: PlanPhase) => {
+  const handlePhaseSelect = useCallback(async (phase: PlanPhase) => {
+    try {
+      await invoke("session_update_phase", { id: session.id, phase, reason: "selected manually" });
+    } catch (e) {
+      // Transition not allowed from the current phase; keep the UI in sync with the backend
+      console.error(e);
+      return;
+    }
     plan.setPhase(phase);
     if (onPhaseChange) {
       onPhaseChange(session.id, phase);
     }
-    if (session.id) {
-      invoke("session_update_phase", { id: session.id, phase }).catch(console.error);
-    }
   }, [plan.setPhase, onPhaseChange, session.id]);
 
   return (
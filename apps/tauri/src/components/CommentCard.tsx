@@ -3,11 +3,12 @@ import { Button } from "@/components/ui/button";
 import { blockLabel, scrollToBlock } from "@/lib/block-utils";
 import { formatDistanceToNow } from "date-fns";
 import { getDateLocale } from "@/lib/date-locale";
-import { Check, Trash2, Undo2 } from "lucide-react";
+import { AlertTriangle, Check, Trash2, Undo2 } from "lucide-react";
 import { useTranslation } from "react-i18next";
 
 interface CommentCardProps {
   comment: Comment;
+  replies?: Comment[];
   isStale: boolean;
   onResolve: (id: string) => void;
   onDelete: (id: string) => void;
@@ -17,6 +18,7 @@ interface CommentCardProps {
 
 export function CommentCard({
   comment,
+  replies = [],
   isStale,
   onResolve,
   onDelete,
@@ -58,6 +60,15 @@ export function CommentCard({
         </span>
       </div>
 
+      {comment.ranges?.map((range) => (
+        <blockquote
+          key={range.block_id}
+          className="mb-1.5 pl-2 border-l-2 border-border text-[11px] text-muted-foreground whitespace-pre-wrap line-clamp-3"
+        >
+          {range.quote}
+        </blockquote>
+      ))}
+
       <p
         className={`text-xs leading-relaxed mb-2 ${
           comment.resolved ? "line-through" : ""
@@ -66,6 +77,24 @@ export function CommentCard({
         {comment.text}
       </p>
 
+      {(comment.anchor_status === "low_confidence" || comment.anchor_status === "orphaned") && (
+        <p className="flex items-center gap-1 mb-2 text-[10px] text-muted-foreground">
+          <AlertTriangle className="h-3 w-3 shrink-0" />
+          {comment.anchor_status === "orphaned" ? t("review.anchorOrphaned") : t("review.anchorLowConfidence")}
+        </p>
+      )}
+
+      {replies.length > 0 && (
+        <div className="mb-2 pl-2 border-l border-border space-y-1">
+          {replies.map((reply) => (
+            <p key={reply.id} className="text-xs leading-relaxed text-muted-foreground">
+              <span className="font-medium text-foreground">{reply.author ?? reply.author_kind}</span>{" "}
+              {reply.text}
+            </p>
+          ))}
+        </div>
+      )}
+
       <div className="flex items-center justify-end gap-1">
         <Button
           variant="ghost"
@@ -145,9 +145,15 @@ export function MarkdownViewer({
       }
     });
 
+    // Comments the agent addressed were resolved in the backend
+    const unlistenReview = listen("review:completed", () => {
+      review.loadComments(resolvedPath);
+    });
+
     return () => {
       invoke("unwatch_file", { path: resolvedPath }).catch(console.error);
       unlistenPromise.then((fn) => fn());
+      unlistenReview.then((fn) => fn());
     };
   }, [resolvedPath, loadContent, review.loadComments, review.refreshHash, embedded]);
 
@@ -53,7 +53,8 @@ export function ReviewPanel({
   const [hideResolved, setHideResolved] = useState(true);
 
   const isSessionMode = !!onApprovePlan;
-  const visibleComments = hideResolved ? comments.filter((c) => !c.resolved) : comments;
+  const threads = comments.filter((c) => !c.parent_id);
+  const visibleComments = hideResolved ? threads.filter((c) => !c.resolved) : threads;
 
   const handleSubmit = () => {
     if (!commentText.trim()) return;
@@ -179,6 +180,7 @@ export function ReviewPanel({
               <CommentCard
                 key={comment.id}
                 comment={comment}
+                replies={comments.filter((r) => r.parent_id === comment.id)}
                 isStale={isStale}
                 onResolve={onResolveComment}
                 onDelete={onDeleteComment}
@@ -11,28 +11,10 @@ export function useComments(workspaceId?: string) {
   const [isPanelOpen, setIsPanelOpen] = useState(false);
   const [hoveredCommentId, setHoveredCommentId] = useState<string | null>(null);
   const pathRef = useRef("");
-  const saveQueue = useRef<Promise<void>>(Promise.resolve());
 
   const isStale = savedHash !== "" && fileHash !== "" && savedHash !== fileHash;
 
-  const unresolvedCount = comments.filter((c) => !c.resolved).length;
-
-  const enqueueSave = useCallback((updatedComments: Comment[]) => {
-    saveQueue.current = saveQueue.current.then(async () => {
-      if (!pathRef.current) return;
-      const hash = await invoke<string>("hash_file", { path: pathRef.current });
-      const data: CommentsData = {
-        file_hash: hash,
-        comments: updatedComments,
-      };
-      await invoke("save_comments", {
-        markdownPath: pathRef.current,
-        workspaceId: workspaceId ?? "",
-        commentsData: data,
-      });
-      setSavedHash(hash);
-    }).catch(console.error);
-  }, [workspaceId]);
+  const unresolvedCount = comments.filter((c) => !c.resolved && !c.parent_id).length;
 
   const loadComments = useCallback(async (path: string) => {
     pathRef.current = path;
@@ -64,49 +46,69 @@ export function useComments(workspaceId?: string) {
     }
   }, []);
 
-  const addComment = useCallback((text: string) => {
+  // Each change goes through its own command, so edits made elsewhere (agent
+  // replies, re-anchoring) are never overwritten by a stale copy of the file.
+  const addComment = useCallback(async (text: string) => {
     if (selectedBlockIds.length === 0 || !text.trim()) return;
-    const comment: Comment = {
-      id: crypto.randomUUID(),
-      block_ids: [...selectedBlockIds],
-      text: text.trim(),
-      timestamp: Date.now(),
-      resolved: false,
-    };
-    setComments((prev) => {
-      const next = [...prev, comment];
-      enqueueSave(next);
-      return next;
-    });
+    const blockIds = [...selectedBlockIds];
     setSelectedBlockIds([]);
     setIsPanelOpen(true);
-  }, [selectedBlockIds, enqueueSave]);
-
-  const resolveComment = useCallback((id: string) => {
-    setComments((prev) => {
-      const next = prev.map((c) =>
-        c.id === id ? { ...c, resolved: !c.resolved } : c
-      );
-      enqueueSave(next);
-      return next;
-    });
-  }, [enqueueSave]);
+    try {
+      const comment = await invoke<Comment>("comment_add", {
+        markdownPath: pathRef.current,
+        workspaceId: workspaceId ?? "",
+        comment: { text: text.trim(), block_ids: blockIds },
+      });
+      setComments((prev) => [...prev, comment]);
+      // The first comment on a file records the content it was made on
+      setSavedHash((prev) => prev || fileHash);
+    } catch (err) {
+      console.error("Failed to add comment:", err);
+    }
+  }, [selectedBlockIds, workspaceId, fileHash]);
 
-  const resolveAll = useCallback(() => {
-    setComments((prev) => {
-      const next = prev.map((c) => (c.resolved ? c : { ...c, resolved: true }));
-      enqueueSave(next);
-      return next;
-    });
-  }, [enqueueSave]);
+  const setResolved = useCallback(async (id: string, resolved: boolean) => {
+    const updated = await invoke<Comment>("comment_set_resolved", { id, resolved });
+    setComments((prev) => prev.map((c) => (c.id === id ? updated : c)));
+  }, []);
 
-  const deleteComment = useCallback((id: string) => {
-    setComments((prev) => {
-      const next = prev.filter((c) => c.id !== id);
-      enqueueSave(next);
-      return next;
+  const resolveComment = useCallback(async (id: string) => {
+    const comment = comments.find((c) => c.id === id);
+    if (!comment) return;
+    try {
+      await setResolved(id, !comment.resolved);
+    } catch (err) {
+      console.error("Failed to resolve comment:", err);
+    }
+  }, [comments, setResolved]);
+
+  const resolveAll = useCallback(async () => {
+    const open = comments.filter((c) => !c.resolved && !c.parent_id);
+    try {
+      await Promise.all(open.map((c) => setResolved(c.id, true)));
+    } catch (err) {
+      console.error("Failed to resolve comments:", err);
+    }
+  }, [comments, setResolved]);
+
+  const addReply = useCallback(async (parentId: string, text: string) => {
+    if (!text.trim()) return;
+    const reply = await invoke<Comment>("comment_add", {
+      markdownPath: pathRef.current,
+      workspaceId: workspaceId ?? "",
+      comment: { text, parent_id: parentId },
     });
-  }, [enqueueSave]);
+    setComments((prev) => [...prev, reply]);
+  }, [workspaceId]);
+
+  const deleteComment = useCallback(async (id: string) => {
+    try {
+      await invoke("comment_delete", { id });
+      setComments((prev) => prev.filter((c) => c.id !== id && c.parent_id !== id));
+    } catch (err) {
+      console.error("Failed to delete comment:", err);
+    }
+  }, []);
 
   const toggleBlockSelection = useCallback((blockId: string, multiSelect: boolean) => {
     setSelectedBlockIds((prev) => {
@@ -138,15 +140,16 @@ export function useComments(workspaceId?: string) {
   }, []);
 
   const generateReview = useCallback((): string => {
-    const unresolved = comments.filter((c) => !c.resolved);
+    const unresolved = comments.filter((c) => !c.resolved && !c.parent_id);
     if (unresolved.length === 0) {
       return "# Plan Review\n\nNo unresolved comments. All feedback has been addressed.";
     }
 
     const sections = unresolved.map((comment, idx) => {
       const blockContents = comment.block_ids.map((blockId) => {
+        const range = comment.ranges?.find((r) => r.block_id === blockId);
         const el = document.getElementById(blockId);
-        const text = el?.textContent?.trim() || "";
+        const text = (range ? range.quote : el?.textContent)?.trim() || "";
         return text.length > 200 ? `${text.slice(0, 200)}...` : text;
       });
       const quoted = blockContents
@@ -182,6 +185,7 @@ export function useComments(workspaceId?: string) {
     addComment,
     resolveComment,
     resolveAll,
+    addReply,
     deleteComment,
     toggleBlockSelection,
     clearSelection,
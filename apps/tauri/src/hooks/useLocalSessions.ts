@@ -1,11 +1,12 @@
 import { useState, useCallback, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import type { SessionRecord } from "@/types";
+import type { PlanImportOptions, SessionRecord } from "@/types";
 
 interface UseLocalSessionsReturn {
   sessions: SessionRecord[];
   loading: boolean;
   createSession: (name: string, prompt: string, provider?: string) => Promise<SessionRecord>;
+  importPlan: (options: PlanImportOptions) => Promise<SessionRecord>;
   updateSessionLocal: (id: string, updates: Partial<SessionRecord>) => void;
   deleteSession: (id: string) => Promise<void>;
   refreshSessions: () => Promise<void>;
@@ -47,6 +48,22 @@ export function useLocalSessions(workspaceId: string): UseLocalSessionsReturn {
     [workspaceId]
   );
 
+  const importPlan = useCallback(
+    async ({ path, mode, phase, name, provider }: PlanImportOptions): Promise<SessionRecord> => {
+      const record = await invoke<SessionRecord>("session_import_plan", {
+        workspaceId,
+        path,
+        mode,
+        phase,
+        name,
+        provider: provider ?? localStorage.getItem("arandu-provider") ?? "copilot",
+      });
+      setSessions((prev) => [record, ...prev]);
+      return record;
+    },
+    [workspaceId]
+  );
+
   const updateSessionLocal = useCallback(
     (id: string, updates: Partial<SessionRecord>) => {
       setSessions((prev) =>
@@ -68,6 +85,7 @@ export function useLocalSessions(workspaceId: string): UseLocalSessionsReturn {
     sessions,
     loading,
     createSession,
+    importPlan,
     updateSessionLocal,
     deleteSession,
     refreshSessions,
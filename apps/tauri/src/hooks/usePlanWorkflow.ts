@@ -7,6 +7,7 @@ interface UsePlanWorkflowReturn {
   phase: PlanPhase;
   planFilePath: string | null;
   startPlanning: (sessionId: string, prompt: string) => Promise<void>;
+  startImportedPlan: (prompt: string) => Promise<void>;
   approvePlan: (reviewMarkdown?: string) => Promise<void>;
   requestChanges: (feedback: string) => Promise<void>;
   setPhase: (phase: PlanPhase) => void;
@@ -54,11 +55,35 @@ export function usePlanWorkflow({
   const onAutoSwitchModeRef = useRef(onAutoSwitchMode);
   onAutoSwitchModeRef.current = onAutoSwitchMode;
 
+  const phaseRef = useRef(phase);
+  phaseRef.current = phase;
+
   const setPhase = useCallback((p: PlanPhase) => {
     setPhaseRaw(p);
     onPhaseChangeRef.current?.(p);
   }, []);
 
+  // The backend rejects transitions its state machine doesn't allow and
+  // records accepted ones with the given reason. Callers only move the UI to
+  // `next` once this returns true, so it never runs ahead of the backend.
+  const persistPhase = useCallback(
+    async (next: PlanPhase, reason: string): Promise<boolean> => {
+      if (!localSessionId) return true;
+      try {
+        await invoke("session_update_phase", {
+          id: localSessionId,
+          phase: next,
+          reason,
+        });
+        return true;
+      } catch (e) {
+        console.error(e);
+        return false;
+      }
+    },
+    [localSessionId]
+  );
+
   const sendPromptRef = useRef(sendPrompt);
   sendPromptRef.current = sendPrompt;
   const setModeRef = useRef(setMode);
@@ -89,19 +114,26 @@ export function usePlanWorkflow({
         if (switched) onAutoSwitchModeRef.current?.(planMode);
       }
 
+      if (!(await persistPhase("planning", "planning started"))) return;
       setPhase("planning");
-      if (localSessionId) {
-        invoke("session_update_phase", {
-          id: localSessionId,
-          phase: "planning",
-        }).catch(console.error);
-      }
 
       await sendPromptRef.current(prompt);
     },
-    [workspaceId, localSessionId]
+    [workspaceId, persistPhase]
   );
 
+  // Imported plans are created already reviewing or executing; only the
+  // agent's mode and the seeded first prompt are left to do
+  const startImportedPlan = useCallback(async (prompt: string) => {
+    const slug = phaseRef.current === "executing" ? "agent" : "plan";
+    const mode = findModeBySlug(availableModesRef.current, slug);
+    if (mode) {
+      const switched = await setModeRef.current(mode, { origin: "workflow" });
+      if (switched) onAutoSwitchModeRef.current?.(mode);
+    }
+    await sendPromptRef.current(prompt);
+  }, []);
+
   const approvePlan = useCallback(
     async (reviewMarkdown?: string) => {
       if (!acpSessionId && !activeSessionIdRef.current) return;
@@ -112,13 +144,13 @@ export function usePlanWorkflow({
         if (switched) onAutoSwitchModeRef.current?.(agentMode);
       }
 
-      setPhase("executing");
-      if (localSessionId) {
-        invoke("session_update_phase", {
-          id: localSessionId,
-          phase: "executing",
-        }).catch(console.error);
+      // Approving straight from planning still passes through review
+      if (phaseRef.current === "planning") {
+        if (!(await persistPhase("reviewing", "plan submitted for review"))) return;
+        setPhase("reviewing");
       }
+      if (!(await persistPhase("executing", reviewMarkdown ? "plan approved with feedback" : "plan approved"))) return;
+      setPhase("executing");
 
       const prompt = reviewMarkdown
         ? `The plan has been reviewed. Here is the feedback:\n\n${reviewMarkdown}\n\nPlease proceed with executing the plan, incorporating the feedback above.`
@@ -126,25 +158,19 @@ export function usePlanWorkflow({
 
       await sendPromptRef.current(prompt);
     },
-    [workspaceId, acpSessionId, activeSessionId, localSessionId]
+    [workspaceId, acpSessionId, activeSessionId, persistPhase]
   );
 
   const requestChanges = useCallback(
     async (feedback: string) => {
+      if (!(await persistPhase("planning", "changes requested"))) return;
       setPhase("planning");
 
-      if (localSessionId) {
-        invoke("session_update_phase", {
-          id: localSessionId,
-          phase: "planning",
-        }).catch(console.error);
-      }
-
       await sendPromptRef.current(
         `Please revise the plan based on this feedback:\n\n${feedback}`
       );
     },
-    [localSessionId]
+    [persistPhase]
   );
 
   const locatePlan = useCallback(async (): Promise<string | null> => {
@@ -157,7 +183,7 @@ export function usePlanWorkflow({
           return sessionPlanFilePath;
         }
       }
-      const path = await invoke<string>("plan_path", { sessionId: localSessionId });
+      const path = await invoke<string>("plan_relocate", { sessionId: localSessionId });
       const content = await invoke<string>("plan_read", { sessionId: localSessionId });
       if (!content) return null;
       setPlanFilePath(path);
@@ -184,6 +210,7 @@ export function usePlanWorkflow({
     phase,
     planFilePath,
     startPlanning,
+    startImportedPlan,
     approvePlan,
     requestChanges,
     setPhase,
@@ -14,6 +14,13 @@ interface MessageRecord {
   tool_call_id: string | null;
   tool_title: string | null;
   tool_status: string | null;
+  tool_payload?: Record<string, unknown> | null;
+  /** Milliseconds since the epoch */
+  started_at?: number | null;
+  completed_at?: number | null;
+  stream_state?: "streaming" | "interrupted" | null;
+  /** Insert order; cursor for messages_list_before / messages_list_after */
+  seq?: number;
   created_at: number;
 }
 
@@ -26,13 +33,17 @@ function toAcpMessage(r: MessageRecord): AcpMessage {
     toolCallId: r.tool_call_id ?? undefined,
     toolTitle: r.tool_title ?? undefined,
     toolStatus: r.tool_status ?? undefined,
+    toolPayload: r.tool_payload ?? undefined,
+    toolStartedAt: r.started_at ? new Date(r.started_at) : undefined,
+    toolCompletedAt: r.completed_at ? new Date(r.completed_at) : undefined,
+    interrupted: r.stream_state === "interrupted" || undefined,
     timestamp: new Date(r.created_at * 1000),
   };
 }
 
 export interface UseSessionMessagesReturn {
   clearMessages: () => void;
-  addOptimisticUserMessage: (text: string) => void;
+  addOptimisticUserMessage: (text: string, clientMessageId?: string) => void;
   messages: AcpMessage[];
   isLoadingInitial: boolean;
   isLoadingMore: boolean;
@@ -74,9 +85,9 @@ export function useSessionMessages(sessionId: string): UseSessionMessagesReturn
     void loadInitial();
   }, [loadInitial]);
 
-  const addOptimisticUserMessage = useCallback((text: string) => {
+  const addOptimisticUserMessage = useCallback((text: string, clientMessageId?: string) => {
     const optimisticMsg: AcpMessage = {
-      id: `optimistic-${Date.now()}`,
+      id: `optimistic-${clientMessageId ?? Date.now()}`,
       role: "user",
       content: text,
       timestamp: new Date(),
@@ -89,15 +100,18 @@ export function useSessionMessages(sessionId: string): UseSessionMessagesReturn
       sessionId: string;
       id: string;
       content: string;
+      clientMessageId?: string | null;
     }>("acp:user-message-saved", (event) => {
-      const { sessionId: msgSessionId, id, content } = event.payload;
+      const { sessionId: msgSessionId, id, content, clientMessageId } = event.payload;
       console.debug("[messages] user-message-saved: session=%s id=%s", msgSessionId, id);
       if (msgSessionId !== sessionId) return;
       setMessages((prev) => {
         if (prev.some((m) => m.id === id)) return prev;
-        const idx = prev.findIndex(
-          (m) => m.id.startsWith("optimistic-") && m.role === "user" && m.content === content
-        );
+        const idx = clientMessageId
+          ? prev.findIndex((m) => m.id === `optimistic-${clientMessageId}`)
+          : prev.findIndex(
+              (m) => m.id.startsWith("optimistic-") && m.role === "user" && m.content === content
+            );
         const persisted: AcpMessage = { id, role: "user", content, timestamp: new Date() };
         if (idx >= 0) {
           const updated = [...prev];
@@ -20,6 +20,7 @@ function getBrowserInvokeFallback(command: string): InvokeResult {
     case "get_whisper_settings":
       return {};
     case "plan_path":
+    case "plan_relocate":
     case "get_initial_file":
     case "get_home_dir":
       return null;
@@ -66,6 +66,12 @@ export interface AcpMessage {
   toolCallId?: string;
   toolTitle?: string;
   toolStatus?: string;
+  /** Merged tool call payload (kind, rawInput, rawOutput, locations, content) */
+  toolPayload?: Record<string, unknown>;
+  toolStartedAt?: Date;
+  toolCompletedAt?: Date;
+  /** The answer was cut off by a crash or disconnect before it finished */
+  interrupted?: boolean;
 }
 
 export interface AcpConnectionStatusEvent {
@@ -88,3 +94,10 @@ export interface AcpConnectionLogEntry {
   message: string;
   workspaceId: string;
 }
+
+export interface AcpSessionRewoundEvent {
+  sessionId: string;
+  /** Archived session holding the conversation as it was before the edit */
+  branchSessionId: string;
+  messageId: string;
+}
@@ -37,6 +37,30 @@ export interface Comment {
   text: string;
   timestamp: number;
   resolved: boolean;
+  /** Set on replies; replies have no blocks and are resolved with their thread */
+  parent_id?: string | null;
+  /** User name, or the provider name for agent replies */
+  author?: string | null;
+  author_kind?: "user" | "agent";
+  edited_at?: number | null;
+  /** Least certain of the comment's anchors since the document last changed */
+  anchor_status?: "anchored" | "relocated" | "low_confidence" | "orphaned";
+  /** Spans within the comment's blocks; a block without one is commented whole */
+  ranges?: TextRange[];
+}
+
+/** A span of source text within one of a comment's blocks */
+export interface TextRange {
+  block_id: string;
+  /** One-based source lines, inclusive */
+  start_line: number;
+  end_line: number;
+  /** Offsets into the first and (exclusive) last line, in UTF-16 code units
+   *  like DOM selection offsets; whole lines when unset */
+  start_char?: number | null;
+  end_char?: number | null;
+  /** The text the range covered; filled in on save when empty */
+  quote: string;
 }
 
 export interface CommentsData {
@@ -53,7 +77,7 @@ export interface CardRect {
 
 export type PlanPhase = "idle" | "planning" | "reviewing" | "executing" | "done";
 
-export type AcpProvider = "copilot" | "claude";
+export type AcpProvider = "copilot" | "claude" | "openai";
 
 export interface SessionRecord {
   id: string;
@@ -65,12 +89,286 @@ export interface SessionRecord {
   plan_file_path: string | null;
   phase: PlanPhase;
   acp_preferences_json: string;
+  comparison_id: string | null;
+  worktree_path: string | null;
+  worktree_branch: string | null;
+  forked_from_session_id: string | null;
+  forked_from_message_id: string | null;
+  archived: boolean;
+  pinned: boolean;
+  tags: string[];
+  created_at: number;
+  updated_at: number;
+}
+
+export interface PhaseEvent {
+  id: number;
+  session_id: string;
+  from_phase: PlanPhase;
+  to_phase: PlanPhase;
+  reason: string | null;
+  created_at: number;
+}
+
+export type PhaseError =
+  | { kind: "unknown_phase"; phase: string }
+  | { kind: "invalid_transition"; from: PlanPhase; to: PlanPhase; allowed: PlanPhase[] }
+  | { kind: "session_not_found"; session_id: string }
+  | { kind: "storage"; message: string };
+
+export interface PlanRevision {
+  id: number;
+  session_id: string;
+  hash: string;
+  source: "write" | "file" | "import" | "fork" | "progress";
+  size: number;
+  created_at: number;
+}
+
+export interface MarkdownBlock {
+  kind: "heading" | "list_item" | "code" | "table" | "quote" | "paragraph";
+  text: string;
+  start_line: number;
+  end_line: number;
+}
+
+export interface PlanBlockChange {
+  status: "unchanged" | "added" | "removed" | "modified";
+  old: MarkdownBlock | null;
+  new: MarkdownBlock | null;
+}
+
+export interface PlanDiff {
+  from_hash: string;
+  to_hash: string;
+  changes: PlanBlockChange[];
+}
+
+export type PlanStepStatus = "pending" | "in_progress" | "done" | "blocked";
+
+export interface PlanStep {
+  id: string;
+  kind: "heading" | "task";
+  title: string;
+  depth: number;
+  line: number;
+  parent_id: string | null;
+  checked: boolean;
+  status: PlanStepStatus;
+}
+
+export interface PlanStepProgressEvent {
+  sessionId: string;
+  steps: PlanStep[];
+  changed: string[];
+}
+
+export interface PlanRun {
+  session_id: string;
+  status: "running" | "awaiting_review" | "completed" | "cancelled";
+  current_step_id: string | null;
   created_at: number;
   updated_at: number;
 }
 
+export interface PlanStepAttempt {
+  id: number;
+  step_id: string;
+  attempt: number;
+  outcome: "running" | "finished" | "failed" | "interrupted";
+  decision: "approved" | "commented" | "retried" | null;
+  comment: string | null;
+  error: string | null;
+  started_at: number;
+  finished_at: number | null;
+}
+
+export interface PlanRunState {
+  run: PlanRun | null;
+  steps: PlanStep[];
+  attempts: PlanStepAttempt[];
+}
+
+export interface CommentFeedback {
+  comment_id: string;
+  session_id: string;
+  message_id: string | null;
+  /** Ordinal of the review prompt among the session's user messages */
+  turn: number | null;
+  /** `addressed` comments were resolved by the backend.
+   *  `unknown` means the comment quoted no text to compare against. */
+  status: "sent" | "addressed" | "unchanged" | "unknown" | "failed";
+  sent_at: number;
+  addressed_at: number | null;
+}
+
+export interface ReviewOutcome {
+  sessionId: string;
+  messageId: string | null;
+  turn: number | null;
+  /** Comments whose quoted text the agent rewrote; the backend resolved them */
+  addressed: string[];
+  unchanged: string[];
+  unknown: string[];
+  /** Replies the agent posted instead of changing the plan */
+  replies: Comment[];
+}
+
+export interface PlanImportOptions {
+  path: string;
+  /** `copy` stores a copy as the session's plan; `link` edits the file in place */
+  mode: "copy" | "link";
+  phase: "reviewing" | "executing";
+  name?: string;
+  provider?: string;
+}
+
+export interface PlanStorageSettings {
+  location: "app_data" | "repo";
+  /** Relative to the workspace root; used when `location` is `repo` */
+  plans_dir: string;
+}
+
+export interface MovedPlan {
+  session_id: string;
+  from: string;
+  to: string;
+}
+
+export interface PlanMigration {
+  moved: MovedPlan[];
+  /** Sessions whose plan is stored elsewhere and was left in place */
+  skipped: string[];
+  /** Sessions whose plan could not be moved and was left in place */
+  failed: string[];
+}
+
+export interface PlanMissingEvent {
+  sessionId: string;
+  path: string;
+}
+
+export interface SessionFilters {
+  tags?: string[];
+  pinned?: boolean;
+  /** Omitted or false excludes archived sessions; true lists only archived ones */
+  archived?: boolean;
+  provider?: AcpProvider;
+  phase?: PlanPhase;
+}
+
+export type SessionSort = "updated_desc" | "created_desc" | "created_asc" | "name_asc";
+
+export interface TagCount {
+  name: string;
+  count: number;
+}
+
 export interface SessionTab {
   id: string;
   acpSessionId: string | null;
   title: string;
 }
+
+/** Workspace env profile entry; `value` is always null for secrets. */
+export interface WorkspaceEnvEntry {
+  key: string;
+  value: string | null;
+  secret: boolean;
+}
+
+export interface WorktreeDiff {
+  branch: string;
+  base: string;
+  stat: string;
+  patch: string;
+}
+
+export interface CheckpointFile {
+  path: string;
+  existed_before: boolean;
+  deleted_after: boolean;
+}
+
+export interface CheckpointRecord {
+  id: string;
+  session_id: string;
+  turn_message_id: string | null;
+  tool_call_id: string;
+  tool_title: string | null;
+  created_at: number;
+  completed_at: number | null;
+  files: CheckpointFile[];
+}
+
+export interface TurnCheckpoints {
+  turn_message_id: string | null;
+  checkpoints: CheckpointRecord[];
+}
+
+export interface RestoreResult {
+  restored: string[];
+  conflicts: string[];
+}
+
+export type DiffDecision = "pending" | "accepted" | "rejected";
+
+export interface DiffHunk {
+  index: number;
+  old_start: number;
+  new_start: number;
+  old_lines: string[];
+  new_lines: string[];
+  status: DiffDecision;
+}
+
+export interface ToolCallDiff {
+  id: string;
+  message_id: string;
+  session_id: string;
+  turn_message_id: string | null;
+  tool_call_id: string;
+  path: string;
+  old_text: string | null;
+  new_text: string;
+  status: DiffDecision | "partial";
+  hunks: DiffHunk[];
+  created_at: number;
+}
+
+export interface SearchHit {
+  session_id: string;
+  session_name: string;
+  workspace_id: string;
+  provider: AcpProvider;
+  /** null when the session name or initial prompt matched */
+  message_id: string | null;
+  role: "user" | "assistant" | null;
+  message_type: string | null;
+  /** Matching text with hits wrapped in <mark>…</mark> */
+  snippet: string;
+  rank: number;
+  created_at: number;
+}
+
+export interface SearchFilters {
+  workspace_id?: string;
+  provider?: AcpProvider;
+  /** Epoch seconds, inclusive */
+  from?: number;
+  to?: number;
+  /** Archived sessions are left out unless true */
+  include_archived?: boolean;
+}
+
+export interface SessionExportResult {
+  bundle_path: string;
+  /** Markdown transcript written next to the bundle */
+  transcript_path: string;
+}
+
+export interface MessagePage<T> {
+  /** Oldest first */
+  messages: T[];
+  has_more: boolean;
+}